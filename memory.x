@@ -1,6 +1,8 @@
 MEMORY {
     BOOT2 : ORIGIN = 0x10000000, LENGTH = 0x100
-    FLASH : ORIGIN = 0x10000100, LENGTH = 2048K - 0x100
+    /* The last 4K sector is reserved for the saved high score, */
+    /* the two below it for the round saved on pause            */
+    FLASH : ORIGIN = 0x10000100, LENGTH = 2048K - 0x100 - 12K
 
     /* Pick one of the two options for RAM layout     */
 
@@ -0,0 +1,222 @@
+// Simple snake AI, used by the attract mode on the start screen
+//
+// Breadth-first search from the food back to the head over the free cells, so
+// the snake takes a shortest path around its body and the obstacles. When no
+// food can be reached it moves to where it has the most room left. It doesn't
+// plan ahead, so it can still trap itself, which is fine for a demo or a hint.
+//
+// Everything is on the stack: a visited bitmap and a queue sized for the board.
+
+use heapless::{Deque, Vec};
+
+use crate::game::{Direction, Game, Position};
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
+
+// Largest board the search handles (40x22 on the Pico). Bigger boards fall back
+// to heading straight for the closest food.
+const MAX_CELLS: usize = 1024;
+
+// Direction the snake should take on the next tick. Never panics: when every
+// move crashes it keeps the current direction.
+pub fn next_move(game: &Game) -> Direction {
+    let Some(head) = game.body().next() else {
+        return game.direction;
+    };
+
+    // Moves that survive the next tick
+    let mut moves: Vec<(Direction, Position), 4> = Vec::new();
+    for direction in DIRECTIONS {
+        if direction == game.direction.opposite() {
+            continue;
+        }
+        if let Some(next) = game.next_position(head, direction) {
+            if !is_blocked(game, next) {
+                let _ = moves.push((direction, next));
+            }
+        }
+    }
+
+    if moves.is_empty() {
+        return game.direction;
+    }
+    if game.width() as usize * game.height() as usize > MAX_CELLS {
+        return closest_to_food(game, &moves);
+    }
+
+    if let Some(direction) = path_to_food(game, &moves) {
+        return direction;
+    }
+
+    // No path to any food: stay alive as long as possible
+    let mut best = moves[0];
+    let mut best_room = 0;
+    for &(direction, position) in &moves {
+        let mut room = 0;
+        search(game, &[position], |_| {
+            room += 1;
+            false
+        });
+        if room > best_room {
+            best = (direction, position);
+            best_room = room;
+        }
+    }
+    best.0
+}
+
+// Search outwards from every food at once. The first move cell reached is the
+// one closest to a food.
+fn path_to_food(game: &Game, moves: &[(Direction, Position)]) -> Option<Direction> {
+    let mut targets: Vec<Position, 9> = game.foods().iter().copied().collect();
+    if let Some(bonus) = game.bonus_food() {
+        let _ = targets.push(bonus);
+    }
+    // Food under the body can't be reached yet
+    targets.retain(|food| !is_blocked(game, *food));
+
+    let mut found = None;
+    search(game, &targets, |position| {
+        found = moves
+            .iter()
+            .find(|(_, next)| *next == position)
+            .map(|(direction, _)| *direction);
+        found.is_some()
+    });
+    found
+}
+
+// Breadth-first walk over the free cells from `starts`, calling `visit` on each
+// cell in order of distance until it returns true
+fn search(game: &Game, starts: &[Position], mut visit: impl FnMut(Position) -> bool) {
+    let width = game.width() as usize;
+    let mut visited = [0u32; MAX_CELLS / 32];
+    let mut queue: Deque<Position, MAX_CELLS> = Deque::new();
+
+    // Marks the cell, true when it was not visited yet
+    let mut mark = |position: Position| {
+        let index = position.y as usize * width + position.x as usize;
+        let bit = 1 << (index % 32);
+        let fresh = visited[index / 32] & bit == 0;
+        visited[index / 32] |= bit;
+        fresh
+    };
+
+    for &start in starts {
+        if mark(start) {
+            // Every cell is queued at most once, so the queue can't overflow
+            let _ = queue.push_back(start);
+        }
+    }
+
+    while let Some(position) = queue.pop_front() {
+        if visit(position) {
+            return;
+        }
+        for direction in DIRECTIONS {
+            if let Some(next) = game.next_position(position, direction) {
+                if !is_blocked(game, next) && mark(next) {
+                    let _ = queue.push_back(next);
+                }
+            }
+        }
+    }
+}
+
+// Snakes, obstacles and poison. The tail leaves its cell on the next tick, so it is free.
+fn is_blocked(game: &Game, position: Position) -> bool {
+    let tail = game.body().next_back();
+    (game.is_occupied(position) && tail != Some(position)) || game.poison_food() == Some(position)
+}
+
+// Greedy choice for boards too big to search
+fn closest_to_food(game: &Game, moves: &[(Direction, Position)]) -> Direction {
+    let mut best = moves[0].0;
+    let mut best_distance = u16::MAX;
+    for &(direction, position) in moves {
+        let distance = food_distance(game, position);
+        if distance < best_distance {
+            best = direction;
+            best_distance = distance;
+        }
+    }
+    best
+}
+
+// Manhattan distance to the closest food, bonus included
+fn food_distance(game: &Game, from: Position) -> u16 {
+    game.foods()
+        .iter()
+        .copied()
+        .chain(game.bonus_food())
+        .map(|food| from.x.abs_diff(food.x) as u16 + from.y.abs_diff(food.y) as u16)
+        .min()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playing() -> Game {
+        let mut game: Game = Game::new(20, 12);
+        game.start();
+        game
+    }
+
+    #[test]
+    fn heads_for_the_food() {
+        let mut game = playing();
+        game.set_food(Position::new(15, 6));
+        assert_eq!(next_move(&game), Direction::Right);
+        game.set_food(Position::new(10, 2));
+        assert_eq!(next_move(&game), Direction::Up);
+        game.set_food(Position::new(10, 9));
+        assert_eq!(next_move(&game), Direction::Down);
+    }
+
+    #[test]
+    fn goes_around_walls_and_poison() {
+        let mut game = playing();
+        game.set_food(Position::new(14, 6));
+        for y in 0..=8 {
+            game.add_obstacle(Position::new(11, y));
+        }
+        // The gap in the wall is below
+        assert_eq!(next_move(&game), Direction::Down);
+
+        let mut game = playing();
+        game.set_food(Position::new(12, 6));
+        game.set_poison_food(Position::new(11, 6));
+        assert_ne!(next_move(&game), Direction::Right);
+    }
+
+    #[test]
+    fn turns_away_from_a_wall_with_no_food_in_reach() {
+        let mut game = playing();
+        for _ in 0..9 {
+            game.step(None);
+        }
+        assert_eq!(game.head(), Position::new(19, 6));
+        for y in 0..12 {
+            game.add_obstacle(Position::new(0, y));
+        }
+        game.set_food(Position::new(0, 0)); // Under a wall
+        assert_ne!(next_move(&game), Direction::Right);
+    }
+
+    #[test]
+    fn plays_a_round_and_eats() {
+        let mut game = playing();
+        while game.is_playing() && game.move_count() < 500 {
+            let direction = next_move(&game);
+            game.step(Some(direction));
+        }
+        assert!(game.food_eaten >= 10, "ate {}", game.food_eaten);
+    }
+}
@@ -0,0 +1,162 @@
+// Display self-test, shared by the screen test binary and the game firmware
+//
+// Works on any embedded-graphics target with RGB565 pixels, straight on the
+// driver rather than through `GameDisplay`, so it also checks what the game's
+// abstraction doesn't use. Every draw error is returned instead of unwrapped:
+// a failed SPI write shows up as an `Err` the caller can log.
+
+use core::fmt::Write;
+
+use embedded_graphics::{
+    mono_font::{ascii::FONT_6X10, MonoTextStyle},
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{Circle, PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+};
+use heapless::String;
+
+use crate::traits::GamePlatform;
+
+// How long each still screen stays up
+const STEP_MS: u32 = 1000;
+const HOLD_MS: u32 = 2000;
+
+// Animation: frames of the moving circle and the time between them
+const ANIMATION_FRAMES: i32 = 30;
+const FRAME_MS: u32 = 100;
+
+/// Run the display test: full-screen red, green and blue fills, a few shapes,
+/// then text with the screen size. With `animate` a circle moves across the
+/// screen for three seconds at the end. `delay` only provides the pauses.
+pub async fn run_display_test<D>(
+    display: &mut D,
+    delay: &impl GamePlatform,
+    animate: bool,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = Rgb565>,
+{
+    let area = display.bounding_box();
+
+    // Test 1: Fill screen with colors
+    for color in [Rgb565::RED, Rgb565::GREEN, Rgb565::BLUE] {
+        display.fill_solid(&area, color)?;
+        delay.delay_ms(STEP_MS).await;
+    }
+
+    // Test 2: Draw shapes
+    display.fill_solid(&area, Rgb565::BLACK)?;
+    draw_shapes(display)?;
+    delay.delay_ms(HOLD_MS).await;
+
+    // Test 3: Text display
+    display.fill_solid(&area, Rgb565::BLACK)?;
+    let text_style = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+    let mut size: String<24> = String::new();
+    write!(
+        &mut size,
+        "Display: {}x{}",
+        area.size.width, area.size.height
+    )
+    .ok();
+    for (text, y) in [
+        ("Screen Test", 20),
+        (size.as_str(), 40),
+        ("All systems OK!", 60),
+    ] {
+        Text::with_baseline(text, Point::new(10, y), text_style, Baseline::Top).draw(display)?;
+    }
+    delay.delay_ms(HOLD_MS).await;
+
+    // Test 4: Animated pattern
+    if animate {
+        let width = area.size.width as i32;
+        for i in 0..ANIMATION_FRAMES {
+            display.fill_solid(&area, Rgb565::BLACK)?;
+
+            // Moving circle
+            let x = (i * 8) % (width - 20).max(1);
+            let y = 50 + ((i * 2) % 20);
+            Circle::new(Point::new(x, y), 10)
+                .into_styled(PrimitiveStyle::with_fill(Rgb565::CYAN))
+                .draw(display)?;
+
+            // Static text
+            Text::with_baseline(
+                "Animation Test",
+                Point::new(10, 10),
+                text_style,
+                Baseline::Top,
+            )
+            .draw(display)?;
+
+            delay.delay_ms(FRAME_MS).await;
+        }
+    }
+
+    Ok(())
+}
+
+// Bars from left to right: white and black for the extremes, the primaries
+// and the secondaries in between, ordered by brightness like SMPTE bars
+const COLOR_BARS: [Rgb565; 8] = [
+    Rgb565::WHITE,
+    Rgb565::YELLOW,
+    Rgb565::CYAN,
+    Rgb565::GREEN,
+    Rgb565::MAGENTA,
+    Rgb565::RED,
+    Rgb565::BLUE,
+    Rgb565::BLACK,
+];
+
+/// Fill the screen with vertical color bars, to check the panel's colors and
+/// that the window is aligned: the first and last bar must touch the edges.
+/// Bar widths come from the display size, the leftover pixels are spread over
+/// the bars so they cover the full width.
+pub fn draw_color_bars<D>(display: &mut D) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = Rgb565>,
+{
+    let area = display.bounding_box();
+    let count = COLOR_BARS.len() as u32;
+    for (i, color) in COLOR_BARS.into_iter().enumerate() {
+        let left = area.size.width * i as u32 / count;
+        let right = area.size.width * (i as u32 + 1) / count;
+        let bar = Rectangle::new(
+            area.top_left + Point::new(left as i32, 0),
+            Size::new(right - left, area.size.height),
+        );
+        display.fill_solid(&bar, color)?;
+    }
+    Ok(())
+}
+
+// Rectangles and circles in every primary, anything off screen is clipped
+fn draw_shapes<D>(display: &mut D) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = Rgb565>,
+{
+    let rects = [
+        (Point::new(10, 10), Size::new(50, 30), Rgb565::RED),
+        (Point::new(70, 20), Size::new(40, 40), Rgb565::GREEN),
+        (Point::new(120, 15), Size::new(30, 50), Rgb565::BLUE),
+    ];
+    for (corner, size, color) in rects {
+        Rectangle::new(corner, size)
+            .into_styled(PrimitiveStyle::with_fill(color))
+            .draw(display)?;
+    }
+
+    let circles = [
+        (Point::new(170, 10), 25, Rgb565::YELLOW),
+        (Point::new(200, 40), 20, Rgb565::MAGENTA),
+    ];
+    for (corner, diameter, color) in circles {
+        Circle::new(corner, diameter)
+            .into_styled(PrimitiveStyle::with_fill(color))
+            .draw(display)?;
+    }
+    Ok(())
+}
@@ -1,101 +1,1924 @@
-use crate::game::{Game, GameState};
-use crate::traits::{GameInput, GamePlatform, GameRenderer, InputEvent};
+use embassy_futures::select::{select, Either};
+use heapless::Vec;
 
-pub struct GameEngine<I, P, R>
+use crate::ai;
+use crate::game::{
+    Difficulty, Direction, Game, GameConfig, GameState, Position, RestartSeed, MAX_FOOD,
+    SNAKE_CAPACITY,
+};
+use crate::levels::{LevelData, LevelError};
+use crate::melody::{MelodyPlayer, Note, GAME_OVER_JINGLE, START_FANFARE};
+use crate::replay::Recorder;
+use crate::traits::{
+    insert_score, GameAudio, GameBacklight, GameInput, GamePlatform, GameRenderer, GameStats,
+    GameStorage, InputEvent, Leaderboard, ScoreEntry,
+};
+
+// Sound effects (frequency Hz, duration ms), longer tunes are in `melody`
+const EAT_BEEP: (u16, u16) = (1760, 40);
+
+// Backlight levels (percent), dimmed on screens that don't need attention
+const FULL_BRIGHTNESS: u8 = 100;
+const DIM_BRIGHTNESS: u8 = 20;
+const SLEEP_BRIGHTNESS: u8 = 5;
+
+// Longest the loop sleeps on an idle screen, it still wakes at once on a press
+const IDLE_WAKE_MS: u32 = 500;
+
+// How long the reset question waits for an answer before the round carries on
+const CONFIRM_RESET_MS: u32 = 3000;
+
+// Countdown shown before each round, one number every ~800 ms
+const COUNTDOWN_FROM: u8 = 3;
+const COUNTDOWN_STEP_MS: u32 = 800;
+
+// Game milestones, logged through defmt on the Pico and passed to the event
+// handler when one is set
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub enum GameEvent {
+    Started,
+    Paused,
+    Resumed,
+    Ate { score: u16 },
+    Died { score: u16, length: u16 },
+    Won { score: u16 },
+}
+
+// What one `GameEngine::step` did
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepResult {
+    pub inputs: u8, // Input events handled, not counting a press that only woke the screen
+    pub state: GameState, // Game state at the end of the frame
+    pub moved: bool, // The game took a step
+    pub rendered: bool, // The frame drew without a display error
+}
+
+// Signing a new leaderboard entry: up and down cycle the letter under the
+// cursor through A to Z, button A confirms it and moves to the next one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InitialsEntry {
+    pub rank: usize, // Leaderboard entry being signed
+    pub initials: [u8; 3],
+    pub cursor: usize, // Letter being picked, 3 once all are confirmed
+}
+
+impl InitialsEntry {
+    pub fn new(rank: usize) -> Self {
+        Self {
+            rank,
+            initials: *b"AAA",
+            cursor: 0,
+        }
+    }
+
+    // Z wraps around to A
+    pub fn next_letter(&mut self) {
+        self.cycle(1);
+    }
+
+    // A wraps around to Z
+    pub fn previous_letter(&mut self) {
+        self.cycle(25);
+    }
+
+    // Confirm the letter under the cursor, true once all three are
+    pub fn confirm(&mut self) -> bool {
+        self.cursor = (self.cursor + 1).min(self.initials.len());
+        self.done()
+    }
+
+    pub fn done(&self) -> bool {
+        self.cursor >= self.initials.len()
+    }
+
+    fn cycle(&mut self, step: u8) {
+        if let Some(letter) = self.initials.get_mut(self.cursor) {
+            *letter = b'A' + (letter.saturating_sub(b'A') + step) % 26;
+        }
+    }
+}
+
+// Boost energy, in steps' worth: a full tank lasts 20 boosted steps and takes
+// 100 normal steps to refill. Run dry, the boost stays off until a quarter is back.
+pub const BOOST_MAX_ENERGY: u16 = 100;
+const BOOST_DRAIN: u16 = 5;
+const BOOST_RECHARGE: u16 = 1;
+const BOOST_READY: u16 = 25;
+
+// Holding A during play doubles the snake's speed while the energy lasts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Boost {
+    energy: u16,
+    held: bool,
+    exhausted: bool, // Ran dry, waiting for `BOOST_READY` energy
+}
+
+impl Boost {
+    pub fn new() -> Self {
+        Self {
+            energy: BOOST_MAX_ENERGY,
+            held: false,
+            exhausted: false,
+        }
+    }
+
+    // Button state for the coming steps, releasing it drops back to normal speed
+    pub fn hold(&mut self, held: bool) {
+        self.held = held;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.held && !self.exhausted && self.energy > 0
+    }
+
+    // One game step: drain while boosting, recharge otherwise
+    pub fn step(&mut self) {
+        if self.is_active() {
+            self.energy = self.energy.saturating_sub(BOOST_DRAIN);
+            self.exhausted = self.energy == 0;
+        } else {
+            self.energy = (self.energy + BOOST_RECHARGE).min(BOOST_MAX_ENERGY);
+            if self.energy >= BOOST_READY {
+                self.exhausted = false;
+            }
+        }
+    }
+
+    // 0 to `BOOST_MAX_ENERGY`, for a gauge
+    pub fn energy(&self) -> u16 {
+        self.energy
+    }
+}
+
+impl Default for Boost {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct GameEngine<I, P, R, A, S, B>
 where
     I: GameInput,
     P: GamePlatform,
     R: GameRenderer,
+    A: GameAudio,
+    S: GameStorage,
+    B: GameBacklight,
 {
     input: I,
     platform: P,
     renderer: R,
+    audio: Option<A>,
+    muted: bool, // Sound off, saved to storage
+    melody: MelodyPlayer,
+    storage: Option<S>,
+    backlight: Option<B>,
+    brightness: Option<u8>, // Last level sent to the backlight
+    high_score: u16,
+    scores: Leaderboard, // Best finished rounds, kept in memory like the high score
+    unsigned: Option<usize>, // Entry of the last round, until its initials are entered
+    initials: Option<InitialsEntry>, // Initials being picked, shown over the end screen
+    confirm_reset: Option<u32>, // When A during play asked whether to reset, the board is frozen
+    round_saved: bool,   // Storage holds a paused round, cleared when the round is over
+    recorder: Recorder,  // Turns of the player's last round
     game: Game,
-    target_frame_time_ms: u32,
+    target_frame_time_ms: u32,         // Frame time at score 0
+    min_frame_time_ms: u32,            // Fastest the game can get
+    speed_step_ms: u32,                // Frame time removed per food worth of score
+    difficulty: Difficulty,            // Last picked on the menu, scales the pace above
+    boost: Option<Boost>,              // Hold A to speed up, `None` when turned off
+    poll_interval_ms: u32,             // Input/render loop period, faster than the game steps
+    countdown_from: u8,                // 0 starts rounds without a countdown
+    countdown_step_ms: u32,            // Time each number stays on screen
+    seeded: bool,                      // RNG got seeded from the clock on a first start
+    game_over_timeout_ms: Option<u32>, // Back to the start screen when idle this long
+    game_over_at: Option<u32>,         // When the round ended, cleared by any input
+    attract_after_ms: Option<u32>,     // Idle time on the start screen before the demo plays
+    idle_since: u32,                   // Last input, or when the start screen came up
+    demo: bool,                        // The AI is playing the current round
+    idle_sleep_after_ms: Option<u32>,  // Quiet time on a static screen before the loop sleeps
+    quiet_since: u32,                  // Last input or screen change
+    quiet_state: GameState,            // Screen `quiet_since` was last reset for
+    asleep: Option<(u32, u32)>,        // When the loop went to sleep, and wakes since
+    screensaver_after_ms: Option<u32>, // Quiet time on an end screen before the screensaver
+    screensaver: bool,                 // The screensaver is up instead of the end screen
+    on_event: Option<fn(GameEvent)>,
+    bordered: Option<GameState>,   // State the border was last drawn for
+    show_fps: bool,                // Draw the loop rate in a corner
+    battery_every_ms: Option<u32>, // How often to read the battery, `None` never does
+    battery_read_at: Option<u32>,  // Time of the last reading
+    fps_since: u32,                // Start of the current frame rate measurement
+    fps_frames: u16,               // Frames counted since then
+    fps: u16,                      // Frames per second over the last measurement
+    timed_state: GameState,        // State the round clock last saw
+    round_started: Option<u32>,    // When the snake started moving this round
+    round_ended: Option<u32>,      // When the round was lost or won
+    paused_since: Option<u32>,     // Start of the current pause
+    paused_ms: u32,                // Time spent paused this round, before the current pause
+    longest_streak: u8,            // Best combo this round
+    prev_snake: Vec<Position, SNAKE_CAPACITY>, // Board as of the last rendered frame
+    prev_foods: Vec<Position, MAX_FOOD>,
+    last_step: u32,      // Deadline the last game step (or countdown number) ran for
+    next_frame: u32,     // Deadline of the frame being run
+    clock_started: bool, // The two above are set, by `run` or the first `step`
 }
 
-impl<I, P, R> GameEngine<I, P, R>
+// Score worth one speed step (one regular food)
+const SCORE_PER_SPEED_STEP: u16 = 10;
+
+impl<I, P, R, A, S, B> GameEngine<I, P, R, A, S, B>
 where
     I: GameInput,
     P: GamePlatform,
     R: GameRenderer,
+    A: GameAudio,
+    S: GameStorage,
+    B: GameBacklight,
 {
     pub fn new(input: I, platform: P, renderer: R, grid_width: u8, grid_height: u8) -> Self {
         Self {
             input,
             platform,
             renderer,
+            audio: None,
+            muted: false,
+            melody: MelodyPlayer::new(),
+            storage: None,
+            backlight: None,
+            brightness: None,
+            high_score: 0,
+            scores: Leaderboard::new(),
+            unsigned: None,
+            initials: None,
+            confirm_reset: None,
+            round_saved: false,
+            recorder: Recorder::new(),
             game: Game::new(grid_width, grid_height),
             target_frame_time_ms: 150, // Default to ~7 FPS
+            min_frame_time_ms: 60,
+            speed_step_ms: 3,
+            difficulty: Difficulty::Normal,
+            boost: None,
+            poll_interval_ms: 30, // ~33 FPS input polling and animations
+            countdown_from: COUNTDOWN_FROM,
+            countdown_step_ms: COUNTDOWN_STEP_MS,
+            seeded: false,
+            game_over_timeout_ms: None,
+            game_over_at: None,
+            attract_after_ms: None,
+            idle_sleep_after_ms: None,
+            quiet_since: 0,
+            quiet_state: GameState::Starting,
+            asleep: None,
+            screensaver_after_ms: None,
+            screensaver: false,
+            idle_since: 0,
+            demo: false,
+            on_event: None,
+            bordered: None,
+            show_fps: false,
+            battery_every_ms: None,
+            battery_read_at: None,
+            fps_since: 0,
+            fps_frames: 0,
+            fps: 0,
+            timed_state: GameState::Starting,
+            round_started: None,
+            round_ended: None,
+            paused_since: None,
+            paused_ms: 0,
+            longest_streak: 0,
+            prev_snake: Vec::new(),
+            prev_foods: Vec::new(),
+            last_step: 0,
+            next_frame: 0,
+            clock_started: false,
         }
     }
 
-    #[allow(dead_code)]
+    pub fn with_audio(mut self, audio: A) -> Self {
+        self.audio = Some(audio);
+        self
+    }
+
+    pub fn with_storage(mut self, mut storage: S) -> Self {
+        // A failed read just means starting from zero
+        self.high_score = storage.load_high_score().unwrap_or(0);
+        self.scores = storage.load_scores().unwrap_or_default();
+        self.renderer.set_leaderboard(&self.scores, None);
+        self.muted = storage.load_muted().unwrap_or(false);
+        self.renderer.set_muted(self.muted);
+        self.storage = Some(storage);
+        self
+    }
+
+    pub fn with_backlight(mut self, backlight: B) -> Self {
+        self.backlight = Some(backlight);
+        self
+    }
+
+    // Get every `GameEvent` of real rounds (the attract demo is not reported)
+    pub fn with_event_handler(mut self, on_event: fn(GameEvent)) -> Self {
+        self.on_event = Some(on_event);
+        self
+    }
+
     pub fn set_frame_rate(&mut self, fps: u32) {
         self.target_frame_time_ms = 1000 / fps;
     }
 
+    // Speed up by `step_ms` per food eaten, never going below `min_frame_time_ms`.
+    // A step of 0 keeps a constant speed.
+    pub fn set_speed_curve(&mut self, min_frame_time_ms: u32, step_ms: u32) {
+        self.min_frame_time_ms = min_frame_time_ms;
+        self.speed_step_ms = step_ms;
+    }
+
+    // Count down from `from` before each round, one number every `step_ms`.
+    // A count of 0 starts the snake as soon as B is pressed.
+    pub fn set_countdown(&mut self, from: u8, step_ms: u32) {
+        self.countdown_from = from;
+        self.countdown_step_ms = step_ms;
+    }
+
+    // Return to the start screen on its own once a round has ended and nothing
+    // was pressed for `timeout_ms` (kiosk/demo setups). `None` waits forever.
+    // The time counts from the end of the round, so it should be longer than
+    // the ~5 s game over animation.
+    pub fn set_game_over_timeout(&mut self, timeout_ms: Option<u32>) {
+        self.game_over_timeout_ms = timeout_ms;
+    }
+
+    // Attract mode: after `idle_ms` on the start screen with no input, an AI
+    // snake plays until something is pressed. `None` keeps the menu static.
+    pub fn set_attract_mode(&mut self, idle_ms: Option<u32>) {
+        self.attract_after_ms = idle_ms;
+    }
+
+    // Low-power idle: after `quiet_ms` on the start or end screen with no
+    // input, the loop sleeps until a press (or a few times a second) and the
+    // backlight goes down. Needs an input that implements `wait_for_input`.
+    pub fn set_idle_sleep(&mut self, quiet_ms: Option<u32>) {
+        self.idle_sleep_after_ms = quiet_ms;
+    }
+
+    // Screensaver: after `quiet_ms` on an end screen with no input, a logo
+    // bounces around a blank screen until a press, which only dismisses it.
+    // Keeps the end screen from burning into OLED panels.
+    pub fn set_screensaver(&mut self, quiet_ms: Option<u32>) {
+        self.screensaver_after_ms = quiet_ms;
+    }
+
+    // Boost: holding A during play doubles the speed until the energy runs
+    // out. Holding A no longer abandons a round then, A+B still does, and a
+    // tap of A doesn't ask to reset either: releasing a short hold reads as a
+    // tap. Needs an input that reports `button_a_held`, and stays off in two
+    // player rounds.
+    pub fn set_boost(&mut self, enabled: bool) {
+        self.boost = enabled.then(Boost::new);
+    }
+
+    // Stats of the current (or last finished) round
+    pub fn stats(&self) -> GameStats {
+        GameStats {
+            food_eaten: self.game.food_eaten,
+            moves: self.game.move_count(),
+            elapsed_ms: self.game_elapsed_ms(),
+            longest_streak: self.longest_streak,
+        }
+    }
+
+    // Energy left for the boost (0 to `BOOST_MAX_ENERGY`), `None` when it's off
+    pub fn boost_energy(&self) -> Option<u16> {
+        self.boost.map(|boost| boost.energy())
+    }
+
+    // Gameplay rules from `config`: scoring, food count and placement, poison,
+    // hunger, sudden death and self collision. The board size and seed stay the
+    // engine's, the pace is `set_frame_rate` and `set_speed_curve`'s and
+    // wrapping comes with the difficulty.
+    pub fn apply_config(&mut self, config: &GameConfig) {
+        self.game.set_scoring(config.scoring);
+        self.game.set_food_count(config.food_count);
+        self.game.set_wall_bias(config.wall_bias);
+        self.game.set_food_lookahead(config.food_lookahead);
+        self.game.set_poison(config.poison);
+        self.game.set_hunger_ticks(config.hunger_ticks);
+        self.game.set_sudden_death(config.sudden_death_ticks);
+        self.game.set_self_collision(config.self_collision);
+    }
+
+    // Swap the board's walls for a maze from `levels.rs` (or a custom one),
+    // kept for every round after. Clears the portals.
+    pub fn load_level(&mut self, level: &LevelData) -> Result<(), LevelError> {
+        self.game.load_level(level)
+    }
+
+    // Bring back the round paused before a reset or power loss, still paused.
+    // Call it last in the setup: loading a level starts a new round, and the
+    // round is restored into the board, walls and rules set up by then. It
+    // brings back its difficulty and the time played so far. One that doesn't
+    // fit is cleared on the first frame. True when it came back.
+    pub fn resume_saved_round(&mut self) -> bool {
+        let saved = self.storage.as_mut().map(|storage| storage.load_snapshot());
+        let Some(Ok(Some(snapshot))) = saved else {
+            return false;
+        };
+        self.round_saved = true;
+        let (difficulty, played_ms) = (snapshot.difficulty, snapshot.played_ms);
+        if self.game.restore(snapshot).is_err() {
+            return false;
+        }
+        self.difficulty = difficulty;
+        self.game
+            .set_wrap_walls(self.difficulty_config().wrap_walls);
+
+        // The round clock carries on from the time played, paused
+        let now = self.platform.current_time_ms();
+        self.timed_state = GameState::Paused;
+        self.round_started = Some(now.wrapping_sub(played_ms));
+        self.round_ended = None;
+        self.paused_since = Some(now);
+        self.paused_ms = 0;
+        true
+    }
+
+    // Link two board cells, see `Game::add_portal`. Kept for every round after.
+    pub fn add_portal(&mut self, a: Position, b: Position) {
+        self.game.add_portal(a, b);
+    }
+
+    // Fresh rounds (the default) reseed the food from the time B was pressed,
+    // preserved ones replay the food of the first round
+    pub fn set_restart_seed(&mut self, mode: RestartSeed) {
+        self.game.set_restart_seed(mode);
+    }
+
+    // Two snakes: player one on the joystick, player two turning left and
+    // right with A and B. Pausing is left out while two play, a long A press
+    // still abandons the round.
+    pub fn set_two_player(&mut self, enabled: bool) {
+        self.game.set_two_player(enabled);
+    }
+
+    // Overlay the loop's frame rate, to watch the frame budget while tuning
+    pub fn set_show_fps(&mut self, show: bool) {
+        self.show_fps = show;
+    }
+
+    // Battery indicator in the status bar, read from the platform every
+    // `every_ms`. `None` (the default) leaves it out, for mains powered builds.
+    pub fn set_battery_check(&mut self, every_ms: Option<u32>) {
+        self.battery_every_ms = every_ms;
+        self.battery_read_at = None;
+    }
+
+    // Frame time for the current score
+    pub fn current_frame_time(&self) -> u32 {
+        let config = self.difficulty_config();
+        let steps = (self.game.score / SCORE_PER_SPEED_STEP) as u32;
+        let floor = self.min_frame_time_ms.min(config.frame_time_ms);
+        let frame_time = config
+            .frame_time_ms
+            .saturating_sub(steps.saturating_mul(config.speed_step_ms))
+            .max(floor);
+        if self.boost.is_some_and(|boost| boost.is_active()) {
+            frame_time / 2
+        } else {
+            frame_time
+        }
+    }
+
+    // The configured pace and walls, adjusted for the picked difficulty
+    pub fn difficulty_config(&self) -> GameConfig {
+        self.difficulty.config(GameConfig {
+            width: self.game.width(),
+            height: self.game.height(),
+            wrap_walls: self.game.wrap_walls(),
+            frame_time_ms: self.target_frame_time_ms,
+            speed_step_ms: self.speed_step_ms,
+            ..GameConfig::default()
+        })
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
     pub async fn run(&mut self) -> Result<(), ()> {
+        self.start_clock();
         loop {
-            let frame_start = self.platform.current_time_ms();
+            self.step().await;
+        }
+    }
 
-            // Handle input
-            match self.input.read_input().await {
-                Ok(InputEvent::Direction(dir)) => {
-                    if self.game.state == GameState::Playing {
-                        self.game.set_direction(dir);
-                    }
+    // Fixed timestep: frames and game steps are scheduled from the previous
+    // deadline rather than from when they happened to run, so time spent
+    // rendering doesn't slow the snake down. Both start from now, as do the
+    // idle timers.
+    fn start_clock(&mut self) {
+        let now = self.platform.current_time_ms();
+        self.last_step = now;
+        self.next_frame = now;
+        self.idle_since = now;
+        self.quiet_since = now;
+        self.fps_since = now;
+        self.clock_started = true;
+    }
+
+    // One frame of `run`: input, a game step when one is due, sound and
+    // drawing, then the wait for the next frame. Tests can call it directly,
+    // the first call starts the clock.
+    pub async fn step(&mut self) -> StepResult {
+        if !self.clock_started {
+            self.start_clock();
+        }
+
+        // A frame stuck in the display or storage stops feeding it, and the
+        // watchdog resets the device
+        self.platform.feed_watchdog();
+        let frame_start = self.platform.current_time_ms();
+
+        // Handle everything read since the last frame without waiting on the
+        // input. Unreadable input is skipped, the next frame polls again.
+        let mut inputs = self.input.poll_input().unwrap_or_default();
+        if !inputs.is_empty() {
+            // Someone is there, don't restart on them
+            self.game_over_at = None;
+            self.idle_since = frame_start;
+            if self.demo {
+                // The press only ends the demo, B has to be pressed again to play
+                self.stop_demo(frame_start);
+                inputs.clear();
+            }
+            if self.screensaver {
+                // Same for the screensaver, the end screen comes back first
+                self.quiet_since = frame_start;
+                inputs.clear();
+            }
+        }
+        let had_input = !inputs.is_empty();
+        let handled = inputs.len() as u8;
+        for input in inputs {
+            if self.handle_input(input) {
+                self.last_step = frame_start;
+            }
+        }
+        if had_input || self.game.state != self.quiet_state {
+            self.quiet_since = frame_start;
+            self.quiet_state = self.game.state;
+        }
+        self.update_screensaver(frame_start);
+
+        if self.game.state != GameState::Starting && !self.demo {
+            self.idle_since = frame_start;
+        } else if self.should_start_demo(frame_start) {
+            self.demo = true;
+            self.game.start();
+            self.last_step = frame_start;
+        }
+
+        if self
+            .confirm_reset
+            .is_some_and(|asked| frame_start.wrapping_sub(asked) >= CONFIRM_RESET_MS)
+        {
+            // No answer: carry on where the round froze
+            self.confirm_reset = None;
+            self.last_step = frame_start;
+        }
+
+        if let Some(boost) = self.boost.as_mut() {
+            // Player two steers with A, so two player rounds go without
+            boost.hold(
+                self.game.is_playing()
+                    && self.confirm_reset.is_none()
+                    && !self.demo
+                    && self.game.second_snake().is_none()
+                    && self.input.button_a_held(),
+            );
+        }
+
+        // Step game logic at the current game speed
+        let since_step = frame_start.wrapping_sub(self.last_step);
+        let mut stepped = false;
+        if self.game.is_counting_down() && since_step >= self.countdown_step_ms {
+            // The snake gets a full step after the last number before moving
+            self.last_step = frame_start;
+            self.game.count_down();
+        } else if self.game.is_playing()
+            && self.confirm_reset.is_none()
+            && since_step >= self.current_frame_time()
+        {
+            // Keep the remainder for the next step, unless a slow frame put us
+            // more than a whole step behind: then drop the lost time instead
+            // of rushing the snake to catch up
+            let step = self.current_frame_time();
+            self.last_step = if since_step < 2 * step {
+                self.last_step.wrapping_add(step)
+            } else {
+                frame_start
+            };
+            stepped = true;
+            if self.demo {
+                self.game.set_direction(ai::next_move(&self.game));
+            }
+            let score_before = self.game.score;
+            self.game.update();
+            if let Some(boost) = self.boost.as_mut() {
+                boost.step();
+            }
+            if !self.demo {
+                self.recorder.record(&self.game);
+                self.longest_streak = self.longest_streak.max(self.game.combo());
+            }
+
+            if self.demo {
+                // Silent, and the demo's score doesn't count
+                if !self.game.is_playing() {
+                    self.stop_demo(frame_start);
                 }
-                Ok(InputEvent::ButtonA) => {
-                    if self.game.state == GameState::GameOver {
-                        self.game.reset();
+            } else if self.game.score > score_before {
+                self.beep(EAT_BEEP);
+                self.emit(GameEvent::Ate {
+                    score: self.game.score,
+                });
+            } else if self.game.game_over() {
+                self.play_melody(GAME_OVER_JINGLE);
+            }
+
+            if !self.demo && !self.game.is_playing() {
+                // The round just ended (lost or won)
+                self.record_high_score();
+                self.record_score();
+                self.renderer.render_stats(&self.stats()).ok();
+                self.game_over_at = Some(frame_start);
+                self.emit(if self.game.won() {
+                    GameEvent::Won {
+                        score: self.game.score,
+                    }
+                } else {
+                    GameEvent::Died {
+                        score: self.game.score,
+                        length: self.game.snake.len() as u16,
+                    }
+                });
+            }
+        }
+        self.check_game_over_timeout(frame_start);
+        self.track_round_time(frame_start);
+        self.clear_saved_round();
+        self.check_battery(frame_start);
+        self.update_sleep(frame_start);
+
+        if let Some(audio) = self.audio.as_mut() {
+            self.melody.update(frame_start, audio);
+            audio.update().ok();
+        }
+        self.update_backlight();
+
+        // Moves are sent as deltas, anything else renders the full state every
+        // frame so the renderer can animate. The game carries on after a failed
+        // frame, the renderer redraws the screen on the next one.
+        let drawn = if stepped && self.game.is_playing() {
+            self.renderer
+                .render_delta(&self.game, &self.prev_snake, &self.prev_foods)
+                .is_ok()
+        } else if self.confirm_reset.is_some() {
+            self.renderer.render_confirm_reset(&self.game).is_ok()
+        } else if self.screensaver {
+            self.renderer.render_screensaver(frame_start).is_ok()
+        } else if let Some(entry) = self.initials {
+            let score = self.scores.get(entry.rank).map_or(0, |entry| entry.score);
+            self.renderer
+                .render_initials(score, &entry.initials, entry.cursor)
+                .is_ok()
+        } else {
+            self.render_screen(frame_start)
+        };
+        let mut rendered = drawn;
+        if !drawn {
+            self.frame_failed();
+        }
+        // Screens are cleared when the state changes, draw the border back on top
+        if self.bordered != Some(self.game.state) {
+            self.bordered = Some(self.game.state);
+            if self.renderer.render_border().is_err() {
+                rendered = false;
+                self.frame_failed();
+            }
+        }
+        if self.show_fps {
+            self.count_frame(frame_start);
+            if self.renderer.render_fps(self.fps).is_err() {
+                rendered = false;
+                self.frame_failed();
+            }
+        }
+        self.prev_snake = self.game.snake.clone();
+        self.prev_foods = self.game.foods().iter().copied().collect();
+
+        // Frame timing: wait for the next tick of the poll clock. A frame that
+        // overran skips the ticks it missed rather than running them back to back.
+        self.next_frame = self.next_frame.wrapping_add(self.poll_interval_ms);
+        let now = self.platform.current_time_ms();
+        if self.asleep.is_some() && self.sleep_until_input().await {
+            self.next_frame = self.platform.current_time_ms();
+        } else if (now.wrapping_sub(self.next_frame) as i32) >= 0 {
+            self.next_frame = now;
+        } else {
+            self.platform.delay_until_ms(self.next_frame).await;
+        }
+
+        StepResult {
+            inputs: handled,
+            state: self.game.state,
+            moved: stepped,
+            rendered,
+        }
+    }
+
+    // A resets to the start screen, B starts and pauses. During the countdown
+    // only a reset is taken. True when the step clock should restart.
+    fn handle_input(&mut self, input: InputEvent) -> bool {
+        if self.initials.is_some() {
+            self.enter_initials(input);
+            return false;
+        }
+        if self.confirm_reset.is_some() {
+            return self.answer_reset(input);
+        }
+        match input {
+            InputEvent::Direction(dir) => match self.game.state {
+                GameState::Playing => self.game.set_direction(dir),
+                GameState::DifficultyMenu(selected) => match dir {
+                    Direction::Up => self.game.open_difficulty_menu(selected.previous()),
+                    Direction::Down => self.game.open_difficulty_menu(selected.next()),
+                    Direction::Left | Direction::Right => {}
+                },
+                _ => {}
+            },
+            // Player two steers with the buttons: A turns left, B turns right
+            InputEvent::ButtonA | InputEvent::ButtonB
+                if self.game.is_playing() && self.game.second_snake().is_some() =>
+            {
+                self.game.turn_second(matches!(input, InputEvent::ButtonB));
+            }
+            InputEvent::ButtonA => match self.game.state {
+                // A new leaderboard entry gets signed on the way out
+                GameState::GameOver | GameState::Won => match self.unsigned.take() {
+                    Some(rank) => {
+                        self.initials = Some(InitialsEntry::new(rank));
+                        self.bordered = None; // The entry screen clears the border
                     }
+                    None => self.game.reset(),
+                },
+                // A opens the difficulty menu and closes it again unchanged
+                GameState::Starting => self.game.open_difficulty_menu(self.difficulty),
+                GameState::DifficultyMenu(_) => self.game.close_difficulty_menu(),
+                GameState::Paused => self.toggle_mute(),
+                // A tap only asks whether to reset, abandoning a round takes a
+                // long press or A+B so a stray touch can't throw a game away.
+                // With the boost on the tap is the end of a hold, so it's left be.
+                GameState::Playing if !self.demo && self.boost.is_none() => {
+                    self.confirm_reset = Some(self.platform.current_time_ms());
                 }
-                Ok(InputEvent::ButtonB) => {
-                    // Reserved for future use (pause, menu, etc.)
+                GameState::Countdown(_) | GameState::Playing => {}
+            },
+            // Holding A is the boost when it's on
+            InputEvent::ButtonALong if self.boost.is_some() && self.game.is_playing() => {}
+            InputEvent::ButtonALong | InputEvent::ButtonAB => match self.game.state {
+                GameState::Starting => {}
+                GameState::DifficultyMenu(_) => self.game.close_difficulty_menu(),
+                GameState::Countdown(_)
+                | GameState::Playing
+                | GameState::Paused
+                | GameState::GameOver
+                | GameState::Won => self.game.reset(),
+            },
+            InputEvent::ButtonB => match self.game.state {
+                GameState::Starting => {
+                    self.start_game();
+                    return true;
                 }
-                Ok(InputEvent::None) => {}
-                Err(_) => {
-                    // Handle input error gracefully
-                    continue;
+                // B takes the highlighted difficulty and starts right away
+                GameState::DifficultyMenu(selected) => {
+                    self.difficulty = selected;
+                    self.game
+                        .set_wrap_walls(self.difficulty_config().wrap_walls);
+                    self.game.close_difficulty_menu();
+                    self.start_game();
+                    return true;
                 }
+                GameState::Playing | GameState::Paused => {
+                    self.game.toggle_pause();
+                    if self.game.is_paused() {
+                        self.save_round();
+                    }
+                    self.emit(if self.game.is_paused() {
+                        GameEvent::Paused
+                    } else {
+                        GameEvent::Resumed
+                    });
+                    return true;
+                }
+                GameState::Countdown(_) | GameState::GameOver | GameState::Won => {}
+            },
+            InputEvent::None => {}
+        }
+        false
+    }
+
+    // The reset question: holding A (or A+B) throws the round away, any other
+    // press takes the question back. True when the round carries on, so the
+    // step clock restarts.
+    fn answer_reset(&mut self, input: InputEvent) -> bool {
+        match input {
+            InputEvent::None => false,
+            InputEvent::ButtonALong | InputEvent::ButtonAB => {
+                self.confirm_reset = None;
+                self.game.reset();
+                false
             }
+            _ => {
+                self.confirm_reset = None;
+                true
+            }
+        }
+    }
 
-            // Update game logic
-            if self.game.state == GameState::Playing {
-                self.game.update();
+    // Up and down pick the letter, A confirms it. Once the third is confirmed
+    // the entry is saved and the start screen comes back, a long press or A+B
+    // leaves the entry unsigned.
+    fn enter_initials(&mut self, input: InputEvent) {
+        let Some(entry) = self.initials.as_mut() else {
+            return;
+        };
+        match input {
+            InputEvent::Direction(Direction::Up) => entry.next_letter(),
+            InputEvent::Direction(Direction::Down) => entry.previous_letter(),
+            InputEvent::ButtonA if entry.confirm() => {
+                let entry = *entry;
+                if let Some(signed) = self.scores.get_mut(entry.rank) {
+                    signed.initials = entry.initials;
+                    if let Some(storage) = self.storage.as_mut() {
+                        storage.save_scores(&self.scores).ok();
+                    }
+                }
+                self.renderer
+                    .set_leaderboard(&self.scores, Some(entry.rank));
+                self.initials = None;
+                self.game.reset();
+            }
+            InputEvent::ButtonALong | InputEvent::ButtonAB => {
+                self.initials = None;
+                self.game.reset();
             }
+            _ => {}
+        }
+    }
 
-            // Render game
-            if let Err(_) = self.renderer.render_game(
-                &self.game.snake,
-                &self.game.food,
-                self.game.score,
-                self.game.state,
-                self.game.width(),
-                self.game.height(),
-            ) {
-                // Handle render error by continuing
-                continue;
+    fn start_game(&mut self) {
+        // Seed the food layout from the time of the first start. Fresh rounds
+        // after it mix in their own start time, so they don't follow each other.
+        let now = self.platform.current_time_ms();
+        match self.game.restart_seed() {
+            _ if !self.seeded => self.game.reseed(now),
+            RestartSeed::Fresh => self.game.reseed(self.game.seed() ^ now),
+            RestartSeed::Preserve => {}
+        }
+        self.seeded = true;
+        self.recorder.start(&self.game);
+        self.game.start_countdown(self.countdown_from);
+        if let Some(boost) = self.boost.as_mut() {
+            *boost = Boost::new();
+        }
+        self.longest_streak = 0;
+        self.play_melody(START_FANFARE);
+        self.emit(GameEvent::Started);
+    }
+
+    fn emit(&self, event: GameEvent) {
+        // Only the Pico has a defmt logger
+        #[cfg(target_os = "none")]
+        defmt::info!("game event: {}", event);
+
+        if let Some(on_event) = self.on_event {
+            on_event(event);
+        }
+    }
+
+    fn should_start_demo(&self, now: u32) -> bool {
+        let Some(idle_ms) = self.attract_after_ms else {
+            return false;
+        };
+        !self.demo
+            && self.game.state == GameState::Starting
+            && now.wrapping_sub(self.idle_since) >= idle_ms
+    }
+
+    // Back to the start screen, the demo starts again after another idle period
+    fn stop_demo(&mut self, now: u32) {
+        self.demo = false;
+        self.idle_since = now;
+        self.game.reset();
+    }
+
+    fn check_game_over_timeout(&mut self, now: u32) {
+        let (Some(timeout), Some(ended)) = (self.game_over_timeout_ms, self.game_over_at) else {
+            return;
+        };
+        if !matches!(self.game.state, GameState::GameOver | GameState::Won) {
+            self.game_over_at = None;
+        } else if now.wrapping_sub(ended) >= timeout {
+            // An entry left half signed keeps its placeholder initials
+            self.game_over_at = None;
+            self.unsigned = None;
+            self.initials = None;
+            self.game.reset();
+        }
+    }
+
+    // Follow the state changes for `game_elapsed_ms`: a round's clock starts
+    // when the snake first moves, stops while paused and when the round ends.
+    // Changes are seen once per frame, so times are rounded to the poll period.
+    fn track_round_time(&mut self, now: u32) {
+        let state = self.game.state;
+        if state == self.timed_state {
+            return;
+        }
+        match (self.timed_state, state) {
+            (GameState::Paused, GameState::Playing) => {
+                let since = self.paused_since.take().unwrap_or(now);
+                self.paused_ms = self.paused_ms.wrapping_add(now.wrapping_sub(since));
+            }
+            (_, GameState::Playing) => {
+                self.round_started = Some(now);
+                self.round_ended = None;
+                self.paused_since = None;
+                self.paused_ms = 0;
+            }
+            (_, GameState::Paused) => self.paused_since = Some(now),
+            (GameState::Playing | GameState::Paused, GameState::GameOver | GameState::Won) => {
+                self.round_ended = Some(now);
+            }
+            (_, GameState::Starting | GameState::Countdown(_)) => self.round_started = None,
+            _ => {}
+        }
+        self.timed_state = state;
+    }
+
+    // Time the current (or last finished) round has been played, in ms.
+    // Pauses and the countdown don't count, so timers built on this can't be
+    // dodged by pausing. 0 before the first round starts.
+    pub fn game_elapsed_ms(&self) -> u32 {
+        let Some(started) = self.round_started else {
+            return 0;
+        };
+        let now = self
+            .paused_since
+            .or(self.round_ended)
+            .unwrap_or_else(|| self.platform.current_time_ms());
+        now.wrapping_sub(started).wrapping_sub(self.paused_ms)
+    }
+
+    // Menus get their own renderer hooks, the board states go through render_game.
+    // False when the frame failed to draw.
+    fn render_screen(&mut self, now: u32) -> bool {
+        let result = match self.game.state {
+            GameState::Starting => self.renderer.render_menu(now),
+            GameState::DifficultyMenu(selected) => self.renderer.render_difficulty(selected),
+            GameState::Countdown(n) => self.renderer.render_countdown(n),
+            GameState::Paused => self
+                .renderer
+                .render_pause(self.game.score, self.game.food_eaten),
+            GameState::Playing | GameState::GameOver | GameState::Won => {
+                self.renderer.render_game(&self.game)
             }
+        };
+        result.is_ok()
+    }
+
+    // Frames started over the last second or more, updated about once a second
+    // so the readout stays legible
+    fn count_frame(&mut self, now: u32) {
+        self.fps_frames = self.fps_frames.saturating_add(1);
+        let elapsed = now.wrapping_sub(self.fps_since);
+        if elapsed >= 1000 {
+            self.fps = (self.fps_frames as u32 * 1000 / elapsed) as u16;
+            self.fps_frames = 0;
+            self.fps_since = now;
+        }
+    }
+
+    // Log and move on. The redrawn screen loses the border, so it goes back on
+    // after the next frame.
+    fn frame_failed(&mut self) {
+        self.bordered = None;
+
+        #[cfg(target_os = "none")]
+        defmt::warn!("frame failed to draw, redrawing it");
+    }
+
+    // Dim the screen while paused or on game over to save power
+    fn update_backlight(&mut self) {
+        let level = match self.game.state {
+            _ if self.asleep.is_some() => SLEEP_BRIGHTNESS,
+            GameState::GameOver if self.initials.is_some() => FULL_BRIGHTNESS,
+            GameState::Paused | GameState::GameOver => DIM_BRIGHTNESS,
+            GameState::Starting
+            | GameState::DifficultyMenu(_)
+            | GameState::Countdown(_)
+            | GameState::Playing
+            | GameState::Won => FULL_BRIGHTNESS,
+        };
+        if self.brightness == Some(level) {
+            return;
+        }
+
+        if let Some(backlight) = self.backlight.as_mut() {
+            backlight.set_brightness(level);
+            self.brightness = Some(level);
+        }
+    }
+
+    // Sound on or off, from the pause screen. The setting is saved right away
+    // so it survives a reboot.
+    pub fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+        if self.muted {
+            self.melody.stop();
+        }
+        self.renderer.set_muted(self.muted);
+        if let Some(storage) = self.storage.as_mut() {
+            // Keep the setting for this session even if it can't be persisted
+            storage.save_muted(self.muted).ok();
+        }
+    }
+
+    pub fn muted(&self) -> bool {
+        self.muted
+    }
+
+    pub fn audio(&self) -> Option<&A> {
+        self.audio.as_ref()
+    }
+
+    // Play a tune over the next frames, cutting off any that is still playing
+    pub fn play_melody(&mut self, notes: &'static [Note]) {
+        if self.muted || self.audio.is_none() {
+            return;
+        }
+        self.melody.play(notes, self.platform.current_time_ms());
+    }
+
+    fn beep(&mut self, (freq_hz, dur_ms): (u16, u16)) {
+        if self.muted {
+            return;
+        }
+        if let Some(audio) = self.audio.as_mut() {
+            // Sound is cosmetic, ignore failures
+            audio.beep(freq_hz, dur_ms).ok();
+        }
+    }
+
+    // Static screens only: the death animation and blinking are over long
+    // before the quiet time, and the demo or a name entry keep the loop awake
+    fn update_screensaver(&mut self, now: u32) {
+        let due = self.screensaver_after_ms.is_some_and(|quiet_ms| {
+            !self.demo
+                && self.initials.is_none()
+                && matches!(self.game.state, GameState::GameOver | GameState::Won)
+                && now.wrapping_sub(self.quiet_since) >= quiet_ms
+        });
+        if self.screensaver && !due {
+            self.bordered = None; // The end screen comes back without its border
+        }
+        self.screensaver = due;
+    }
+
+    fn should_sleep(&self, now: u32) -> bool {
+        let Some(quiet_ms) = self.idle_sleep_after_ms else {
+            return false;
+        };
+        !self.demo
+            && self.initials.is_none()
+            && matches!(
+                self.game.state,
+                GameState::Starting | GameState::GameOver | GameState::Won
+            )
+            && now.wrapping_sub(self.quiet_since) >= quiet_ms
+    }
 
-            // Frame timing
-            let frame_time = self.platform.current_time_ms() - frame_start;
-            if frame_time < self.target_frame_time_ms {
-                self.platform
-                    .delay_ms(self.target_frame_time_ms - frame_time)
-                    .await;
+    fn update_sleep(&mut self, now: u32) {
+        let sleepy = self.should_sleep(now);
+        match self.asleep {
+            None if sleepy => self.asleep = Some((now, 0)),
+            Some((since, wakes)) if !sleepy => {
+                self.asleep = None;
+                // Only the Pico has a defmt logger, awake the loop runs ~33 times a second
+                #[cfg(target_os = "none")]
+                defmt::info!(
+                    "idle sleep: woke {} times in {} ms",
+                    wakes,
+                    now.wrapping_sub(since)
+                );
+                #[cfg(not(target_os = "none"))]
+                let _ = (since, wakes);
             }
+            _ => {}
         }
     }
 
-    #[allow(dead_code)]
+    // Wait for a press or the idle tick, whichever comes first. False when the
+    // input can't wait, the caller then keeps the normal frame rate.
+    async fn sleep_until_input(&mut self) -> bool {
+        if let Some((_, wakes)) = self.asleep.as_mut() {
+            *wakes += 1;
+        }
+        let woke = select(
+            self.input.wait_for_input(),
+            self.platform.delay_ms(IDLE_WAKE_MS),
+        )
+        .await;
+        !matches!(woke, Either::First(false))
+    }
+
+    // The charge moves slowly and an ADC read isn't free, so only sample it now and then
+    fn check_battery(&mut self, now: u32) {
+        let Some(every_ms) = self.battery_every_ms else {
+            return;
+        };
+        if self
+            .battery_read_at
+            .is_some_and(|at| now.wrapping_sub(at) < every_ms)
+        {
+            return;
+        }
+        self.battery_read_at = Some(now);
+        let percent = self.platform.read_battery_percent().min(100);
+        self.renderer.set_battery(percent);
+    }
+
+    // Each pause keeps the round in storage, to carry on after a reset or power
+    // loss. The demo's rounds aren't kept.
+    fn save_round(&mut self) {
+        if self.demo {
+            return;
+        }
+        let mut snapshot = self.game.snapshot();
+        snapshot.difficulty = self.difficulty;
+        snapshot.played_ms = self.game_elapsed_ms();
+        if let Some(storage) = self.storage.as_mut() {
+            // A round that can't be saved just isn't resumed
+            storage.save_snapshot(Some(&snapshot)).ok();
+            self.round_saved = true;
+        }
+    }
+
+    // The saved round goes once it ended or was abandoned
+    fn clear_saved_round(&mut self) {
+        let in_round = matches!(
+            self.game.state,
+            GameState::Countdown(_) | GameState::Playing | GameState::Paused
+        );
+        if !self.round_saved || in_round {
+            return;
+        }
+        if let Some(storage) = self.storage.as_mut() {
+            storage.save_snapshot(None).ok();
+        }
+        self.round_saved = false;
+    }
+
+    fn record_high_score(&mut self) {
+        if self.game.score <= self.high_score {
+            return;
+        }
+
+        self.high_score = self.game.score;
+        if let Some(storage) = self.storage.as_mut() {
+            // Keep the score in memory even if it can't be persisted
+            storage.save_high_score(self.high_score).ok();
+        }
+    }
+
+    // Leaderboard entry for the round that just ended, the renderer is told
+    // either way so it doesn't highlight the previous round's entry
+    fn record_score(&mut self) {
+        let latest = insert_score(&mut self.scores, ScoreEntry::new(self.game.score));
+        self.renderer.set_leaderboard(&self.scores, latest);
+        self.unsigned = latest;
+        if latest.is_none() {
+            return;
+        }
+
+        if let Some(storage) = self.storage.as_mut() {
+            // Keep the scores in memory even if they can't be persisted
+            storage.save_scores(&self.scores).ok();
+        }
+    }
+
+    pub fn scores(&self) -> &[ScoreEntry] {
+        &self.scores
+    }
+
+    pub fn high_score(&self) -> u16 {
+        self.high_score
+    }
+
     pub fn game(&self) -> &Game {
         &self.game
     }
 
-    #[allow(dead_code)]
     pub fn game_mut(&mut self) -> &mut Game {
         &mut self.game
     }
+
+    // Seed and turns of the last round the player started, to play it again
+    // with `Game::replay`. A round restored from storage isn't recorded.
+    pub fn recording(&self) -> &Recorder {
+        &self.recorder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::future::Future;
+
+    use super::*;
+    use crate::mock::{MockAudio, MockDisplay, MockInput, MockPlatform, MockStorage, Shape};
+    use crate::renderer::GridRenderer;
+    use crate::snapshot::GameSnapshot;
+    use crate::traits::{Color, FixedBacklight};
+
+    type TestEngine<'a> = GameEngine<
+        MockInput<'a>,
+        &'a MockPlatform,
+        GridRenderer<MockDisplay<64>>,
+        MockAudio,
+        MockStorage,
+        FixedBacklight,
+    >;
+
+    fn engine<'a>(clock: &'a MockPlatform, script: &[(u32, InputEvent)]) -> TestEngine<'a> {
+        let renderer = GridRenderer::new(MockDisplay::new(240, 135), 6);
+        let mut engine = GameEngine::new(MockInput::new(clock, script), clock, renderer, 20, 12);
+        engine.set_countdown(0, 0);
+        engine
+    }
+
+    // Run frames until the clock reaches `ms`
+    fn run_until(engine: &mut TestEngine, clock: &MockPlatform, ms: u32) {
+        while clock.current_time_ms() < ms {
+            embassy_futures::block_on(engine.step());
+        }
+    }
+
+    #[test]
+    fn a_paused_round_comes_back_after_a_reset() {
+        let clock = MockPlatform::new(0);
+        let script = [(100, InputEvent::ButtonB), (700, InputEvent::ButtonB)];
+        let mut first = engine(&clock, &script).with_storage(MockStorage::new());
+        run_until(&mut first, &clock, 800);
+        assert!(first.game().is_paused());
+        let snapshot = first.game().snapshot();
+        let storage = first.storage.take().unwrap();
+        let saved = storage.snapshot.clone().unwrap();
+        assert_eq!(saved.played_ms, first.game_elapsed_ms());
+        assert_eq!(
+            GameSnapshot {
+                played_ms: 0,
+                ..saved
+            },
+            snapshot
+        );
+
+        // Power comes back: the round is there, paused, and B carries on with it
+        let clock = MockPlatform::new(0);
+        let mut engine = engine(&clock, &[(100, InputEvent::ButtonB)]).with_storage(storage);
+        assert!(engine.resume_saved_round());
+        assert!(engine.game().is_paused());
+        assert_eq!(engine.game().snapshot(), snapshot);
+        run_until(&mut engine, &clock, 200);
+        assert!(engine.game().is_playing());
+        assert!(engine.storage.as_ref().unwrap().snapshot.is_some());
+
+        // Once the snake hits the wall the saved round is gone
+        run_until(&mut engine, &clock, 10_000);
+        assert!(engine.game().game_over());
+        assert_eq!(engine.storage.as_ref().unwrap().snapshot, None);
+    }
+
+    #[test]
+    fn a_resumed_round_keeps_its_difficulty_and_clock() {
+        let clock = MockPlatform::new(0);
+        let script = [
+            (0, InputEvent::ButtonA),
+            (50, InputEvent::Direction(Direction::Up)),
+            (100, InputEvent::ButtonB),
+            (1000, InputEvent::ButtonB),
+        ];
+        let mut first = engine(&clock, &script).with_storage(MockStorage::new());
+        run_until(&mut first, &clock, 1100);
+        assert!(first.game().is_paused() && first.game().wrap_walls());
+        let played = first.game_elapsed_ms();
+        assert!(played >= 850, "{played}");
+        let storage = first.storage.take().unwrap();
+
+        // An Easy round comes back wrapping, its clock going on from before
+        let clock = MockPlatform::new(0);
+        let mut rebooted = engine(&clock, &[(300, InputEvent::ButtonB)]).with_storage(storage);
+        assert!(rebooted.resume_saved_round());
+        assert_eq!(rebooted.difficulty(), Difficulty::Easy);
+        assert!(rebooted.game().wrap_walls());
+        run_until(&mut rebooted, &clock, 250);
+        assert_eq!(rebooted.game_elapsed_ms(), played);
+        run_until(&mut rebooted, &clock, 600);
+        assert!(rebooted.game().is_playing());
+        let elapsed = rebooted.game_elapsed_ms();
+        assert!(
+            (played + 250..=played + 330).contains(&elapsed),
+            "{elapsed}"
+        );
+    }
+
+    #[test]
+    fn a_saved_round_comes_back_in_its_level() {
+        // A wall at (8, 5), the snake starting on (11, 5)
+        let level = LevelData {
+            name: "Post",
+            width: 4,
+            height: 1,
+            cells: b"1000",
+            spawn: Position::new(3, 0),
+        };
+        let clock = MockPlatform::new(0);
+        let script = [(100, InputEvent::ButtonB), (400, InputEvent::ButtonB)];
+        let mut first = engine(&clock, &script).with_storage(MockStorage::new());
+        first.load_level(&level).unwrap();
+        assert!(!first.resume_saved_round());
+        run_until(&mut first, &clock, 500);
+        assert!(first.game().is_paused());
+        let snapshot = first.game().snapshot();
+        let storage = first.storage.take().unwrap();
+
+        // Loading the level after the round came back would start over
+        let clock = MockPlatform::new(0);
+        let mut rebooted = engine(&clock, &[]).with_storage(storage);
+        rebooted.load_level(&level).unwrap();
+        assert!(rebooted.resume_saved_round());
+        assert!(rebooted.game().is_paused());
+        assert_eq!(rebooted.game().snapshot(), snapshot);
+        assert_eq!(rebooted.game().obstacles(), &[Position::new(8, 5)]);
+    }
+
+    #[test]
+    fn the_config_reaches_the_game() {
+        let clock = MockPlatform::new(0);
+        let mut engine = engine(&clock, &[]);
+        engine.apply_config(&GameConfig {
+            food_count: 3,
+            hunger_ticks: 40,
+            sudden_death_ticks: 20,
+            wall_bias: 128,
+            food_lookahead: 2,
+            self_collision: false,
+            poison: true,
+            ..GameConfig::default()
+        });
+
+        let game = engine.game();
+        assert_eq!((game.width(), game.height()), (20, 12));
+        assert_eq!(game.foods().len(), 3);
+        assert_eq!(game.hunger_ticks(), 40);
+        assert_eq!(game.sudden_death(), 20);
+        assert_eq!(game.wall_bias(), 128);
+        assert_eq!(game.food_lookahead(), 2);
+        assert!(!game.self_collision());
+        assert!(game.poison());
+    }
+
+    fn frame(engine: &mut TestEngine) -> StepResult {
+        engine.renderer.display_mut().reset();
+        embassy_futures::block_on(engine.step())
+    }
+
+    #[test]
+    fn a_frame_handles_input_then_moves_then_draws() {
+        let clock = MockPlatform::new(0);
+        let script = [
+            (0, InputEvent::ButtonB),
+            (200, InputEvent::Direction(Direction::Up)),
+        ];
+        let mut engine = engine(&clock, &script);
+        let started = frame(&mut engine);
+        assert_eq!(
+            started,
+            StepResult {
+                inputs: 1,
+                state: GameState::Playing,
+                moved: false,
+                rendered: true,
+            }
+        );
+        assert!(!engine.renderer.display().shapes().is_empty()); // The board
+
+        // Frames between steps draw nothing, the step is a small delta
+        let mut result = frame(&mut engine);
+        while !result.moved {
+            assert!(engine.renderer.display().shapes().is_empty());
+            result = frame(&mut engine);
+        }
+        assert_eq!(engine.game().snake[0], Position::new(11, 6));
+        let shapes = engine.renderer.display().shapes();
+        assert!(shapes.len() < 8);
+        assert!(matches!(
+            shapes[0],
+            Shape::Rect { color, .. } if color == Color::BLACK
+        )); // The tail's old cell
+
+        // The turn read on a frame is taken on the next step
+        let mut result = frame(&mut engine);
+        while result.inputs == 0 {
+            assert!(!result.moved);
+            result = frame(&mut engine);
+        }
+        assert!(!result.moved);
+        while !frame(&mut engine).moved {}
+        assert_eq!(engine.game().snake[0], Position::new(11, 5));
+    }
+
+    #[test]
+    fn every_event_of_a_poll_is_handled() {
+        let clock = MockPlatform::new(0);
+        let script = [
+            (0, InputEvent::ButtonB),
+            (60, InputEvent::Direction(Direction::Up)),
+            (60, InputEvent::Direction(Direction::Left)),
+            (60, InputEvent::Direction(Direction::Up)),
+            (60, InputEvent::Direction(Direction::Right)),
+        ];
+        let mut engine = engine(&clock, &script);
+        frame(&mut engine);
+        frame(&mut engine);
+        let poll = frame(&mut engine);
+        assert_eq!(poll.inputs, 3); // A full poll, the last event waits
+        assert_eq!(frame(&mut engine).inputs, 1);
+
+        // The turns were queued, one per step
+        for head in [(10, 5), (9, 5), (9, 4), (10, 4)] {
+            while !frame(&mut engine).moved {
+                assert!(clock.current_time_ms() < 1000);
+            }
+            assert_eq!(engine.game().head(), Position::new(head.0, head.1));
+        }
+    }
+
+    #[test]
+    fn the_round_clock_skips_the_countdown_and_pauses() {
+        let clock = MockPlatform::new(0);
+        let script = [
+            (0, InputEvent::ButtonB),
+            (3000, InputEvent::ButtonB),
+            (8000, InputEvent::ButtonB),
+        ];
+        let mut engine = engine(&clock, &script);
+        engine.set_countdown(3, 800);
+        engine.game_mut().set_wrap_walls(true); // Keep the snake alive
+        run_until(&mut engine, &clock, 2000);
+        assert!(engine.game().is_counting_down());
+        assert_eq!(engine.game_elapsed_ms(), 0);
+
+        // Moving from 2400 to 3000, paused until 8000, then a second more
+        run_until(&mut engine, &clock, 7000);
+        assert!(engine.game().is_paused());
+        let paused = engine.game_elapsed_ms();
+        assert!(paused.abs_diff(600) <= 60, "{paused} ms");
+        run_until(&mut engine, &clock, 9000);
+        let elapsed = engine.game_elapsed_ms();
+        assert!(elapsed.abs_diff(1600) <= 60, "{elapsed} ms");
+    }
+
+    #[test]
+    fn a_paused_frame_does_not_move() {
+        let clock = MockPlatform::new(0);
+        let script = [(0, InputEvent::ButtonB), (30, InputEvent::ButtonB)];
+        let mut engine = engine(&clock, &script);
+        frame(&mut engine);
+        let paused = frame(&mut engine);
+        assert_eq!((paused.inputs, paused.state), (1, GameState::Paused));
+        for _ in 0..20 {
+            let result = frame(&mut engine);
+            assert!(!result.moved && result.rendered);
+        }
+        assert_eq!(engine.game().snake[0], Position::new(10, 6));
+    }
+
+    #[test]
+    fn the_snake_speeds_up_as_the_score_rises() {
+        let clock = MockPlatform::new(0);
+        let mut engine = engine(&clock, &[(0, InputEvent::ButtonB)]);
+        engine.set_speed_curve(60, 10);
+        assert_eq!(engine.current_frame_time(), 150);
+        engine.game_mut().score = 30; // Three foods' worth
+        assert_eq!(engine.current_frame_time(), 120);
+        engine.game_mut().score = 500;
+        assert_eq!(engine.current_frame_time(), 60); // The floor
+
+        // The loop steps at that pace: five steps in 300 ms, where the
+        // starting pace makes two
+        frame(&mut engine);
+        engine.game_mut().score = 90;
+        run_until(&mut engine, &clock, 330);
+        assert_eq!(engine.game().move_count(), 5);
+    }
+
+    #[test]
+    fn the_demo_plays_when_idle_and_a_press_stops_it() {
+        let clock = MockPlatform::new(0);
+        let mut engine = engine(&clock, &[(3000, InputEvent::ButtonB)]);
+        engine.set_attract_mode(Some(1000));
+        run_until(&mut engine, &clock, 950);
+        assert_eq!(engine.game().state, GameState::Starting);
+        run_until(&mut engine, &clock, 1050);
+        assert!(engine.demo && engine.game().is_playing());
+
+        // The AI keeps the snake alive, and its score doesn't count
+        run_until(&mut engine, &clock, 2950);
+        assert!(engine.demo && engine.game().is_playing());
+        assert!(engine.game().move_count() > 10);
+
+        // B only ends the demo, it takes another press to play
+        run_until(&mut engine, &clock, 3050);
+        assert!(!engine.demo);
+        assert_eq!(engine.game().state, GameState::Starting);
+        assert_eq!(engine.high_score(), 0);
+        assert!(engine.scores().is_empty());
+    }
+
+    #[test]
+    fn finished_rounds_go_on_the_leaderboard() {
+        let clock = MockPlatform::new(0);
+        let mut storage = MockStorage::new();
+        storage.scores = [70, 20].into_iter().map(ScoreEntry::new).collect();
+        let mut engine = engine(&clock, &[(0, InputEvent::ButtonB)]).with_storage(storage);
+        assert_eq!(engine.scores().len(), 2);
+
+        frame(&mut engine);
+        engine.game_mut().score = 40;
+        run_until(&mut engine, &clock, 3000);
+        assert!(engine.game().game_over());
+        let scores: std::vec::Vec<u16> = engine.scores().iter().map(|entry| entry.score).collect();
+        assert_eq!(scores, [70, 40, 20]);
+        assert_eq!(engine.unsigned, Some(1));
+        assert_eq!(
+            engine.storage.as_ref().unwrap().scores.as_slice(),
+            engine.scores()
+        );
+    }
+
+    #[test]
+    fn initials_letters_cycle_and_wrap() {
+        let mut entry = InitialsEntry::new(2);
+        entry.previous_letter();
+        assert_eq!(entry.initials, *b"ZAA");
+        entry.next_letter();
+        entry.next_letter();
+        assert!(!entry.confirm());
+        entry.next_letter();
+        assert!(!entry.confirm());
+        assert!(entry.confirm());
+        assert_eq!(entry.initials, *b"BBA");
+        assert!(entry.confirm()); // Stays done
+        assert_eq!(entry.cursor, 3);
+    }
+
+    #[test]
+    fn a_new_score_is_signed_with_initials() {
+        let clock = MockPlatform::new(0);
+        let up = InputEvent::Direction(Direction::Up);
+        let down = InputEvent::Direction(Direction::Down);
+        let script = [
+            (0, InputEvent::ButtonB),
+            (3000, InputEvent::ButtonA),
+            (3100, up),
+            (3200, InputEvent::ButtonA),
+            (3300, down),
+            (3400, down),
+            (3500, InputEvent::ButtonA),
+            (3600, InputEvent::ButtonA),
+        ];
+        let mut engine = engine(&clock, &script).with_storage(MockStorage::new());
+        frame(&mut engine);
+        engine.game_mut().score = 40;
+        run_until(&mut engine, &clock, 3050);
+        assert_eq!(engine.initials, Some(InitialsEntry::new(0)));
+        assert_eq!(engine.scores()[0].initials, ScoreEntry::NO_INITIALS);
+
+        run_until(&mut engine, &clock, 3650);
+        assert_eq!(engine.initials, None);
+        assert_eq!(engine.scores()[0].initials, *b"BYA");
+        assert_eq!(engine.storage.as_ref().unwrap().scores[0].initials, *b"BYA");
+        assert_eq!(engine.game().state, GameState::Starting);
+    }
+
+    #[test]
+    fn the_menu_picks_the_difficulty() {
+        let clock = MockPlatform::new(0);
+        let script = [
+            (0, InputEvent::ButtonA),
+            (100, InputEvent::Direction(Direction::Down)),
+            (200, InputEvent::Direction(Direction::Down)),
+            (300, InputEvent::ButtonB),
+        ];
+        let mut engine = engine(&clock, &script);
+        frame(&mut engine);
+        assert_eq!(
+            engine.game().state,
+            GameState::DifficultyMenu(Difficulty::Normal)
+        );
+        run_until(&mut engine, &clock, 250);
+        assert_eq!(
+            engine.game().state,
+            GameState::DifficultyMenu(Difficulty::Easy)
+        );
+
+        // Easy: slower, with walls that wrap
+        run_until(&mut engine, &clock, 350);
+        assert_eq!(engine.difficulty(), Difficulty::Easy);
+        assert!(engine.game().is_playing());
+        assert!(engine.game().wrap_walls());
+        assert_eq!(engine.current_frame_time(), 200);
+    }
+
+    #[test]
+    fn muting_silences_the_game_and_is_remembered() {
+        let clock = MockPlatform::new(0);
+        let script = [
+            (0, InputEvent::ButtonB),
+            (100, InputEvent::ButtonB),
+            (200, InputEvent::ButtonA),
+            (300, InputEvent::ButtonB),
+        ];
+        let mut first = engine(&clock, &script)
+            .with_audio(MockAudio::new())
+            .with_storage(MockStorage::new());
+        frame(&mut first);
+        assert!(!first.audio().unwrap().beeps().is_empty()); // The fanfare
+
+        run_until(&mut first, &clock, 350);
+        assert!(first.muted() && first.game().is_playing());
+        let beeps = first.audio().unwrap().beeps().len();
+        let head = first.game().head();
+        first.game_mut().set_food(Position::new(head.x + 1, head.y));
+        run_until(&mut first, &clock, 700);
+        assert_eq!(first.game().food_eaten, 1);
+        assert_eq!(first.audio().unwrap().beeps().len(), beeps);
+
+        // Saved, and read back on the next boot
+        let storage = first.storage.take().unwrap();
+        assert!(storage.muted);
+        let clock = MockPlatform::new(0);
+        let rebooted = engine(&clock, &[]).with_storage(storage);
+        assert!(rebooted.muted());
+    }
+
+    #[test]
+    fn boost_drains_and_recharges() {
+        let mut boost = Boost::new();
+        assert!(!boost.is_active());
+        boost.hold(true);
+        for _ in 0..20 {
+            assert!(boost.is_active());
+            boost.step();
+        }
+        assert_eq!(boost.energy(), 0);
+        assert!(!boost.is_active());
+
+        // Still held, but it takes a quarter tank to come back
+        for _ in 0..BOOST_READY - 1 {
+            boost.step();
+            assert!(!boost.is_active());
+        }
+        boost.step();
+        assert!(boost.is_active());
+        boost.hold(false);
+        assert!(!boost.is_active());
+    }
+
+    #[test]
+    fn holding_a_boosts_the_snake() {
+        let clock = MockPlatform::new(0);
+        let mut engine = engine(&clock, &[(0, InputEvent::ButtonB)]);
+        engine.set_boost(true);
+        engine.game_mut().set_wrap_walls(true);
+        frame(&mut engine);
+        engine.input.hold_a(true);
+        run_until(&mut engine, &clock, 600);
+        assert_eq!(engine.current_frame_time(), 75);
+        let boosted = engine.game().move_count();
+        assert!(engine.boost_energy() < Some(BOOST_MAX_ENERGY));
+
+        engine.input.hold_a(false);
+        run_until(&mut engine, &clock, 1200);
+        assert_eq!(engine.current_frame_time(), 150);
+        let normal = engine.game().move_count() - boosted;
+        assert!(
+            boosted >= 2 * normal - 1,
+            "{boosted} boosted, {normal} normal"
+        );
+    }
+
+    #[test]
+    fn letting_go_of_a_boost_does_not_ask_to_reset() {
+        let clock = MockPlatform::new(0);
+        let script = [(0, InputEvent::ButtonB), (300, InputEvent::ButtonA)];
+        let mut engine = engine(&clock, &script);
+        engine.set_boost(true);
+        engine.game_mut().set_wrap_walls(true);
+        frame(&mut engine);
+        engine.input.hold_a(true);
+        run_until(&mut engine, &clock, 250);
+        assert!(engine.boost_energy() < Some(BOOST_MAX_ENERGY));
+
+        // A hold shorter than a long press comes in as a tap when let go
+        engine.input.hold_a(false);
+        run_until(&mut engine, &clock, 400);
+        assert!(engine.confirm_reset.is_none());
+        assert!(engine.game().is_playing());
+        let moves = engine.game().move_count();
+        run_until(&mut engine, &clock, 800);
+        assert!(engine.game().move_count() > moves);
+    }
+
+    #[test]
+    fn a_quiet_end_screen_turns_into_the_screensaver() {
+        let clock = MockPlatform::new(0);
+        let script = [(0, InputEvent::ButtonB), (9000, InputEvent::ButtonA)];
+        let mut engine = engine(&clock, &script);
+        engine.set_screensaver(Some(5000));
+        run_until(&mut engine, &clock, 3000);
+        assert!(engine.game().game_over() && !engine.screensaver);
+        run_until(&mut engine, &clock, 8000);
+        assert!(engine.screensaver);
+
+        // A press only brings the end screen back
+        run_until(&mut engine, &clock, 9050);
+        assert!(!engine.screensaver);
+        assert!(engine.game().game_over());
+    }
+
+    #[test]
+    fn a_tap_of_a_only_asks_before_a_reset() {
+        let clock = MockPlatform::new(0);
+        let script = [
+            (0, InputEvent::ButtonB),
+            (100, InputEvent::ButtonA),
+            (1000, InputEvent::ButtonB),
+            (1100, InputEvent::ButtonA),
+            (1200, InputEvent::ButtonALong),
+        ];
+        let mut engine = engine(&clock, &script);
+        engine.game_mut().set_wrap_walls(true);
+        run_until(&mut engine, &clock, 150);
+        assert!(engine.confirm_reset.is_some());
+        let moves = engine.game().move_count();
+
+        // The board waits for the answer, any other press carries on
+        run_until(&mut engine, &clock, 950);
+        assert_eq!(engine.game().move_count(), moves);
+        run_until(&mut engine, &clock, 1050);
+        assert!(engine.confirm_reset.is_none());
+        assert!(engine.game().is_playing());
+
+        // A long press confirms it
+        run_until(&mut engine, &clock, 1250);
+        assert_eq!(engine.game().state, GameState::Starting);
+    }
+
+    #[test]
+    fn an_unanswered_reset_question_lets_the_round_go_on() {
+        let clock = MockPlatform::new(0);
+        let script = [(0, InputEvent::ButtonB), (100, InputEvent::ButtonA)];
+        let mut engine = engine(&clock, &script);
+        engine.game_mut().set_wrap_walls(true);
+        run_until(&mut engine, &clock, 150);
+        assert!(engine.confirm_reset.is_some());
+        run_until(&mut engine, &clock, 100 + CONFIRM_RESET_MS + 100);
+        assert!(engine.confirm_reset.is_none());
+        assert!(engine.game().is_playing());
+    }
+
+    #[test]
+    fn a_scripted_round_runs_to_game_over() {
+        let clock = MockPlatform::new(0);
+        let script = [
+            (100, InputEvent::ButtonB),
+            (400, InputEvent::Direction(Direction::Up)),
+        ];
+        let mut engine = engine(&clock, &script);
+        clock.stop_at(20_000);
+        {
+            let mut run = core::pin::pin!(engine.run());
+            let mut context = core::task::Context::from_waker(core::task::Waker::noop());
+            assert!(run.as_mut().poll(&mut context).is_pending());
+        }
+        assert!(clock.current_time_ms() >= 20_000);
+
+        // Up from the middle row into the top wall
+        assert!(engine.game().game_over());
+        assert_eq!(engine.game().snake[0].y, 0);
+        let stats = engine.stats();
+        assert_eq!(stats.moves, engine.game().move_count());
+        assert!(stats.elapsed_ms > 0 && stats.elapsed_ms < 2000);
+        assert_eq!(engine.game().score, 0);
+    }
+
+    #[test]
+    fn a_recorded_round_replays_to_the_same_end() {
+        let clock = MockPlatform::new(0);
+        let script = [
+            (100, InputEvent::ButtonB),
+            (400, InputEvent::Direction(Direction::Up)),
+            (800, InputEvent::Direction(Direction::Left)),
+            (1400, InputEvent::Direction(Direction::Down)),
+        ];
+        let mut engine = engine(&clock, &script);
+        run_until(&mut engine, &clock, 10_000);
+        assert!(engine.game().game_over());
+        let recording = engine.recording();
+        assert_eq!(recording.turns().len(), 4); // Right, then the three turns
+        assert!(!recording.truncated());
+
+        let mut replayed: Game = Game::new(20, 12);
+        replayed.replay(recording.seed(), recording.turns());
+        assert!(replayed.game_over());
+        assert_eq!(replayed.snapshot(), engine.game().snapshot());
+    }
+
+    #[test]
+    fn levels_and_portals_outlast_the_round() {
+        let clock = MockPlatform::new(0);
+        let renderer = GridRenderer::new(MockDisplay::new(240, 135), 6);
+        let mut engine: TestEngine =
+            GameEngine::new(MockInput::new(&clock, &[]), &clock, renderer, 39, 20);
+        assert_eq!(engine.load_level(&crate::levels::PILLARS), Ok(()));
+        let (a, b) = (Position::new(1, 1), Position::new(37, 18));
+        engine.add_portal(a, b);
+        let walls = engine.game().obstacles().len();
+        assert!(walls > 0);
+
+        engine.start_game();
+        engine.game_mut().reset();
+        assert_eq!(engine.game().obstacles().len(), walls);
+        assert_eq!(engine.game().portals(), [(a, b)]);
+
+        // Too big for the board: refused, the walls stay
+        let mut small: TestEngine = GameEngine::new(
+            MockInput::new(&clock, &[]),
+            &clock,
+            GridRenderer::new(MockDisplay::new(120, 60), 6),
+            20,
+            10,
+        );
+        assert_eq!(
+            small.load_level(&crate::levels::PILLARS),
+            Err(LevelError::TooLarge)
+        );
+        assert!(small.game().obstacles().is_empty());
+    }
+
+    #[test]
+    fn fresh_rounds_are_reseeded_from_the_clock() {
+        let clock = MockPlatform::new(1000);
+        let mut engine = engine(&clock, &[]);
+        engine.start_game();
+        assert_eq!(engine.game().seed(), 1000);
+
+        // Same start time, but the generator moved on: the seed still changes
+        engine.game_mut().reset();
+        let carried = engine.game().seed();
+        clock.advance(1234);
+        engine.start_game();
+        assert_eq!(engine.game().seed(), carried ^ 2234);
+        assert_ne!(engine.game().seed(), 1000);
+    }
+
+    #[test]
+    fn preserved_rounds_replay_the_first_seed() {
+        let clock = MockPlatform::new(1000);
+        let mut engine = engine(&clock, &[]);
+        engine.set_restart_seed(RestartSeed::Preserve);
+        engine.start_game();
+        let food = *engine.game().food();
+        for _ in 0..5 {
+            engine.game_mut().update();
+        }
+
+        engine.game_mut().reset();
+        clock.advance(1234);
+        engine.start_game();
+        assert_eq!(engine.game().seed(), 1000);
+        assert_eq!(*engine.game().food(), food);
+    }
 }
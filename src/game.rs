@@ -1,5 +1,8 @@
 use heapless::Vec;
 
+use crate::levels::{LevelData, LevelError};
+use crate::snapshot::{GameSnapshot, SnakeSnapshot, SnapshotError};
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Direction {
     Up,
@@ -17,15 +20,111 @@ impl Direction {
             Direction::Right => Direction::Left,
         }
     }
+
+    // Quarter turn to the right of this direction, or to the left
+    pub fn turned(&self, clockwise: bool) -> Direction {
+        let right = match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        };
+        if clockwise {
+            right
+        } else {
+            right.opposite()
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum GameState {
+    Starting,                   // Waiting on the start screen
+    DifficultyMenu(Difficulty), // Picking a difficulty, holds the highlighted one
+    Countdown(u8),              // Counting down before the snake moves, holds the number shown
     Playing,
+    Paused,
     GameOver,
+    Won, // The snake fills the whole board
 }
 
-#[derive(Debug, Clone, Copy)]
+// Presets picked on the difficulty menu before a round
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Difficulty {
+    Easy,   // Slower, gentler speed up, the walls wrap
+    Normal, // The configured pace, the walls kill
+    Hard,   // Faster, steeper speed up, the walls kill
+}
+
+impl Difficulty {
+    // In menu order
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    // Next one down the menu, wrapping around at the end
+    pub fn next(&self) -> Difficulty {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+
+    pub fn previous(&self) -> Difficulty {
+        self.next().next()
+    }
+
+    // `config` adjusted for this difficulty: the pace is scaled from the
+    // configured one, Easy wraps the walls and the others make them lethal
+    pub fn config(&self, config: GameConfig) -> GameConfig {
+        let (frame_time_ms, speed_step_ms) = match self {
+            Difficulty::Easy => (config.frame_time_ms * 4 / 3, config.speed_step_ms / 2),
+            Difficulty::Normal => (config.frame_time_ms, config.speed_step_ms),
+            Difficulty::Hard => (config.frame_time_ms * 2 / 3, config.speed_step_ms * 2),
+        };
+        GameConfig {
+            frame_time_ms,
+            speed_step_ms,
+            wrap_walls: *self == Difficulty::Easy,
+            ..config
+        }
+    }
+}
+
+// How the food sequence behaves when the game is restarted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RestartSeed {
+    Preserve, // Replay the previous round's food sequence (route practice)
+    Fresh,    // Keep drawing from the generator, the engine mixes in the start time
+}
+
+// Second snake of a two player round. Player one stays `Game::snake` with the
+// game's direction and score, so single player code doesn't change.
+#[derive(Debug, Clone)]
+pub struct Snake<const CAP: usize = SNAKE_CAPACITY> {
+    pub body: Vec<Position, CAP>,
+    pub direction: Direction,
+    direction_queue: Vec<Direction, 4>,
+    pub alive: bool,
+    pub score: u16,
+}
+
+// What sits on a food cell
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FoodKind {
+    Normal, // Grows the snake by one
+    Bonus,  // Worth more, times out
+    Poison, // Shrinks the snake and takes points
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Position {
     pub x: u8,
     pub y: u8,
@@ -37,165 +136,2095 @@ impl Position {
     }
 }
 
-pub struct Game {
-    pub snake: Vec<Position, 64>, // Max snake length
-    pub food: Position,
+// Signed cell used while moving: a step off the board stays a negative or
+// too large coordinate instead of a `u8` wrapping round to 255, and only
+// becomes a `Position` again once it is known to be on the board
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Cell {
+    x: i16,
+    y: i16,
+}
+
+impl Cell {
+    fn step(self, direction: Direction) -> Self {
+        let (dx, dy) = match direction {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+        };
+        Self {
+            x: self.x + dx,
+            y: self.y + dy,
+        }
+    }
+
+    // Inside the area from (`min`, `min`) up to, not including, (`right`, `bottom`)
+    fn within(self, min: i16, right: i16, bottom: i16) -> bool {
+        (min..right).contains(&self.x) && (min..bottom).contains(&self.y)
+    }
+
+    // Brought back into the same area from the opposite edge
+    fn wrapped(self, min: i16, right: i16, bottom: i16) -> Self {
+        Self {
+            x: min + (self.x - min).rem_euclid(right - min),
+            y: min + (self.y - min).rem_euclid(bottom - min),
+        }
+    }
+
+    // `None` for coordinates a `Position` can't hold
+    fn position(self) -> Option<Position> {
+        Some(Position::new(
+            u8::try_from(self.x).ok()?,
+            u8::try_from(self.y).ok()?,
+        ))
+    }
+}
+
+impl From<Position> for Cell {
+    fn from(position: Position) -> Self {
+        Self {
+            x: position.x as i16,
+            y: position.y as i16,
+        }
+    }
+}
+
+// Default max snake length, enough to fill the 40x22 Pico board
+pub const SNAKE_CAPACITY: usize = 1024;
+
+// Max number of food items on the board at once
+pub const MAX_FOOD: usize = 8;
+
+// Max number of obstacle cells in a level
+pub const MAX_OBSTACLES: usize = 64;
+
+// Max number of portal pairs on the board
+pub const MAX_PORTALS: usize = 4;
+
+// Bonus food: worth more, appears now and then after eating, and times out
+const BONUS_TICKS: u16 = 40;
+const BONUS_CHANCE: u32 = 4; // 1 in N normal foods spawns a bonus
+
+// Poison food: shows up now and then after eating, takes points and tail
+// segments instead of growing the snake, and times out like the bonus
+const POISON_SHRINK: usize = 2; // Tail segments lost on top of the move
+const POISON_TICKS: u16 = 60;
+const POISON_CHANCE: u32 = 5; // 1 in N normal foods spawns poison, when enabled
+const MIN_LENGTH: usize = 2; // Poison that would leave less than this kills
+
+// Sudden death stops closing in once the safe area is this many cells across
+const MIN_SAFE_SIZE: u8 = 5;
+
+// Combo: eating again within the window multiplies the points, up to MAX_COMBO
+const COMBO_WINDOW_TICKS: u16 = 15;
+const MAX_COMBO: u8 = 5;
+
+// Returned by `food()` once the board is full and nothing is left to eat
+const NO_FOOD: Position = Position { x: 0, y: 0 };
+
+// Points per kind of food, so modes can tune them. The combo multiplier only
+// applies to gains, poison takes its points off as they are.
+#[derive(Debug, Clone, Copy)]
+pub struct ScoringRules {
+    pub normal: u16,
+    pub bonus: u16,
+    pub poison: i16, // Usually negative
+    pub combo_multiplier: fn(u8) -> u16,
+}
+
+impl ScoringRules {
+    // Score change for eating `food` as the `combo`th food of a streak
+    pub fn delta(&self, food: FoodKind, combo: u8) -> i32 {
+        match food {
+            FoodKind::Normal => self.normal as i32 * (self.combo_multiplier)(combo) as i32,
+            FoodKind::Bonus => self.bonus as i32 * (self.combo_multiplier)(combo) as i32,
+            FoodKind::Poison => self.poison as i32,
+        }
+    }
+
+    // `score` after eating `food`, kept between 0 and `u16::MAX`
+    pub fn apply(&self, score: u16, food: FoodKind, combo: u8) -> u16 {
+        (score as i32 + self.delta(food, combo)).clamp(0, u16::MAX as i32) as u16
+    }
+}
+
+// Rules sharing a multiplier function compare equal, though the same function
+// may not always have the same address
+impl PartialEq for ScoringRules {
+    fn eq(&self, other: &Self) -> bool {
+        self.normal == other.normal
+            && self.bonus == other.bonus
+            && self.poison == other.poison
+            && core::ptr::fn_addr_eq(self.combo_multiplier, other.combo_multiplier)
+    }
+}
+
+impl Default for ScoringRules {
+    fn default() -> Self {
+        Self {
+            normal: 10,
+            bonus: 50,
+            poison: -20,
+            combo_multiplier,
+        }
+    }
+}
+
+// Gameplay settings, gathered in one place to tune or build a game from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GameConfig {
+    pub width: u8,
+    pub height: u8,
+    pub start_len: u8, // Snake length at the start of each round
+    pub scoring: ScoringRules,
+    pub wrap_walls: bool,
+    pub hunger_ticks: u16, // Moves without food before starving, 0 disables hunger
+    pub wall_bias: u8,     // Chance in 255 that food drawn next to a wall is drawn again
+    pub food_lookahead: u8, // Cells ahead of the head kept free of new food
+    pub self_collision: bool, // Off lets a snake pass through its own body
+    pub food_count: u8,    // Food items kept on the board (1..=MAX_FOOD)
+    pub sudden_death_ticks: u16, // Moves between closing rings, 0 disables sudden death
+    pub poison: bool,      // Poison food can appear after eating
+    pub seed: u32,
+    // Pace, kept by the engine rather than the game
+    pub frame_time_ms: u32, // Time between moves at score 0
+    pub speed_step_ms: u32, // Taken off the frame time per food worth of score
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            width: 40, // Pico LCD 1.14 with 6 px cells
+            height: 22,
+            start_len: 3,
+            scoring: ScoringRules::default(),
+            wrap_walls: false,
+            hunger_ticks: 0,
+            wall_bias: 0,
+            food_lookahead: 1,
+            self_collision: true,
+            food_count: 1,
+            sudden_death_ticks: 0,
+            poison: false,
+            seed: DEFAULT_SEED,
+            frame_time_ms: 150,
+            speed_step_ms: 3,
+        }
+    }
+}
+
+// `CAP` is the longest the snake can get. Boards with more free cells than that
+// are won once the snake reaches `CAP`.
+pub struct Game<const CAP: usize = SNAKE_CAPACITY> {
+    pub snake: Vec<Position, CAP>,
+    foods: Vec<Position, MAX_FOOD>,
+    food_count: u8,                  // How many food items to keep on the board
+    bonus: Option<(Position, u16)>,  // Bonus food and ticks left before it vanishes
+    poison: Option<(Position, u16)>, // Poison food and ticks left, same as the bonus
+    poison_enabled: bool,
+    second: Option<Snake<CAP>>, // Player two, only in two player rounds
+    two_player: bool,
+    first_alive: bool, // Player one can die first and watch player two finish
+    obstacles: Vec<Position, MAX_OBSTACLES>, // Wall cells inside the board, kept across resets
+    spawn: Option<Position>, // Snake head at the start of a round, from the level
+    portals: Vec<(Position, Position), MAX_PORTALS>, // Linked cell pairs, kept across resets
     pub direction: Direction,
-    pub next_direction: Direction,
+    direction_queue: Vec<Direction, 4>, // Turns waiting for the next ticks
     pub state: GameState,
     pub score: u16,
     pub food_eaten: u16,
-    pub game_over: bool,
+    combo: u8,             // Foods eaten in quick succession, 0 before the first
+    ticks_since_food: u16, // Moves since the last food (or the start)
+    move_count: u32,       // Updates since the round started
     width: u8,
     height: u8,
-    rng_state: u32, // Simple LFSR for random numbers
+    start_len: u8,
+    scoring: ScoringRules,
+    wrap_walls: bool, // Tunnel through the edges instead of dying
+    hunger_ticks: u16,
+    wall_bias: u8,
+    food_lookahead: u8,
+    self_collision: bool,
+    sudden_death_ticks: u16, // Ticks between the walls moving in, 0 keeps them still
+    border_inset: u8,        // Rings of cells along the edges that have turned lethal
+    rng_state: u32,          // Simple LFSR for random numbers
+    seed: u32,               // RNG state at the start of the current round
+    restart_seed: RestartSeed,
 }
 
-impl Game {
+// Default RNG seed, also used in place of zero (xorshift gets stuck on 0)
+const DEFAULT_SEED: u32 = 0xACE1;
+
+impl<const CAP: usize> Game<CAP> {
     pub fn new(width: u8, height: u8) -> Self {
-        let mut snake = Vec::new();
-        // Start snake in the middle of the screen
-        let start_x = width / 2;
-        let start_y = height / 2;
+        Self::from_config(GameConfig {
+            width,
+            height,
+            ..GameConfig::default()
+        })
+    }
+
+    pub fn with_seed(width: u8, height: u8, seed: u32) -> Self {
+        Self::from_config(GameConfig {
+            width,
+            height,
+            seed,
+            ..GameConfig::default()
+        })
+    }
 
-        snake.push(Position::new(start_x, start_y)).unwrap();
-        snake.push(Position::new(start_x - 1, start_y)).unwrap();
-        snake.push(Position::new(start_x - 2, start_y)).unwrap();
+    // Boards smaller than 1x1 are bumped up to a single cell
+    pub fn from_config(config: GameConfig) -> Self {
+        let seed = if config.seed == 0 {
+            DEFAULT_SEED
+        } else {
+            config.seed
+        };
 
         let mut game = Self {
-            snake,
-            food: Position::new(0, 0),
+            snake: Vec::new(),
+            foods: Vec::new(),
+            food_count: config.food_count.clamp(1, MAX_FOOD as u8),
+            bonus: None,
+            poison: None,
+            poison_enabled: config.poison,
+            second: None,
+            two_player: false,
+            first_alive: true,
+            obstacles: Vec::new(),
+            spawn: None,
+            portals: Vec::new(),
             direction: Direction::Right,
-            next_direction: Direction::Right,
-            state: GameState::Playing,
+            direction_queue: Vec::new(),
+            state: GameState::Starting,
             score: 0,
             food_eaten: 0,
-            game_over: false,
-            width,
-            height,
-            rng_state: 0xACE1u32, // Seed for random number generator
+            combo: 0,
+            ticks_since_food: 0,
+            move_count: 0,
+            width: config.width.max(1),
+            height: config.height.max(1),
+            start_len: config.start_len,
+            scoring: config.scoring,
+            wrap_walls: config.wrap_walls,
+            hunger_ticks: config.hunger_ticks,
+            wall_bias: config.wall_bias,
+            food_lookahead: config.food_lookahead,
+            self_collision: config.self_collision,
+            sudden_death_ticks: config.sudden_death_ticks,
+            border_inset: 0,
+            rng_state: seed, // Seed for random number generator
+            seed,
+            restart_seed: RestartSeed::Fresh,
         };
 
-        game.spawn_food();
+        game.place_snake();
+        game.fill_food();
         game
     }
 
     pub fn reset(&mut self) {
-        self.snake.clear();
-        let start_x = self.width / 2;
-        let start_y = self.height / 2;
-
-        self.snake.push(Position::new(start_x, start_y)).unwrap();
-        self.snake
-            .push(Position::new(start_x - 1, start_y))
-            .unwrap();
-        self.snake
-            .push(Position::new(start_x - 2, start_y))
-            .unwrap();
+        self.place_snake();
 
         self.direction = Direction::Right;
-        self.next_direction = Direction::Right;
-        self.state = GameState::Playing;
+        self.direction_queue.clear();
+        self.first_alive = true;
+        self.state = GameState::Starting;
         self.score = 0;
         self.food_eaten = 0;
-        self.game_over = false;
-        self.spawn_food();
+        self.combo = 0;
+        self.ticks_since_food = 0;
+        self.move_count = 0;
+        self.border_inset = 0;
+
+        match self.restart_seed {
+            RestartSeed::Preserve => self.rng_state = self.seed,
+            RestartSeed::Fresh => self.seed = self.rng_state,
+        }
+
+        self.foods.clear();
+        self.bonus = None;
+        self.poison = None;
+        self.fill_food();
     }
 
-    pub fn set_direction(&mut self, direction: Direction) {
-        // Prevent the snake from going back into itself
-        if direction != self.direction.opposite() {
-            self.next_direction = direction;
+    // Number of food items kept on the board (1..=MAX_FOOD)
+    pub fn set_food_count(&mut self, count: u8) {
+        self.food_count = count.clamp(1, MAX_FOOD as u8);
+        self.foods.truncate(self.food_count as usize);
+        self.fill_food();
+    }
+
+    pub fn food_count(&self) -> u8 {
+        self.food_count
+    }
+
+    // First food item, for renderers that only draw one
+    pub fn food(&self) -> &Position {
+        self.foods.first().unwrap_or(&NO_FOOD)
+    }
+
+    pub fn foods(&self) -> &[Position] {
+        &self.foods
+    }
+
+    // Pin the first food item to `position`, so tests can place food without
+    // going through the RNG. Cells outside the safe area, under the snake or on
+    // an obstacle are ignored.
+    pub fn set_food(&mut self, position: Position) {
+        if !self.in_safe_area(position) || self.is_occupied(position) || self.is_portal(position) {
+            return;
+        }
+        if self.bonus_food() == Some(position) {
+            self.bonus = None;
+        }
+        if self.poison_food() == Some(position) {
+            self.poison = None;
+        }
+
+        match self.foods.iter().position(|food| *food == position) {
+            Some(index) => self.foods.swap(0, index),
+            None if self.foods.is_empty() => {
+                let _ = self.foods.push(position);
+            }
+            None => self.foods[0] = position,
         }
     }
 
-    pub fn update(&mut self) {
-        if self.state != GameState::Playing {
+    // Put poison on `position` for `POISON_TICKS`, like `set_food`. Taken cells
+    // and cells off the board are ignored.
+    pub fn set_poison_food(&mut self, position: Position) {
+        if position.x >= self.width || position.y >= self.height || !self.is_free(position) {
+            return;
+        }
+        self.poison = Some((position, POISON_TICKS));
+    }
+
+    // Put a bonus on `position` for `BONUS_TICKS`, like `set_poison_food`
+    pub fn set_bonus_food(&mut self, position: Position) {
+        if position.x >= self.width || position.y >= self.height || !self.is_free(position) {
             return;
         }
+        self.bonus = Some((position, BONUS_TICKS));
+    }
+
+    // Place a wall cell. Cells outside the board, under the snake, where a
+    // snake starts or on a portal are ignored, food sitting on the cell is
+    // moved elsewhere.
+    pub fn add_obstacle(&mut self, position: Position) -> &mut Self {
+        if position.x >= self.width
+            || position.y >= self.height
+            || self.is_occupied(position)
+            || self.is_start_cell(position, self.spawn)
+            || self.is_portal(position)
+        {
+            return self;
+        }
+
+        if self.obstacles.push(position).is_ok() {
+            self.foods.retain(|food| *food != position);
+            if self.bonus_food() == Some(position) {
+                self.bonus = None;
+            }
+            if self.poison_food() == Some(position) {
+                self.poison = None;
+            }
+            self.fill_food();
+        }
+        self
+    }
+
+    // Replace the obstacles with the walls of `level`, centered on the board,
+    // and start the snake on its spawn cell from now on. Portals belong to the
+    // old layout and are removed. Resets the round.
+    // Nothing changes when the level doesn't fit or its spawn is blocked.
+    pub fn load_level(&mut self, level: &LevelData) -> Result<(), LevelError> {
+        let (width, height) = (level.width, level.height);
+        if level.cells.len() != width as usize * height as usize {
+            return Err(LevelError::BadData);
+        }
+        if width > self.width || height > self.height {
+            return Err(LevelError::TooLarge);
+        }
+        if level.wall_count() > MAX_OBSTACLES {
+            return Err(LevelError::TooManyWalls);
+        }
 
-        // Update direction
-        self.direction = self.next_direction;
+        // The starting body trails left of the spawn, every cell must be open
+        let spawn = level.spawn;
+        let len = self.start_len.max(1);
+        if spawn.x >= width || spawn.y >= height || spawn.x + 1 < len {
+            return Err(LevelError::SpawnBlocked);
+        }
+        if (0..len).any(|i| level.is_wall(spawn.x - i, spawn.y)) {
+            return Err(LevelError::SpawnBlocked);
+        }
 
-        // Calculate new head position
-        let head = self.snake[0];
-        let new_head = match self.direction {
-            Direction::Up => Position::new(head.x, head.y.wrapping_sub(1)),
-            Direction::Down => Position::new(head.x, head.y.wrapping_add(1)),
-            Direction::Left => Position::new(head.x.wrapping_sub(1), head.y),
-            Direction::Right => Position::new(head.x.wrapping_add(1), head.y),
+        let left = (self.width - width) / 2;
+        let top = (self.height - height) / 2;
+        // With two players neither snake starts on the spawn, check where they do
+        let board_spawn = Position::new(left + spawn.x, top + spawn.y);
+        let blocks_a_snake = |x: u8, y: u8| {
+            level.is_wall(x, y)
+                && self.is_start_cell(Position::new(left + x, top + y), Some(board_spawn))
         };
+        if (0..height).any(|y| (0..width).any(|x| blocks_a_snake(x, y))) {
+            return Err(LevelError::SpawnBlocked);
+        }
 
-        // Check wall collision
-        if new_head.x >= self.width || new_head.y >= self.height {
-            self.game_over = true;
-            return;
+        self.obstacles.clear();
+        self.portals.clear();
+        for y in 0..height {
+            for x in 0..width {
+                if level.is_wall(x, y) {
+                    let _ = self.obstacles.push(Position::new(left + x, top + y));
+                }
+            }
         }
+        self.spawn = Some(board_spawn);
+        self.reset();
+        Ok(())
+    }
 
-        // Check self collision
-        for segment in &self.snake {
-            if new_head.x == segment.x && new_head.y == segment.y {
-                self.game_over = true;
-                return;
+    // Link two cells: a head moving onto either comes out on the other, keeping
+    // its direction. Cells off the board, on walls or already linked, a cell
+    // paired with itself and pairs beyond `MAX_PORTALS` are ignored, food on
+    // the cells is moved elsewhere.
+    pub fn add_portal(&mut self, a: Position, b: Position) -> &mut Self {
+        let usable = |position: Position| {
+            position.x < self.width
+                && position.y < self.height
+                && !self.obstacles.contains(&position)
+                && !self.is_portal(position)
+        };
+        if a == b || !usable(a) || !usable(b) {
+            return self;
+        }
+
+        if self.portals.push((a, b)).is_ok() {
+            self.foods.retain(|food| *food != a && *food != b);
+            if self
+                .bonus_food()
+                .is_some_and(|bonus| bonus == a || bonus == b)
+            {
+                self.bonus = None;
             }
+            if self
+                .poison_food()
+                .is_some_and(|poison| poison == a || poison == b)
+            {
+                self.poison = None;
+            }
+            self.fill_food();
         }
+        self
+    }
 
-        // Check food collision
-        let ate_food = new_head.x == self.food.x && new_head.y == self.food.y;
+    pub fn portals(&self) -> &[(Position, Position)] {
+        &self.portals
+    }
 
-        // Add new head
-        self.snake.insert(0, new_head).unwrap();
+    fn is_portal(&self, position: Position) -> bool {
+        self.portals
+            .iter()
+            .any(|(a, b)| *a == position || *b == position)
+    }
 
-        if ate_food {
-            self.score += 10;
-            self.food_eaten += 1;
-            self.spawn_food();
+    // Where a head moving onto `position` ends up: the paired cell for a portal,
+    // `position` itself otherwise
+    fn through_portal(&self, position: Position) -> Position {
+        self.portals
+            .iter()
+            .find_map(|(a, b)| match position {
+                p if p == *a => Some(*b),
+                p if p == *b => Some(*a),
+                _ => None,
+            })
+            .unwrap_or(position)
+    }
+
+    // Player one's segments, head first
+    pub fn body(&self) -> impl DoubleEndedIterator<Item = Position> + '_ {
+        self.snake.iter().copied()
+    }
+
+    // Player one's head. Every round starts with at least one segment.
+    pub fn head(&self) -> Position {
+        self.snake[0]
+    }
+
+    // Whether a snake (either one) or a wall covers `position`. Food, portals
+    // and sudden death rings don't count.
+    pub fn is_occupied(&self, position: Position) -> bool {
+        self.is_on_snake(position) || self.obstacles.contains(&position)
+    }
+
+    pub fn obstacles(&self) -> &[Position] {
+        &self.obstacles
+    }
+
+    pub fn bonus_food(&self) -> Option<Position> {
+        self.bonus.map(|(position, _)| position)
+    }
+
+    pub fn bonus_ticks_left(&self) -> u16 {
+        self.bonus.map_or(0, |(_, ticks)| ticks)
+    }
+
+    pub fn poison_food(&self) -> Option<Position> {
+        self.poison.map(|(position, _)| position)
+    }
+
+    // Kind of food on `position`, `None` when there is none
+    pub fn food_at(&self, position: Position) -> Option<FoodKind> {
+        if self.foods.contains(&position) {
+            Some(FoodKind::Normal)
+        } else if self.bonus_food() == Some(position) {
+            Some(FoodKind::Bonus)
+        } else if self.poison_food() == Some(position) {
+            Some(FoodKind::Poison)
         } else {
-            // Remove tail if no food eaten
-            self.snake.pop();
+            None
         }
     }
 
-    fn spawn_food(&mut self) {
-        // Limit attempts to prevent infinite loop
-        for _attempt in 0..100 {
-            let x = self.next_random() % self.width as u32;
-            let y = self.next_random() % self.height as u32;
+    // Two snakes on the board, player two starting opposite player one. Takes
+    // effect from the next reset, or right away on the start screen. Turn it
+    // on before loading a level, which checks where both snakes start.
+    pub fn set_two_player(&mut self, enabled: bool) {
+        self.two_player = enabled;
+        if self.state == GameState::Starting {
+            self.place_snake();
+            let snake_free: Vec<Position, MAX_FOOD> = self
+                .foods
+                .iter()
+                .copied()
+                .filter(|food| !self.is_on_snake(*food))
+                .collect();
+            self.foods = snake_free;
+            self.fill_food();
+        }
+    }
 
-            let new_food = Position::new(x as u8, y as u8);
+    pub fn two_player(&self) -> bool {
+        self.two_player
+    }
 
-            // Make sure food doesn't spawn on snake
-            let mut valid = true;
-            for segment in &self.snake {
-                if segment.x == new_food.x && segment.y == new_food.y {
-                    valid = false;
-                    break;
-                }
-            }
+    // Player two's snake, `None` in single player rounds
+    pub fn second_snake(&self) -> Option<&Snake<CAP>> {
+        self.second.as_ref()
+    }
 
-            if valid {
-                self.food = new_food;
-                return;
-            }
+    // False once player one crashed while player two plays on
+    pub fn first_alive(&self) -> bool {
+        self.first_alive
+    }
+
+    pub fn set_scoring(&mut self, scoring: ScoringRules) {
+        self.scoring = scoring;
+    }
+
+    pub fn scoring(&self) -> &ScoringRules {
+        &self.scoring
+    }
+
+    // Let poison food appear after eating, off by default
+    pub fn set_poison(&mut self, enabled: bool) {
+        self.poison_enabled = enabled;
+        if !enabled {
+            self.poison = None;
         }
+    }
 
-        // Fallback if no valid position found (shouldn't happen with reasonable snake size)
-        self.food = Position::new(0, 0);
+    pub fn poison(&self) -> bool {
+        self.poison_enabled
     }
 
-    // Simple LFSR random number generator
-    fn next_random(&mut self) -> u32 {
-        self.rng_state ^= self.rng_state << 13;
-        self.rng_state ^= self.rng_state >> 17;
-        self.rng_state ^= self.rng_state << 5;
-        self.rng_state
+    pub fn set_wrap_walls(&mut self, wrap_walls: bool) {
+        self.wrap_walls = wrap_walls;
     }
 
-    pub fn width(&self) -> u8 {
-        self.width
+    pub fn wrap_walls(&self) -> bool {
+        self.wrap_walls
     }
 
-    pub fn height(&self) -> u8 {
-        self.height
+    // Starve after `ticks` moves without eating, 0 turns hunger off
+    pub fn set_hunger_ticks(&mut self, ticks: u16) {
+        self.hunger_ticks = ticks;
+    }
+
+    pub fn hunger_ticks(&self) -> u16 {
+        self.hunger_ticks
+    }
+
+    // Keep food off the outer ring of the board: a random cell there is drawn
+    // again `bias` times in 255, 255 always does. 0 spawns uniformly and draws
+    // the same food as before for a given seed.
+    pub fn set_wall_bias(&mut self, bias: u8) {
+        self.wall_bias = bias;
+    }
+
+    pub fn wall_bias(&self) -> u8 {
+        self.wall_bias
+    }
+
+    // New food skips the `cells` in front of the head, so it can't be eaten
+    // before the player has seen it. 0 allows any cell.
+    pub fn set_food_lookahead(&mut self, cells: u8) {
+        self.food_lookahead = cells;
+    }
+
+    pub fn food_lookahead(&self) -> u8 {
+        self.food_lookahead
+    }
+
+    // Forgiving mode for young players: with `false` a snake passes through its
+    // own body and only walls, obstacles and the other snake end the round
+    pub fn set_self_collision(&mut self, enabled: bool) {
+        self.self_collision = enabled;
+    }
+
+    pub fn self_collision(&self) -> bool {
+        self.self_collision
+    }
+
+    // Moves left before starving, `None` when hunger is off. Divide by
+    // `hunger_ticks()` for a depleting bar.
+    pub fn hunger_left(&self) -> Option<u16> {
+        if self.hunger_ticks == 0 {
+            return None;
+        }
+        Some(self.hunger_ticks.saturating_sub(self.ticks_since_food))
+    }
+
+    // Sudden death: every `ticks` moves the outermost safe ring of cells turns
+    // lethal, down to a 5x5 area. 0 turns it off.
+    pub fn set_sudden_death(&mut self, ticks: u16) {
+        self.sudden_death_ticks = ticks;
+    }
+
+    pub fn sudden_death(&self) -> u16 {
+        self.sudden_death_ticks
+    }
+
+    // Lethal rings along the edges: cells closer than this to an edge act as walls
+    pub fn border_inset(&self) -> u8 {
+        self.border_inset
+    }
+
+    // Inside the walls, once sudden death has moved them in
+    pub fn in_safe_area(&self, position: Position) -> bool {
+        let inset = self.border_inset;
+        position.x >= inset
+            && position.y >= inset
+            && position.x < self.width.saturating_sub(inset)
+            && position.y < self.height.saturating_sub(inset)
+    }
+
+    pub fn set_restart_seed(&mut self, mode: RestartSeed) {
+        self.restart_seed = mode;
+    }
+
+    pub fn restart_seed(&self) -> RestartSeed {
+        self.restart_seed
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    // Restart the RNG from `seed`. Before the round starts the pending food is
+    // re-rolled too, so a seed taken at the first button press changes the layout.
+    pub fn reseed(&mut self, seed: u32) {
+        let seed = if seed == 0 { DEFAULT_SEED } else { seed };
+        self.seed = seed;
+        self.rng_state = seed;
+        if self.state == GameState::Starting {
+            self.foods.clear();
+            self.fill_food();
+        }
+    }
+
+    pub fn start(&mut self) {
+        if matches!(self.state, GameState::Starting | GameState::Countdown(_)) {
+            self.state = GameState::Playing;
+        }
+    }
+
+    // Show the difficulty menu with `selected` highlighted, or move the
+    // highlight when it is already up. Only from the start screen.
+    pub fn open_difficulty_menu(&mut self, selected: Difficulty) {
+        if matches!(
+            self.state,
+            GameState::Starting | GameState::DifficultyMenu(_)
+        ) {
+            self.state = GameState::DifficultyMenu(selected);
+        }
+    }
+
+    // Back to the start screen from the difficulty menu
+    pub fn close_difficulty_menu(&mut self) {
+        if matches!(self.state, GameState::DifficultyMenu(_)) {
+            self.state = GameState::Starting;
+        }
+    }
+
+    // Show `from`, `from - 1`, ... 1 before playing. 0 starts right away.
+    pub fn start_countdown(&mut self, from: u8) {
+        if self.state != GameState::Starting {
+            return;
+        }
+        self.state = match from {
+            0 => GameState::Playing,
+            n => GameState::Countdown(n),
+        };
+    }
+
+    // Advance the countdown by one, starting the game after the last number
+    pub fn count_down(&mut self) {
+        if let GameState::Countdown(n) = self.state {
+            self.state = if n > 1 {
+                GameState::Countdown(n - 1)
+            } else {
+                GameState::Playing
+            };
+        }
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.state = match self.state {
+            GameState::Playing => GameState::Paused,
+            GameState::Paused => GameState::Playing,
+            other => other,
+        };
+    }
+
+    pub fn set_direction(&mut self, direction: Direction) {
+        // Compare against the last queued turn so quick double inputs can't reverse the snake
+        let last = self
+            .direction_queue
+            .last()
+            .copied()
+            .unwrap_or(self.direction);
+
+        // Prevent the snake from going back into itself, and skip repeats
+        if direction != last.opposite() && direction != last {
+            // Drop the input when the queue is full
+            let _ = self.direction_queue.push(direction);
+        }
+    }
+
+    // Steer player two a quarter turn right (`clockwise`) or left of where it
+    // is heading, counting turns still queued
+    pub fn turn_second(&mut self, clockwise: bool) {
+        if let Some(second) = self.second.as_mut() {
+            let last = second
+                .direction_queue
+                .last()
+                .copied()
+                .unwrap_or(second.direction);
+            let _ = second.direction_queue.push(last.turned(clockwise));
+        }
+    }
+
+    // Testable primitive: optionally turn, then advance exactly one tick. The
+    // turn replaces any queued ones and takes effect on this tick (a reversal is
+    // still ignored). `update` remains the normal entry point for the engine.
+    pub fn step(&mut self, direction: Option<Direction>) {
+        if let Some(direction) = direction {
+            self.direction_queue.clear();
+            if direction != self.direction.opposite() {
+                self.direction = direction;
+            }
+        }
+        self.update();
+    }
+
+    // Play a recorded round again: restart from `seed` and apply each
+    // `(tick, direction)` turn on its tick (see `replay::Recorder`). The RNG
+    // makes it deterministic, so the same food and outcome come back. Runs until
+    // the round ends, or for at most `u16::MAX` ticks with wrapping walls.
+    pub fn replay(&mut self, seed: u32, turns: &[(u16, Direction)]) {
+        self.reset();
+        self.reseed(seed); // Re-rolls the food from `seed` before the round starts
+        self.start();
+
+        let mut turns = turns.iter().peekable();
+        while self.is_playing() && self.ticks() < u16::MAX {
+            let mut direction = None;
+            while let Some(&&(tick, turn)) = turns.peek() {
+                if tick > self.ticks() {
+                    break;
+                }
+                direction = Some(turn);
+                turns.next();
+            }
+            self.step(direction);
+        }
+    }
+
+    // The round as it stands, to save and `restore` after a reset. Queued
+    // turns are left out, the settings (walls, portals, modes) belong to the
+    // game the snapshot is restored into. The difficulty and time played are
+    // the engine's to fill in.
+    pub fn snapshot(&self) -> GameSnapshot<CAP> {
+        GameSnapshot {
+            snake: self.snake.clone(),
+            foods: self.foods.clone(),
+            direction: self.direction,
+            score: self.score,
+            food_eaten: self.food_eaten,
+            rng_state: self.rng_state,
+            move_count: self.move_count,
+            ticks_since_food: self.ticks_since_food,
+            combo: self.combo,
+            border_inset: self.border_inset,
+            first_alive: self.first_alive,
+            bonus: self.bonus,
+            poison: self.poison,
+            second: self.second.as_ref().map(|second| SnakeSnapshot {
+                body: second.body.clone(),
+                direction: second.direction,
+                alive: second.alive,
+                score: second.score,
+            }),
+            difficulty: Difficulty::Normal,
+            played_ms: 0,
+        }
+    }
+
+    // Carry on a saved round, paused so the player can get ready. Nothing
+    // changes when the snapshot doesn't fit this board.
+    pub fn restore(&mut self, snapshot: GameSnapshot<CAP>) -> Result<(), SnapshotError> {
+        let on_board = |cell: &Position| cell.x < self.width && cell.y < self.height;
+        let timed_on_board =
+            |timed: Option<(Position, u16)>| timed.is_none_or(|(cell, _)| on_board(&cell));
+        let second_on_board = snapshot
+            .second
+            .as_ref()
+            .is_none_or(|second| !second.body.is_empty() && second.body.iter().all(on_board));
+        // Sudden death never closes in past `MIN_SAFE_SIZE`
+        let inset = snapshot.border_inset;
+        let rings_fit = inset == 0
+            || (self.width.saturating_sub(inset.saturating_mul(2)) >= MIN_SAFE_SIZE
+                && self.height.saturating_sub(inset.saturating_mul(2)) >= MIN_SAFE_SIZE);
+        if snapshot.snake.is_empty()
+            || !snapshot.snake.iter().all(on_board)
+            || !snapshot.foods.iter().all(on_board)
+            || !timed_on_board(snapshot.bonus)
+            || !timed_on_board(snapshot.poison)
+            || !second_on_board
+            || !rings_fit
+            || snapshot.combo > MAX_COMBO
+        {
+            return Err(SnapshotError::DoesNotFit);
+        }
+
+        self.reset();
+        self.snake = snapshot.snake;
+        self.foods = snapshot.foods;
+        self.direction = snapshot.direction;
+        self.score = snapshot.score;
+        self.food_eaten = snapshot.food_eaten;
+        self.rng_state = snapshot.rng_state;
+        self.move_count = snapshot.move_count;
+        self.ticks_since_food = snapshot.ticks_since_food;
+        self.combo = snapshot.combo;
+        self.border_inset = snapshot.border_inset;
+        self.first_alive = snapshot.first_alive;
+        self.bonus = snapshot.bonus;
+        self.poison = snapshot.poison;
+        self.second = snapshot.second.map(|second| Snake {
+            body: second.body,
+            direction: second.direction,
+            direction_queue: Vec::new(),
+            alive: second.alive,
+            score: second.score,
+        });
+        self.fill_food();
+        self.state = GameState::Paused;
+        Ok(())
+    }
+
+    pub fn update(&mut self) {
+        // Nothing moves while starting, paused or after game over
+        if self.state != GameState::Playing {
+            return;
+        }
+        self.move_count = self.move_count.saturating_add(1);
+
+        // Apply one queued turn per tick
+        if !self.direction_queue.is_empty() {
+            self.direction = self.direction_queue.remove(0);
+        }
+        if let Some(second) = self.second.as_mut() {
+            if !second.direction_queue.is_empty() {
+                second.direction = second.direction_queue.remove(0);
+            }
+        }
+
+        // Heads meeting on the same cell take both snakes out
+        if let Some(second) = self.second.as_ref().filter(|second| second.alive) {
+            let first = self.next_position(self.snake[0], self.direction);
+            if self.first_alive
+                && first.is_some()
+                && first == self.next_position(second.body[0], second.direction)
+            {
+                self.first_alive = false;
+                if let Some(second) = self.second.as_mut() {
+                    second.alive = false;
+                }
+                self.state = GameState::GameOver;
+                return;
+            }
+        }
+
+        let sudden_death = self.sudden_death_ticks as u32;
+        if sudden_death > 0 && self.move_count.is_multiple_of(sudden_death) {
+            self.close_in();
+            if self.state != GameState::Playing {
+                return;
+            }
+        }
+
+        if self.first_alive {
+            self.update_first();
+            // Player one is out, but the round goes on while player two lives
+            if self.state == GameState::GameOver && self.second_alive() {
+                self.first_alive = false;
+                self.state = GameState::Playing;
+            }
+        } else {
+            // Timed food runs down with player two alone too
+            self.bonus = Self::count_down_food(self.bonus);
+            self.poison = Self::count_down_food(self.poison);
+        }
+        if self.state == GameState::Playing && self.second_alive() {
+            self.update_second();
+        }
+    }
+
+    // Player one's move: the whole game in single player
+    fn update_first(&mut self) {
+        // Calculate new head position, checking wall collision
+        let Some(new_head) = self.next_position(self.snake[0], self.direction) else {
+            self.state = GameState::GameOver;
+            return;
+        };
+
+        // Check obstacle collision, and player two's snake
+        if self.obstacles.contains(&new_head) || self.second_blocks(new_head) {
+            self.state = GameState::GameOver;
+            return;
+        }
+
+        // Check food collision
+        let eaten = self.foods.iter().position(|food| *food == new_head);
+        let ate_food = eaten.is_some();
+        let ate_bonus = self.bonus_food() == Some(new_head);
+        let ate_poison = self.poison_food() == Some(new_head);
+        let grows = ate_food || ate_bonus;
+
+        // Check self collision, unless it's turned off. The tail moves out of
+        // its cell this tick unless the snake grows, so stepping into it is legal.
+        let body = if !self.self_collision {
+            &[]
+        } else if grows {
+            &self.snake[..]
+        } else {
+            &self.snake[..self.snake.len() - 1]
+        };
+        for segment in body {
+            if new_head.x == segment.x && new_head.y == segment.y {
+                self.state = GameState::GameOver;
+                return;
+            }
+        }
+
+        // Move: the tail leaves its cell unless the snake grows, then the head
+        // moves in. A full snake can't take the new head, so the move is dropped.
+        if !grows {
+            self.snake.pop();
+        }
+        if self.snake.insert(0, new_head).is_err() {
+            return;
+        }
+        self.ticks_since_food = self.ticks_since_food.saturating_add(1);
+        if self.ticks_since_food > COMBO_WINDOW_TICKS {
+            self.combo = 0;
+        }
+
+        // Starved: the last move used up the hunger timer without reaching food
+        if !grows && self.hunger_ticks > 0 && self.ticks_since_food >= self.hunger_ticks {
+            self.state = GameState::GameOver;
+            return;
+        }
+
+        // Bonus and poison food count down every tick and disappear when they run out
+        if ate_bonus {
+            self.bonus = None;
+        } else {
+            self.bonus = Self::count_down_food(self.bonus);
+        }
+        if ate_poison {
+            self.poison = None;
+        } else {
+            self.poison = Self::count_down_food(self.poison);
+        }
+
+        if ate_poison {
+            // Too short to lose the tail segments: the poison kills
+            if self.snake.len() < MIN_LENGTH + POISON_SHRINK {
+                self.state = GameState::GameOver;
+                return;
+            }
+            self.snake.truncate(self.snake.len() - POISON_SHRINK);
+            self.score = self.scoring.apply(self.score, FoodKind::Poison, self.combo);
+            self.combo = 0;
+        }
+
+        if grows {
+            // Quick eats build the combo, a slow one starts over at 1
+            self.combo = (self.combo + 1).min(MAX_COMBO);
+            self.ticks_since_food = 0;
+            let food = if let Some(index) = eaten {
+                self.foods.remove(index);
+                FoodKind::Normal
+            } else {
+                FoodKind::Bonus
+            };
+            self.score = self.scoring.apply(self.score, food, self.combo);
+            self.food_eaten += 1;
+            if self.snake.len() >= self.max_length() {
+                self.state = GameState::Won;
+                return;
+            }
+            if ate_food {
+                self.spawn_food();
+                if self.bonus.is_none() && self.next_random().is_multiple_of(BONUS_CHANCE) {
+                    self.bonus = self.random_free_cell().map(|cell| (cell, BONUS_TICKS));
+                }
+                if self.poison_enabled
+                    && self.poison.is_none()
+                    && self.next_random().is_multiple_of(POISON_CHANCE)
+                {
+                    self.poison = self.random_free_cell().map(|cell| (cell, POISON_TICKS));
+                }
+            }
+        }
+    }
+
+    fn second_alive(&self) -> bool {
+        self.second.as_ref().is_some_and(|second| second.alive)
+    }
+
+    // Turn the next ring of cells lethal. Food there is moved inside, and a snake
+    // with any part of its body in the ring dies on the spot.
+    fn close_in(&mut self) {
+        let (width, height) = (self.width, self.height);
+        let inset = self.border_inset + 1;
+        if width.saturating_sub(2 * inset) < MIN_SAFE_SIZE
+            || height.saturating_sub(2 * inset) < MIN_SAFE_SIZE
+        {
+            return;
+        }
+        self.border_inset = inset;
+
+        let foods: Vec<Position, MAX_FOOD> = self
+            .foods
+            .iter()
+            .copied()
+            .filter(|food| self.in_safe_area(*food))
+            .collect();
+        self.foods = foods;
+        if self
+            .bonus_food()
+            .is_some_and(|bonus| !self.in_safe_area(bonus))
+        {
+            self.bonus = None;
+        }
+        if self
+            .poison_food()
+            .is_some_and(|poison| !self.in_safe_area(poison))
+        {
+            self.poison = None;
+        }
+
+        let caught = |game: &Self, body: &[Position]| {
+            body.iter().any(|segment| !game.in_safe_area(*segment))
+        };
+        if self.first_alive && caught(self, &self.snake) {
+            self.first_alive = false;
+        }
+        if let Some(mut second) = self.second.take() {
+            if second.alive && caught(self, &second.body) {
+                second.alive = false;
+            }
+            self.second = Some(second);
+        }
+        if !self.first_alive && !self.second_alive() {
+            self.state = GameState::GameOver;
+            return;
+        }
+        self.fill_food();
+    }
+
+    fn second_blocks(&self, position: Position) -> bool {
+        self.second
+            .as_ref()
+            .is_some_and(|second| second.body.contains(&position))
+    }
+
+    // Player two's move, after player one's. It eats the same food for its own
+    // score (no combo or hunger) and dies on the walls, obstacles, itself (unless
+    // self collision is off) and player one's snake. Once both are out the
+    // round is over.
+    fn update_second(&mut self) {
+        let Some(mut second) = self.second.take() else {
+            return;
+        };
+        let new_head = self
+            .next_position(second.body[0], second.direction)
+            .filter(|head| !self.obstacles.contains(head) && !self.snake.contains(head));
+        let eaten = new_head.and_then(|head| self.foods.iter().position(|food| *food == head));
+        let ate_bonus = new_head.is_some() && new_head == self.bonus_food();
+        let ate_poison = new_head.is_some() && new_head == self.poison_food();
+        let grows = eaten.is_some() || ate_bonus;
+
+        // Its own tail leaves the cell unless it grows, as for player one
+        let body_len = if grows {
+            second.body.len()
+        } else {
+            second.body.len() - 1
+        };
+        let crashed = match new_head {
+            Some(head) => self.self_collision && second.body[..body_len].contains(&head),
+            None => true,
+        };
+        let too_short = ate_poison && second.body.len() < MIN_LENGTH + POISON_SHRINK;
+
+        if let (Some(head), false, false) = (new_head, crashed, too_short) {
+            if !grows {
+                second.body.pop();
+            }
+            if second.body.insert(0, head).is_ok() {
+                // No combo for player two, every food counts once
+                if let Some(index) = eaten {
+                    self.foods.remove(index);
+                    second.score = self.scoring.apply(second.score, FoodKind::Normal, 1);
+                } else if ate_bonus {
+                    self.bonus = None;
+                    second.score = self.scoring.apply(second.score, FoodKind::Bonus, 1);
+                } else if ate_poison {
+                    self.poison = None;
+                    second.body.truncate(second.body.len() - POISON_SHRINK);
+                    second.score = self.scoring.apply(second.score, FoodKind::Poison, 1);
+                }
+            }
+        } else {
+            second.alive = false;
+            if !self.first_alive {
+                self.state = GameState::GameOver;
+            }
+        }
+
+        // Back on the board before the new food looks for a free cell
+        self.second = Some(second);
+        if eaten.is_some() {
+            self.spawn_food();
+        }
+    }
+
+    // One tick off a timed food, `None` once it runs out
+    fn count_down_food(food: Option<(Position, u16)>) -> Option<(Position, u16)> {
+        food.filter(|(_, ticks)| *ticks > 1)
+            .map(|(position, ticks)| (position, ticks - 1))
+    }
+
+    // Cell reached by moving one step from `position`, `None` when that leaves
+    // the board or its safe area. With wrapping walls it re-enters from the
+    // opposite edge of the safe area. Stepping on a portal lands on its pair,
+    // so collisions are checked on the cell the head comes out on.
+    pub fn next_position(&self, position: Position, direction: Direction) -> Option<Position> {
+        let cell = Cell::from(position).step(direction);
+
+        let inset = self.border_inset as i16;
+        let (right, bottom) = (self.width as i16 - inset, self.height as i16 - inset);
+        let step = if self.wrap_walls {
+            cell.wrapped(inset, right, bottom).position()?
+        } else if cell.within(inset, right, bottom) {
+            cell.position()?
+        } else {
+            return None;
+        };
+
+        // The pair of a portal can sit in a ring sudden death has closed
+        Some(self.through_portal(step)).filter(|cell| self.in_safe_area(*cell))
+    }
+
+    // Start the snake in the middle of the board, or on the level's spawn cell,
+    // body trailing to the left. Both snakes are checked against the walls
+    // when a level is loaded.
+    fn place_snake(&mut self) {
+        let (len, head, second_head) = self.start_heads(self.spawn);
+        self.snake.clear();
+        for i in 0..len {
+            let _ = self.snake.push(Position::new(head.x - i, head.y));
+        }
+
+        self.second = second_head.map(|head| Snake {
+            body: (0..len)
+                .map(|i| Position::new(head.x + i, head.y))
+                .collect(),
+            direction: Direction::Left,
+            direction_queue: Vec::new(),
+            alive: true,
+            score: 0,
+        });
+    }
+
+    // Length and heads of the snakes at the start of a round with `spawn`.
+    // The length is clamped to the board width, and the head shifted right
+    // when needed, so the body always fits on small boards. With two players
+    // the spawn is left out: player one starts a third of the way down and
+    // player two mirrored below it, heading left with its body to the right.
+    fn start_heads(&self, spawn: Option<Position>) -> (u8, Position, Option<Position>) {
+        let max_len = (self.width as usize).min(CAP) as u8;
+        let len = self.start_len.clamp(1, max_len.max(1));
+        let two_player = self.two_player && self.height >= 2;
+        let (start_x, start_y) = match spawn {
+            Some(spawn) if !two_player => (spawn.x.max(len - 1), spawn.y),
+            _ if two_player => ((self.width / 2).max(len - 1), (self.height - 1) / 3),
+            _ => ((self.width / 2).max(len - 1), self.height / 2),
+        };
+        let second =
+            two_player.then(|| Position::new(self.width - 1 - start_x, self.height - 1 - start_y));
+        (len, Position::new(start_x, start_y), second)
+    }
+
+    // True when `position` is under a snake at the start of a round
+    fn is_start_cell(&self, position: Position, spawn: Option<Position>) -> bool {
+        let (len, head, second_head) = self.start_heads(spawn);
+        let under = |head: Position, trailing_left: bool| {
+            let (tail_x, head_x) = if trailing_left {
+                (head.x + 1 - len, head.x)
+            } else {
+                (head.x, head.x + len - 1)
+            };
+            position.y == head.y && (tail_x..=head_x).contains(&position.x)
+        };
+        under(head, true) || second_head.is_some_and(|head| under(head, false))
+    }
+
+    fn spawn_food(&mut self) {
+        // Random attempts first, bounded by the board size
+        let attempts = self.width as u32 * self.height as u32;
+        for _attempt in 0..attempts {
+            let x = self.next_random() % self.width as u32;
+            let y = self.next_random() % self.height as u32;
+
+            let new_food = Position::new(x as u8, y as u8);
+
+            // Make sure food doesn't spawn on snake or other food
+            if self.is_free(new_food)
+                && !self.ahead_of_head(new_food)
+                && !self.reject_near_wall(new_food)
+            {
+                let _ = self.foods.push(new_food);
+                return;
+            }
+        }
+
+        // Board is nearly full: take the first free cell, in front of the head
+        // only when nothing else is left
+        let free = |game: &Self, ahead: bool| {
+            (0..game.height)
+                .flat_map(|y| (0..game.width).map(move |x| Position::new(x, y)))
+                .find(|cell| game.is_free(*cell) && game.ahead_of_head(*cell) == ahead)
+        };
+        if let Some(new_food) = free(self, false).or_else(|| free(self, true)) {
+            let _ = self.foods.push(new_food);
+            return;
+        }
+
+        // No free cell left: once the last food is gone the snake covers the whole board
+        if self.foods.is_empty() {
+            self.state = GameState::Won;
+        }
+    }
+
+    // Whether `position` is one of the `food_lookahead` cells the head is about
+    // to move through, following wrapping walls and portals
+    fn ahead_of_head(&self, position: Position) -> bool {
+        let Some(mut cell) = self.snake.first().copied() else {
+            return false;
+        };
+        for _ in 0..self.food_lookahead {
+            match self.next_position(cell, self.direction) {
+                Some(next) if next == position => return true,
+                Some(next) => cell = next,
+                None => return false,
+            }
+        }
+        false
+    }
+
+    // Wall bias roll for a spawn candidate. Only draws from the generator for
+    // cells on the outer ring with the bias on, so uniform spawning keeps the
+    // same sequence.
+    fn reject_near_wall(&mut self, position: Position) -> bool {
+        if self.wall_bias == 0 || !self.on_outer_ring(position) {
+            return false;
+        }
+        self.next_random() % 255 < self.wall_bias as u32
+    }
+
+    // Cells along the edges of the open board, inside any sudden death rings
+    fn on_outer_ring(&self, position: Position) -> bool {
+        let inset = self.border_inset;
+        position.x <= inset
+            || position.y <= inset
+            || position.x + 1 + inset >= self.width
+            || position.y + 1 + inset >= self.height
+    }
+
+    // Random free cell for the bonus or poison. They are optional, so give up
+    // quietly if none turns up.
+    fn random_free_cell(&mut self) -> Option<Position> {
+        let attempts = self.width as u32 * self.height as u32;
+        for _attempt in 0..attempts {
+            let x = self.next_random() % self.width as u32;
+            let y = self.next_random() % self.height as u32;
+
+            let position = Position::new(x as u8, y as u8);
+            if self.is_free(position) {
+                return Some(position);
+            }
+        }
+        None
+    }
+
+    // Top up the board to `food_count` items, stopping early if it is full
+    fn fill_food(&mut self) {
+        while self.foods.len() < self.food_count as usize {
+            let before = self.foods.len();
+            self.spawn_food();
+            if self.foods.len() == before {
+                break;
+            }
+        }
+    }
+
+    // Either snake
+    fn is_on_snake(&self, position: Position) -> bool {
+        self.snake.contains(&position)
+            || self
+                .second
+                .as_ref()
+                .is_some_and(|second| second.body.contains(&position))
+    }
+
+    fn is_free(&self, position: Position) -> bool {
+        !self.is_occupied(position)
+            && !self.foods.contains(&position)
+            && self.bonus_food() != Some(position)
+            && self.poison_food() != Some(position)
+            && !self.is_portal(position)
+            && self.in_safe_area(position)
+    }
+
+    // Simple LFSR random number generator
+    fn next_random(&mut self) -> u32 {
+        self.rng_state ^= self.rng_state << 13;
+        self.rng_state ^= self.rng_state >> 17;
+        self.rng_state ^= self.rng_state << 5;
+        self.rng_state
+    }
+
+    // Updates while playing since the round started, including the one that
+    // ended it. For the stats and anything lined up with the moves.
+    pub fn move_count(&self) -> u32 {
+        self.move_count
+    }
+
+    // `move_count` held at `u16::MAX`, the clock of a replay
+    pub fn ticks(&self) -> u16 {
+        self.move_count.min(u16::MAX as u32) as u16
+    }
+
+    // Current combo, 2 and up means the next food is worth more
+    pub fn combo(&self) -> u8 {
+        self.combo
+    }
+
+    pub fn game_over(&self) -> bool {
+        self.state == GameState::GameOver
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.state == GameState::Playing
+    }
+
+    pub fn is_counting_down(&self) -> bool {
+        matches!(self.state, GameState::Countdown(_))
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state == GameState::Paused
+    }
+
+    pub fn won(&self) -> bool {
+        self.state == GameState::Won
+    }
+
+    // Snake length needed to win: every cell of the safe area the body can
+    // take, so not obstacles or portals, or the snake capacity if that is smaller
+    pub fn max_length(&self) -> usize {
+        let inset = 2 * self.border_inset as usize;
+        let cells = (self.width as usize - inset) * (self.height as usize - inset);
+        let portals = self.portals.iter().flat_map(|(a, b)| [a, b]);
+        let blocked = self
+            .obstacles
+            .iter()
+            .chain(portals)
+            .filter(|cell| self.in_safe_area(**cell))
+            .count();
+        cells.saturating_sub(blocked).min(CAP)
+    }
+
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+}
+
+// Points multiplier for a combo, the first food of a streak counts once
+pub fn combo_multiplier(combo: u8) -> u16 {
+    combo.clamp(1, MAX_COMBO) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: u8, y: u8) -> Position {
+        Position::new(x, y)
+    }
+
+    // 20x12 board with the snake heading right from (10, 6), already moving
+    fn playing() -> Game {
+        let mut game: Game = Game::new(20, 12);
+        game.start();
+        game
+    }
+
+    fn with_length(start_len: u8) -> Game {
+        let mut game: Game = Game::from_config(GameConfig {
+            width: 20,
+            height: 12,
+            start_len,
+            ..GameConfig::default()
+        });
+        game.start();
+        game
+    }
+
+    fn steps(game: &mut Game, directions: &[Direction]) {
+        for direction in directions {
+            game.step(Some(*direction));
+        }
+    }
+
+    #[test]
+    fn new_game_starts_in_the_middle() {
+        let game: Game = Game::new(20, 12);
+        assert_eq!(game.snake[..], [at(10, 6), at(9, 6), at(8, 6)]);
+        assert_eq!(game.state, GameState::Starting);
+        assert_eq!(game.direction, Direction::Right);
+        assert_eq!(game.foods().len(), 1);
+        assert!(!game.snake.contains(game.food()));
+    }
+
+    #[test]
+    fn nothing_moves_before_the_start() {
+        let mut game: Game = Game::new(20, 12);
+        game.update();
+        assert_eq!(game.head(), at(10, 6));
+        assert_eq!(game.move_count(), 0);
+    }
+
+    #[test]
+    fn update_moves_the_head_and_drops_the_tail() {
+        let mut game = playing();
+        game.set_food(at(0, 0));
+        game.update();
+        assert_eq!(game.snake[..], [at(11, 6), at(10, 6), at(9, 6)]);
+        assert_eq!(game.state, GameState::Playing);
+    }
+
+    #[test]
+    fn walls_end_the_round_instead_of_wrapping_coordinates() {
+        let mut game = playing();
+        game.set_food(at(19, 11));
+        steps(&mut game, &[Direction::Up]);
+        steps(&mut game, &[Direction::Left; 10]);
+        assert_eq!(game.head(), at(0, 5));
+        assert!(game.is_playing());
+
+        game.update();
+        assert!(game.game_over());
+        assert_eq!(game.head(), at(0, 5));
+    }
+
+    #[test]
+    fn cells_off_the_board_have_no_position() {
+        let corner = Cell::from(at(0, 0));
+        assert_eq!(corner.step(Direction::Left).position(), None);
+        assert_eq!(corner.step(Direction::Up).position(), None);
+        assert_eq!(
+            corner.step(Direction::Left).wrapped(0, 20, 12).position(),
+            Some(at(19, 0))
+        );
+    }
+
+    #[test]
+    fn wrapping_walls_come_back_on_the_other_side() {
+        let mut game = playing();
+        game.set_wrap_walls(true);
+        game.set_food(at(0, 0));
+        steps(&mut game, &[Direction::Up; 7]);
+        assert!(game.is_playing());
+        assert_eq!(game.head(), at(10, 11));
+    }
+
+    #[test]
+    fn reversing_is_ignored() {
+        let mut game = playing();
+        game.set_food(at(0, 0));
+        game.set_direction(Direction::Left);
+        game.update();
+        assert_eq!(game.head(), at(11, 6));
+    }
+
+    #[test]
+    fn quick_turns_are_queued_one_per_tick() {
+        let mut game = playing();
+        game.set_food(at(0, 0));
+        game.set_direction(Direction::Up);
+        game.set_direction(Direction::Down); // Reverses the queued turn, dropped
+        game.set_direction(Direction::Left);
+        game.update();
+        assert_eq!(game.head(), at(10, 5));
+        game.update();
+        assert_eq!(game.head(), at(9, 5));
+        game.update();
+        assert_eq!(game.head(), at(8, 5));
+    }
+
+    #[test]
+    fn the_tail_cell_can_be_entered_as_it_leaves() {
+        let mut game = with_length(4);
+        game.set_food(at(0, 0));
+        steps(
+            &mut game,
+            &[Direction::Up, Direction::Left, Direction::Down],
+        );
+        assert!(game.is_playing());
+        assert_eq!(game.snake[..], [at(9, 6), at(9, 5), at(10, 5), at(10, 6)]);
+    }
+
+    #[test]
+    fn running_into_the_body_ends_the_round() {
+        let mut game = with_length(5);
+        game.set_food(at(0, 0));
+        steps(
+            &mut game,
+            &[Direction::Up, Direction::Left, Direction::Down],
+        );
+        assert!(game.game_over());
+    }
+
+    #[test]
+    fn without_self_collision_the_snake_passes_through_itself() {
+        let mut game = with_length(5);
+        game.set_self_collision(false);
+        game.set_food(at(0, 0));
+        steps(
+            &mut game,
+            &[Direction::Up, Direction::Left, Direction::Down],
+        );
+        assert!(game.is_playing());
+        assert_eq!(game.head(), at(9, 6));
+    }
+
+    #[test]
+    fn eating_grows_the_snake_and_scores() {
+        let mut game = playing();
+        game.set_food(at(11, 6));
+        game.update();
+        assert_eq!(game.snake.len(), 4);
+        assert_eq!(game.score, 10);
+        assert_eq!(game.food_eaten, 1);
+        assert_eq!(game.foods().len(), 1);
+        assert!(!game.snake.contains(game.food()));
+    }
+
+    #[test]
+    fn quick_eats_build_a_combo() {
+        let mut game: Game = Game::new(40, 22);
+        game.start();
+        game.set_food(at(21, 11));
+        game.update();
+        game.set_food(at(22, 11));
+        game.update();
+        assert_eq!(game.combo(), 2);
+        assert_eq!(game.score, 10 + 2 * 10);
+
+        game.set_food(at(0, 0));
+        for _ in 0..=COMBO_WINDOW_TICKS {
+            game.update();
+        }
+        assert!(game.is_playing());
+        assert_eq!(game.combo(), 0);
+    }
+
+    #[test]
+    fn scoring_rules_set_the_points() {
+        let rules = ScoringRules::default();
+        assert_eq!(rules.delta(FoodKind::Bonus, 3), 150);
+        assert_eq!(rules.apply(5, FoodKind::Poison, 1), 0);
+        assert_eq!(rules.apply(u16::MAX, FoodKind::Normal, 5), u16::MAX);
+
+        fn flat(_: u8) -> u16 {
+            1
+        }
+        let mut game = playing();
+        game.set_scoring(ScoringRules {
+            normal: 3,
+            combo_multiplier: flat,
+            ..ScoringRules::default()
+        });
+        game.set_food(at(11, 6));
+        game.update();
+        game.set_food(at(12, 6));
+        game.update();
+        assert_eq!(game.score, 6);
+    }
+
+    #[test]
+    fn several_foods_stay_on_the_board() {
+        let mut game = playing();
+        game.set_food_count(3);
+        assert_eq!(game.foods().len(), 3);
+        let foods = game.foods();
+        assert!(foods[0] != foods[1] && foods[1] != foods[2] && foods[0] != foods[2]);
+        assert!(foods.iter().all(|food| !game.is_occupied(*food)));
+
+        game.set_food(at(11, 6));
+        game.update();
+        assert_eq!(game.foods().len(), 3);
+
+        game.set_food_count(100);
+        assert_eq!(game.food_count(), MAX_FOOD as u8);
+    }
+
+    #[test]
+    fn bonus_food_scores_more_and_times_out() {
+        let mut game = playing();
+        game.set_food(at(0, 0));
+        game.set_bonus_food(at(11, 6));
+        game.update();
+        assert_eq!(game.score, 50);
+        assert_eq!(game.snake.len(), 4);
+        assert_eq!(game.bonus_food(), None);
+
+        let mut game = playing();
+        game.set_wrap_walls(true);
+        game.set_food(at(0, 0));
+        game.set_bonus_food(at(0, 11));
+        for _ in 1..BONUS_TICKS {
+            game.update();
+        }
+        assert_eq!(game.bonus_ticks_left(), 1);
+        game.update();
+        assert_eq!(game.bonus_food(), None);
+        assert_eq!(game.score, 0);
+    }
+
+    #[test]
+    fn poison_shrinks_the_snake_and_costs_points() {
+        let mut game = with_length(5);
+        game.score = 50;
+        game.set_food(at(0, 0));
+        game.set_poison_food(at(11, 6));
+        game.update();
+        assert!(game.is_playing());
+        assert_eq!(game.snake.len(), 3);
+        assert_eq!(game.head(), at(11, 6));
+        assert_eq!(game.score, 30);
+        assert_eq!(game.poison_food(), None);
+
+        // Points never go below 0
+        let mut game = with_length(5);
+        game.set_food(at(0, 0));
+        game.set_poison_food(at(11, 6));
+        game.update();
+        assert_eq!(game.score, 0);
+    }
+
+    #[test]
+    fn poison_kills_a_snake_too_short_to_shrink() {
+        // Down to the minimum length is still alive
+        let mut game = with_length((MIN_LENGTH + POISON_SHRINK) as u8);
+        game.set_food(at(0, 0));
+        game.set_poison_food(at(11, 6));
+        game.update();
+        assert!(game.is_playing());
+        assert_eq!(game.snake.len(), MIN_LENGTH);
+
+        let mut game = with_length((MIN_LENGTH + POISON_SHRINK - 1) as u8);
+        game.set_food(at(0, 0));
+        game.set_poison_food(at(11, 6));
+        game.update();
+        assert_eq!(game.state, GameState::GameOver);
+    }
+
+    #[test]
+    fn poison_only_spawns_when_enabled() {
+        let mut game = playing();
+        assert!(!game.poison());
+        game.set_poison(true);
+        game.set_wrap_walls(true);
+        for _ in 0..200 {
+            let ahead = game.next_position(game.head(), game.direction);
+            if let Some(ahead) = ahead {
+                game.set_food(ahead);
+            }
+            game.update();
+            if game.poison_food().is_some() {
+                break;
+            }
+        }
+        assert!(game.poison_food().is_some());
+
+        // Turning it off takes the poison off the board
+        game.set_poison(false);
+        assert_eq!(game.poison_food(), None);
+
+        let game: Game = Game::from_config(GameConfig {
+            poison: true,
+            ..GameConfig::default()
+        });
+        assert!(game.poison());
+    }
+
+    #[test]
+    fn obstacles_block_the_snake_and_the_food() {
+        let mut game = playing();
+        game.set_food(at(5, 5));
+        game.add_obstacle(at(5, 5)).add_obstacle(at(12, 6));
+        game.add_obstacle(at(9, 6)); // Under the snake, ignored
+        assert_eq!(game.obstacles(), [at(5, 5), at(12, 6)]);
+        assert!(!game.foods().contains(&at(5, 5)));
+        assert_eq!(game.foods().len(), 1);
+
+        game.update();
+        assert!(game.is_playing());
+        game.update();
+        assert!(game.game_over());
+    }
+
+    #[test]
+    fn filling_the_board_wins() {
+        let mut game: Game = Game::from_config(GameConfig {
+            width: 4,
+            height: 1,
+            ..GameConfig::default()
+        });
+        assert_eq!(game.foods(), [at(3, 0)]);
+        assert_eq!(game.max_length(), 4);
+        game.start();
+        game.update();
+        assert!(game.won());
+        assert_eq!(game.snake.len(), 4);
+    }
+
+    #[test]
+    fn a_full_board_leaves_no_food_to_spawn() {
+        let game: Game = Game::from_config(GameConfig {
+            width: 3,
+            height: 1,
+            ..GameConfig::default()
+        });
+        assert!(game.foods().is_empty());
+        assert!(game.won());
+    }
+
+    #[test]
+    fn the_win_length_leaves_out_walls_and_portals() {
+        let mut game: Game = Game::new(10, 10);
+        game.add_obstacle(at(5, 1));
+        game.add_portal(at(1, 1), at(8, 8));
+        assert_eq!(game.max_length(), 100 - 3);
+    }
+
+    #[test]
+    fn a_small_capacity_wins_at_capacity() {
+        let mut game: Game<4> = Game::new(10, 10);
+        assert_eq!(game.max_length(), 4);
+        game.start();
+        game.set_food(at(6, 5));
+        game.update();
+        assert!(game.won());
+    }
+
+    #[test]
+    fn the_config_sets_the_food_count_and_sudden_death() {
+        let game: Game = Game::from_config(GameConfig {
+            width: 20,
+            height: 12,
+            food_count: 4,
+            sudden_death_ticks: 10,
+            ..GameConfig::default()
+        });
+        assert_eq!(game.foods().len(), 4);
+        assert_eq!(game.sudden_death(), 10);
+
+        let game: Game = Game::from_config(GameConfig {
+            food_count: 0,
+            ..GameConfig::default()
+        });
+        assert_eq!(game.food_count(), 1);
+    }
+
+    #[test]
+    fn the_seed_decides_the_food() {
+        assert_eq!(
+            Game::<SNAKE_CAPACITY>::with_seed(20, 12, 0).seed(),
+            DEFAULT_SEED
+        );
+        let a: Game = Game::with_seed(20, 12, 42);
+        let b: Game = Game::with_seed(20, 12, 42);
+        assert_eq!(a.foods(), b.foods());
+
+        let mut game = playing();
+        game.reseed(0);
+        assert_eq!(game.seed(), DEFAULT_SEED);
+        assert_ne!(game.next_random(), 0);
+    }
+
+    #[test]
+    fn preserved_seed_replays_the_food() {
+        let mut game: Game = Game::new(20, 12);
+        game.set_restart_seed(RestartSeed::Preserve);
+        let first = *game.food();
+        game.start();
+        game.set_food(at(11, 6));
+        game.update();
+        game.reset();
+        assert_eq!(*game.food(), first);
+    }
+
+    #[test]
+    fn start_length_is_kept_on_the_board() {
+        assert_eq!(with_length(5).snake.len(), 5);
+
+        let narrow: Game = Game::from_config(GameConfig {
+            width: 2,
+            height: 1,
+            start_len: 5,
+            ..GameConfig::default()
+        });
+        assert_eq!(narrow.snake[..], [at(1, 0), at(0, 0)]);
+
+        let empty: Game = Game::new(0, 0);
+        assert_eq!((empty.width(), empty.height()), (1, 1));
+        assert_eq!(empty.snake.len(), 1);
+    }
+
+    #[test]
+    fn step_turns_on_the_same_tick() {
+        let mut game = playing();
+        game.set_food(at(0, 0));
+        game.set_direction(Direction::Down);
+        game.step(Some(Direction::Up));
+        assert_eq!(game.head(), at(10, 5));
+        game.step(Some(Direction::Down));
+        assert_eq!(game.head(), at(10, 4));
+        game.step(None);
+        assert_eq!(game.head(), at(10, 3));
+    }
+
+    #[test]
+    fn hunger_ends_the_round_without_food() {
+        let mut game = playing();
+        game.set_hunger_ticks(5);
+        game.set_food(at(12, 6));
+        game.update();
+        game.update();
+        assert_eq!(game.hunger_left(), Some(5));
+
+        game.set_food(at(0, 0));
+        for _ in 0..4 {
+            game.update();
+        }
+        assert_eq!(game.hunger_left(), Some(1));
+        game.update();
+        assert!(game.game_over());
+    }
+
+    #[test]
+    fn two_players_each_have_a_snake() {
+        let mut game: Game = Game::new(20, 12);
+        game.set_two_player(true);
+        let second = game.second_snake().unwrap();
+        assert_eq!(game.head(), at(10, 3));
+        assert_eq!(second.body[..], [at(9, 8), at(10, 8), at(11, 8)]);
+        assert_eq!(second.direction, Direction::Left);
+
+        // Player two turns up into the top wall and goes out first, the round
+        // lasts until player one hits the right wall
+        game.start();
+        game.set_food(at(0, 11));
+        game.turn_second(true);
+        for _ in 0..9 {
+            game.update();
+        }
+        assert!(!game.second_snake().unwrap().alive);
+        assert!(game.is_playing());
+        game.update();
+        assert!(game.game_over());
+    }
+
+    #[test]
+    fn sudden_death_closes_in_down_to_the_minimum() {
+        let mut game = playing();
+        game.set_sudden_death(2);
+        game.set_food(at(0, 0));
+        game.update();
+        game.update();
+        assert_eq!(game.border_inset(), 1);
+        assert!(!game.in_safe_area(at(0, 0)));
+        assert!(game.foods().iter().all(|food| game.in_safe_area(*food)));
+
+        for _ in 0..4 {
+            game.update();
+        }
+        assert_eq!(game.border_inset(), 3);
+        assert!(game.is_playing());
+        game.update();
+        assert!(game.game_over());
+        assert_eq!(game.border_inset(), 3);
+    }
+
+    #[test]
+    fn portals_move_the_head_to_their_pair() {
+        let mut game = playing();
+        game.set_food(at(0, 0));
+        game.add_portal(at(12, 6), at(3, 2));
+        game.update();
+        game.update();
+        assert_eq!(game.head(), at(3, 2));
+        game.update();
+        assert_eq!(game.head(), at(4, 2));
+        assert!(game.foods().iter().all(|food| !game.is_portal(*food)));
+    }
+
+    #[test]
+    fn difficulty_scales_the_pace() {
+        let config = GameConfig::default();
+        let easy = Difficulty::Easy.config(config);
+        assert_eq!((easy.frame_time_ms, easy.wrap_walls), (200, true));
+        let hard = Difficulty::Hard.config(config);
+        assert_eq!((hard.frame_time_ms, hard.speed_step_ms), (100, 6));
+        assert!(!hard.wrap_walls);
+        assert_eq!(Difficulty::Hard.next(), Difficulty::Easy);
+        assert_eq!(Difficulty::Easy.previous(), Difficulty::Hard);
+
+        let mut game: Game = Game::new(20, 12);
+        game.open_difficulty_menu(Difficulty::Hard);
+        assert_eq!(game.state, GameState::DifficultyMenu(Difficulty::Hard));
+        game.close_difficulty_menu();
+        assert_eq!(game.state, GameState::Starting);
+    }
+
+    #[test]
+    fn countdown_leads_into_play() {
+        let mut game: Game = Game::new(20, 12);
+        game.start_countdown(3);
+        assert_eq!(game.state, GameState::Countdown(3));
+        game.update();
+        assert_eq!(game.head(), at(10, 6));
+        game.count_down();
+        game.count_down();
+        assert_eq!(game.state, GameState::Countdown(1));
+        game.count_down();
+        assert!(game.is_playing());
+
+        let mut game: Game = Game::new(20, 12);
+        game.start_countdown(0);
+        assert!(game.is_playing());
+    }
+
+    #[test]
+    fn pausing_freezes_the_snake() {
+        let mut game = playing();
+        game.set_food(at(0, 0));
+        game.toggle_pause();
+        game.update();
+        assert!(game.is_paused());
+        assert_eq!((game.head(), game.move_count()), (at(10, 6), 0));
+        game.toggle_pause();
+        game.update();
+        assert_eq!((game.head(), game.move_count()), (at(11, 6), 1));
+    }
+
+    #[test]
+    fn wall_bias_keeps_food_off_the_edges() {
+        let mut game: Game = Game::new(20, 12);
+        game.set_wall_bias(255);
+        for seed in 1..200 {
+            game.reseed(seed);
+            assert!(!game.on_outer_ring(*game.food()), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn food_never_spawns_just_ahead_of_the_head() {
+        let mut game: Game = Game::new(20, 12);
+        game.set_food_lookahead(3);
+        for seed in 1..500 {
+            game.reseed(seed);
+            assert!(![at(11, 6), at(12, 6), at(13, 6)].contains(game.food()));
+        }
+    }
+
+    #[test]
+    fn occupancy_covers_snake_and_walls() {
+        let mut game: Game = Game::new(20, 12);
+        game.add_obstacle(at(2, 2));
+        game.set_food(at(4, 4));
+        assert!(game.is_occupied(at(8, 6)));
+        assert!(game.is_occupied(at(2, 2)));
+        assert!(!game.is_occupied(at(4, 4)));
+        assert_eq!(game.food_at(at(4, 4)), Some(FoodKind::Normal));
+        assert_eq!(game.body().last(), Some(at(8, 6)));
+        assert_eq!(game.body().count(), 3);
+    }
+
+    #[test]
+    fn moves_are_counted() {
+        let mut game = playing();
+        game.set_food(at(0, 0));
+        for _ in 0..3 {
+            game.update();
+        }
+        assert_eq!((game.move_count(), game.ticks()), (3, 3));
+        game.reset();
+        assert_eq!(game.move_count(), 0);
     }
 }
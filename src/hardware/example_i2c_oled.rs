@@ -1,58 +1,139 @@
 // Example implementation for a different hardware configuration:
-// I2C OLED display + keyboard input (for desktop/web)
-// 
+// SSD1306 128x64 I2C OLED display + keyboard input (for desktop/web)
+//
 // This demonstrates how the same game logic can work with
-// completely different hardware interfaces
+// completely different hardware interfaces. The display is real (built with
+// the `oled` feature), the input and platform are still stubs.
 
 #![allow(dead_code)]
 
-use crate::game::{GameState, Position};
-use crate::traits::{Color, GameDisplay, GameInput, GamePlatform, GameRenderer, InputEvent};
+use embassy_rp::i2c::{self, I2c};
+use embassy_rp::peripherals::I2C0;
+use embedded_graphics::{
+    image::ImageRaw,
+    mono_font::MonoTextStyle,
+    pixelcolor::{BinaryColor, Rgb565},
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+};
+use ssd1306::mode::BufferedGraphicsMode;
+use ssd1306::prelude::*;
+use ssd1306::{I2CDisplayInterface, Ssd1306};
+
+use snake_embedded::game::{Difficulty, Game, GameState, Position};
+use snake_embedded::renderer::{fit_grid, GridError};
+use snake_embedded::scene::CHAR_HEIGHT;
+use snake_embedded::screensaver::Screensaver;
+use snake_embedded::snapshot::GameSnapshot;
+use snake_embedded::traits::{
+    Color, Dither, Font, GameDisplay, GameInput, GamePlatform, GameRenderer, GameStorage,
+    InputEvents, Leaderboard, Theme,
+};
+
+// The panel on I2C0, drawn into a RAM framebuffer that `update` sends over
+pub type OledDisplay = Ssd1306<
+    I2CInterface<I2c<'static, I2C0, i2c::Blocking>>,
+    DisplaySize128x64,
+    BufferedGraphicsMode<DisplaySize128x64>,
+>;
+
+const OLED_WIDTH: u16 = 128;
+const OLED_HEIGHT: u16 = 64;
 
-// Example for SSD1306 I2C OLED display
 pub struct I2COLEDDisplay {
-    // display: SSD1306<...>, // Would contain actual I2C display driver
-    width: u16,
-    height: u16,
-    cell_size: u16,
+    display: OledDisplay,
 }
 
 impl I2COLEDDisplay {
-    pub fn new(width: u16, height: u16, cell_size: u16) -> Self {
-        Self { width, height, cell_size }
+    // Takes the bus at the panel's default address (0x3C) and initializes it
+    pub fn new(i2c: I2c<'static, I2C0, i2c::Blocking>) -> Self {
+        let interface = I2CDisplayInterface::new(i2c);
+        let mut display = Ssd1306::new(interface, DisplaySize128x64, DisplayRotation::Rotate0)
+            .into_buffered_graphics_mode();
+        display.init().ok();
+        Self { display }
+    }
+
+    // One bit per pixel: anything but black is lit
+    fn pixel(color: Color) -> BinaryColor {
+        if color == Color::BLACK {
+            BinaryColor::Off
+        } else {
+            BinaryColor::On
+        }
     }
 }
 
 impl GameDisplay for I2COLEDDisplay {
     type Error = ();
-    
+
     fn dimensions(&self) -> (u16, u16) {
-        (self.width, self.height)
+        (OLED_WIDTH, OLED_HEIGHT)
     }
-    
-    fn clear(&mut self, _color: Color) -> Result<(), Self::Error> {
-        // Clear OLED display buffer
-        // self.display.clear();
+
+    fn clear(&mut self, color: Color) -> Result<(), Self::Error> {
+        self.display.clear(Self::pixel(color)).ok();
         Ok(())
     }
-    
-    fn draw_rect(&mut self, _x: u16, _y: u16, _width: u16, _height: u16, _color: Color) -> Result<(), Self::Error> {
-        // Draw rectangle on OLED
-        // self.display.fill_rect(x, y, width, height, color.into())?;
+
+    fn draw_rect(
+        &mut self,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        color: Color,
+    ) -> Result<(), Self::Error> {
+        Rectangle::new(
+            Point::new(x as i32, y as i32),
+            Size::new(width as u32, height as u32),
+        )
+        .into_styled(PrimitiveStyle::with_fill(Self::pixel(color)))
+        .draw(&mut self.display)
+        .ok();
         Ok(())
     }
-    
-    fn draw_text(&mut self, _text: &str, _x: u16, _y: u16, _color: Color) -> Result<(), Self::Error> {
-        // Draw text on OLED
-        // self.display.draw_text(text, x, y, font, color.into())?;
+
+    fn draw_text(&mut self, text: &str, x: u16, y: u16, color: Color) -> Result<(), Self::Error> {
+        self.draw_text_in(text, x, y, color, Font::Small)
+    }
+
+    fn draw_text_in(
+        &mut self,
+        text: &str,
+        x: u16,
+        y: u16,
+        color: Color,
+        font: Font,
+    ) -> Result<(), Self::Error> {
+        let text_style = MonoTextStyle::new(font.mono_font(), Self::pixel(color));
+        // (x, y) is the top-left corner of the text, like draw_rect
+        Text::with_baseline(
+            text,
+            Point::new(x as i32, y as i32),
+            text_style,
+            Baseline::Top,
+        )
+        .draw(&mut self.display)
+        .ok();
         Ok(())
     }
-    
-    fn update(&mut self) -> Result<(), Self::Error> {
-        // Flush buffer to display
-        // self.display.flush()?;
+
+    // No color to show a sprite in: the outline of its box, like the food cell
+    fn draw_image(&mut self, x: u16, y: u16, image: &ImageRaw<Rgb565>) -> Result<(), Self::Error> {
+        let size = image.size();
+        Rectangle::new(Point::new(x as i32, y as i32), size)
+            .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+            .draw(&mut self.display)
+            .ok();
         Ok(())
     }
+
+    fn update(&mut self) -> Result<(), Self::Error> {
+        // Send the framebuffer, the panel shows nothing until then
+        self.display.flush().map_err(|_| ())
+    }
 }
 
 // Example keyboard input (could be used for desktop/web versions)
@@ -68,18 +149,25 @@ impl KeyboardInput {
 
 impl GameInput for KeyboardInput {
     type Error = ();
-    
-    async fn read_input(&mut self) -> Result<InputEvent, Self::Error> {
+
+    fn poll_input(&mut self) -> Result<InputEvents, Self::Error> {
         // Read from keyboard/stdin
-        // match read_key() {
-        //     'w' | 'W' => Ok(InputEvent::Direction(Direction::Up)),
-        //     's' | 'S' => Ok(InputEvent::Direction(Direction::Down)),
-        //     'a' | 'A' => Ok(InputEvent::Direction(Direction::Left)),
-        //     'd' | 'D' => Ok(InputEvent::Direction(Direction::Right)),
-        //     ' ' => Ok(InputEvent::ButtonA),
-        //     _ => Ok(InputEvent::None),
+        // let mut events = InputEvents::new();
+        // while let Some(key) = read_key() {
+        //     let event = match key {
+        //         'w' | 'W' => InputEvent::Direction(Direction::Up),
+        //         's' | 'S' => InputEvent::Direction(Direction::Down),
+        //         'a' | 'A' => InputEvent::Direction(Direction::Left),
+        //         'd' | 'D' => InputEvent::Direction(Direction::Right),
+        //         'r' | 'R' => InputEvent::ButtonA,
+        //         ' ' => InputEvent::ButtonB,
+        //         _ => continue,
+        //     };
+        //     if events.push(event).is_err() {
+        //         break;
+        //     }
         // }
-        Ok(InputEvent::None)
+        Ok(InputEvents::new())
     }
 }
 
@@ -99,96 +187,291 @@ impl GamePlatform for DesktopPlatform {
         // For desktop: std::thread::sleep(Duration::from_millis(ms));
         // For async: tokio::time::sleep(Duration::from_millis(ms)).await;
     }
-    
+
     fn current_time_ms(&self) -> u32 {
         // self.start_time.elapsed().as_millis() as u32
         0
     }
 }
 
+// RAM-only storage: the scores survive restarts but not power cycles
+pub struct VolatileStorage {
+    high_score: u16,
+    scores: Leaderboard,
+    muted: bool,
+    snapshot: Option<GameSnapshot>,
+}
+
+impl VolatileStorage {
+    pub fn new() -> Self {
+        Self {
+            high_score: 0,
+            scores: Leaderboard::new(),
+            muted: false,
+            snapshot: None,
+        }
+    }
+}
+
+impl GameStorage for VolatileStorage {
+    type Error = ();
+
+    fn load_high_score(&mut self) -> Result<u16, Self::Error> {
+        Ok(self.high_score)
+    }
+
+    fn save_high_score(&mut self, score: u16) -> Result<(), Self::Error> {
+        self.high_score = score;
+        Ok(())
+    }
+
+    fn load_scores(&mut self) -> Result<Leaderboard, Self::Error> {
+        Ok(self.scores.clone())
+    }
+
+    fn save_scores(&mut self, scores: &Leaderboard) -> Result<(), Self::Error> {
+        self.scores = scores.clone();
+        Ok(())
+    }
+
+    fn load_muted(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.muted)
+    }
+
+    fn save_muted(&mut self, muted: bool) -> Result<(), Self::Error> {
+        self.muted = muted;
+        Ok(())
+    }
+
+    fn load_snapshot(&mut self) -> Result<Option<GameSnapshot>, Self::Error> {
+        Ok(self.snapshot.clone())
+    }
+
+    fn save_snapshot(&mut self, snapshot: Option<&GameSnapshot>) -> Result<(), Self::Error> {
+        self.snapshot = snapshot.cloned();
+        Ok(())
+    }
+}
+
 // Renderer for the I2C OLED setup
 pub struct I2COLEDRenderer {
     display: I2COLEDDisplay,
     cell_size: u16,
+    // Color palette, turned into fill patterns so the head (solid), body
+    // (checkerboard) and food (outline) stay apart on the monochrome panel
+    theme: Theme,
+    // Keeps a still end screen from burning into the panel
+    screensaver: Screensaver,
 }
 
 impl I2COLEDRenderer {
     pub fn new(display: I2COLEDDisplay, cell_size: u16) -> Self {
-        Self { display, cell_size }
+        Self {
+            display,
+            cell_size,
+            theme: Theme::classic(),
+            screensaver: Screensaver::new(),
+        }
+    }
+
+    fn draw_pattern_rect(
+        &mut self,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        color: Color,
+    ) -> Result<(), ()> {
+        let lit = self.theme.text;
+        match Dither::for_color(color) {
+            Dither::Solid => self.display.draw_rect(x, y, width, height, lit),
+            Dither::Checkerboard => {
+                for dy in 0..height {
+                    for dx in ((dy % 2)..width).step_by(2) {
+                        self.display.draw_pixel(x + dx, y + dy, lit)?;
+                    }
+                }
+                Ok(())
+            }
+            Dither::Outline => {
+                self.display.draw_rect(x, y, width, 1, lit)?;
+                self.display.draw_rect(x, y + height - 1, width, 1, lit)?;
+                self.display.draw_rect(x, y, 1, height, lit)?;
+                self.display.draw_rect(x + width - 1, y, 1, height, lit)
+            }
+            Dither::Empty => Ok(()),
+        }
+    }
+
+    fn draw_cell(&mut self, position: Position, color: Color) -> Result<(), ()> {
+        let size = self.cell_size;
+        self.draw_pattern_rect(
+            position.x as u16 * size,
+            position.y as u16 * size,
+            size,
+            size,
+            color,
+        )
+    }
+
+    // Board size in cells, 128 / cell_size by 64 / cell_size (32x16 with 4 px cells)
+    pub fn grid_size(&self) -> Result<(u8, u8), GridError> {
+        let (width, height) = self.display.dimensions();
+        fit_grid(width, height, self.cell_size)
     }
 }
 
 impl GameRenderer for I2COLEDRenderer {
     type Error = ();
-    
-    fn render_game(&mut self, 
-                   snake: &[Position], 
-                   food: &Position, 
-                   score: u16, 
-                   state: GameState,
-                   _grid_width: u8,
-                   _grid_height: u8) -> Result<(), Self::Error> {
-        
-        self.display.clear(Color::BLACK)?;
-        
-        match state {
+
+    fn render_game(&mut self, game: &Game) -> Result<(), Self::Error> {
+        let score = game.score;
+        // Every frame is drawn from scratch, the screensaver only has to start over next time
+        self.screensaver.reset();
+
+        match game.state {
+            GameState::Starting => return self.render_menu(0),
+            GameState::DifficultyMenu(selected) => return self.render_difficulty(selected),
+            GameState::Countdown(n) => return self.render_countdown(n),
+            GameState::Paused => return self.render_pause(score, game.food_eaten),
+            _ => {}
+        }
+
+        // The OLED is buffered, so redrawing everything each frame doesn't flicker
+        self.display.clear(self.theme.background)?;
+
+        match game.state {
             GameState::Playing => {
-                // Draw snake segments
-                for segment in snake {
-                    self.display.draw_rect(
-                        segment.x as u16 * self.cell_size,
-                        segment.y as u16 * self.cell_size,
-                        self.cell_size,
-                        self.cell_size,
-                        Color::WHITE, // OLED is monochrome
-                    )?;
+                // Draw snake segments, the head in its own pattern
+                for (i, segment) in game.body().enumerate() {
+                    let color = if i == 0 {
+                        self.theme.snake_head
+                    } else {
+                        self.theme.snake
+                    };
+                    self.draw_cell(segment, color)?;
                 }
-                
+
                 // Draw food
-                self.display.draw_rect(
-                    food.x as u16 * self.cell_size,
-                    food.y as u16 * self.cell_size,
-                    self.cell_size,
-                    self.cell_size,
-                    Color::WHITE,
-                )?;
-                
+                for food in game.foods() {
+                    self.draw_cell(*food, self.theme.food)?;
+                }
+
                 // Draw score
                 let mut score_text = heapless::String::<32>::new();
                 core::fmt::write(&mut score_text, format_args!("Score: {}", score)).unwrap();
-                self.display.draw_text(&score_text, 0, 0, Color::WHITE)?;
+                self.display.draw_text(&score_text, 0, 0, self.theme.text)?;
             }
+            // Drawn by the menu hooks
+            GameState::Starting
+            | GameState::DifficultyMenu(_)
+            | GameState::Countdown(_)
+            | GameState::Paused => {}
             GameState::GameOver => {
-                self.display.draw_text("GAME OVER", 32, 16, Color::WHITE)?;
-                
+                self.display
+                    .draw_text_centered("GAME OVER", 16, self.theme.text)?;
+
                 let mut final_score = heapless::String::<32>::new();
                 core::fmt::write(&mut final_score, format_args!("Score: {}", score)).unwrap();
-                self.display.draw_text(&final_score, 32, 32, Color::WHITE)?;
-                
-                self.display.draw_text("Press SPACE", 32, 48, Color::WHITE)?;
+                self.display
+                    .draw_text_centered(&final_score, 32, self.theme.text)?;
+
+                self.display
+                    .draw_text_centered("Press R", 48, self.theme.text)?;
+            }
+            GameState::Won => {
+                self.display
+                    .draw_text_centered("YOU WIN!", 16, self.theme.text)?;
+
+                let mut final_score = heapless::String::<32>::new();
+                core::fmt::write(&mut final_score, format_args!("Score: {}", score)).unwrap();
+                self.display
+                    .draw_text_centered(&final_score, 32, self.theme.text)?;
+
+                self.display
+                    .draw_text_centered("Press R", 48, self.theme.text)?;
             }
         }
-        
+
         self.display.update()?;
         Ok(())
     }
+
+    fn render_screensaver(&mut self, t_ms: u32) -> Result<(), Self::Error> {
+        let theme = self.theme;
+        self.screensaver.step(&mut self.display, t_ms, &theme)
+    }
+
+    // Static, the panel is redrawn from scratch every frame anyway
+    fn render_menu(&mut self, _t_ms: u32) -> Result<(), Self::Error> {
+        self.display.clear(self.theme.background)?;
+        self.display
+            .draw_text_centered("Press SPACE", 32, self.theme.text)?;
+        self.display.update()
+    }
+
+    fn render_difficulty(&mut self, selected: Difficulty) -> Result<(), Self::Error> {
+        self.display.clear(self.theme.background)?;
+        for (i, difficulty) in Difficulty::ALL.iter().enumerate() {
+            // No color to spare on the one bit panel, an arrow marks the choice
+            let mut text = heapless::String::<16>::new();
+            let marker = if *difficulty == selected { ">" } else { " " };
+            core::fmt::write(&mut text, format_args!("{} {}", marker, difficulty.name())).unwrap();
+            self.display
+                .draw_text(&text, 32, 12 + i as u16 * 14, self.theme.text)?;
+        }
+        self.display.update()
+    }
+
+    fn render_pause(&mut self, score: u16, _food_eaten: u16) -> Result<(), Self::Error> {
+        self.display.clear(self.theme.background)?;
+        self.display
+            .draw_text_centered("PAUSED", 16, self.theme.text)?;
+
+        let mut score_text = heapless::String::<32>::new();
+        core::fmt::write(&mut score_text, format_args!("Score: {}", score)).unwrap();
+        self.display
+            .draw_text_centered(&score_text, 32, self.theme.text)?;
+        self.display.update()
+    }
+
+    fn render_countdown(&mut self, n: u8) -> Result<(), Self::Error> {
+        self.display.clear(self.theme.background)?;
+
+        let mut text = heapless::String::<4>::new();
+        core::fmt::write(&mut text, format_args!("{}", n)).unwrap();
+        // Centered with the 6x10 font metrics
+        let (_, height) = self.display.dimensions();
+        let y = height.saturating_sub(CHAR_HEIGHT) / 2;
+        self.display.draw_text_centered(&text, y, self.theme.text)?;
+        self.display.update()
+    }
 }
 
 /*
 Usage example for this alternative hardware:
 
-let oled_display = I2COLEDDisplay::new(128, 64, 4);
+// SSD1306 on I2C0: GP4 is SDA, GP5 is SCL (build with `--features oled`)
+let mut i2c_config = embassy_rp::i2c::Config::default();
+i2c_config.frequency = 400_000;
+let i2c = I2c::new_blocking(p.I2C0, p.PIN_5, p.PIN_4, i2c_config);
+
+let oled_display = I2COLEDDisplay::new(i2c);
 let keyboard_input = KeyboardInput::new();
 let desktop_platform = DesktopPlatform::new();
 let oled_renderer = I2COLEDRenderer::new(oled_display, 4);
+let (grid_width, grid_height) = oled_renderer.grid_size().unwrap(); // 32x16
 
 let mut engine = GameEngine::new(
     keyboard_input,
     desktop_platform,
     oled_renderer,
-    32, // 128/4 = 32 cells wide
-    16, // 64/4 = 16 cells tall
-);
+    grid_width,
+    grid_height,
+)
+.with_audio(SilentAudio) // from traits.rs
+.with_storage(VolatileStorage::new())
+.with_backlight(FixedBacklight); // from traits.rs
 
 engine.run().await?;
-*/
\ No newline at end of file
+*/
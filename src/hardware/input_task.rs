@@ -0,0 +1,93 @@
+// Input sampled in a task of its own (`input-task` feature)
+//
+// The sampler task reads the joystick and buttons every 10 ms and queues what
+// it saw, and `ChannelInput` hands the queue to the engine, so the engine can
+// run on the same task-based input a hand-written loop would use.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::{Channel, Receiver, TrySendError};
+use embassy_time::{Duration, Timer};
+use snake_embedded::traits::{GameInput, InputEvent, InputEvents};
+
+#[cfg(feature = "digital-joystick")]
+use super::pico_waveshare::PicoButtonInput;
+#[cfg(not(feature = "digital-joystick"))]
+use super::pico_waveshare::PicoWaveshareInput;
+
+// The board's input, whichever joystick the build reads
+#[cfg(not(feature = "digital-joystick"))]
+pub type PicoInput = PicoWaveshareInput;
+#[cfg(feature = "digital-joystick")]
+pub type PicoInput = PicoButtonInput;
+
+// Events queued between the sampler task and the engine
+pub const INPUT_QUEUE: usize = 10;
+pub type InputChannel = Channel<CriticalSectionRawMutex, InputEvent, INPUT_QUEUE>;
+
+// Button A as of the last sample, for `button_a_held`
+static BUTTON_A_HELD: AtomicBool = AtomicBool::new(false);
+
+// How often the sampler task reads the joystick and buttons
+const INPUT_SAMPLE_PERIOD: Duration = Duration::from_millis(10);
+
+// Reads the input in its own task, faster than the engine's frames, so short
+// presses aren't missed while a frame draws. A full queue drops its oldest
+// event rather than holding the sampler up.
+#[embassy_executor::task]
+pub async fn input_sampler(mut input: PicoInput, events: &'static InputChannel) {
+    loop {
+        for event in input.poll_input().unwrap_or_default() {
+            if let Err(TrySendError::Full(event)) = events.try_send(event) {
+                events.try_receive().ok();
+                events.try_send(event).ok();
+            }
+        }
+        BUTTON_A_HELD.store(input.button_a_held(), Ordering::Relaxed);
+        Timer::after(INPUT_SAMPLE_PERIOD).await;
+    }
+}
+
+// Engine side of the sampler task: hands over the queued events
+pub struct ChannelInput {
+    events: Receiver<'static, CriticalSectionRawMutex, InputEvent, INPUT_QUEUE>,
+}
+
+impl ChannelInput {
+    pub fn new(events: &'static InputChannel) -> Self {
+        Self {
+            events: events.receiver(),
+        }
+    }
+}
+
+impl GameInput for ChannelInput {
+    type Error = ();
+
+    // As many queued events as a frame takes, the rest wait for the next frame
+    fn poll_input(&mut self) -> Result<InputEvents, Self::Error> {
+        let mut events = InputEvents::new();
+        while !events.is_full() {
+            let Ok(event) = self.events.try_receive() else {
+                break;
+            };
+            events.push(event).ok();
+        }
+        Ok(events)
+    }
+
+    async fn read_input(&mut self) -> Result<InputEvents, Self::Error> {
+        self.events.ready_to_receive().await;
+        self.poll_input()
+    }
+
+    async fn wait_for_input(&mut self) -> bool {
+        self.events.ready_to_receive().await;
+        true
+    }
+
+    fn button_a_held(&self) -> bool {
+        BUTTON_A_HELD.load(Ordering::Relaxed)
+    }
+}
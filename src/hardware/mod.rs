@@ -1,2 +1,7 @@
 pub mod pico_waveshare;
-pub mod example_i2c_oled;
\ No newline at end of file
+// Sampler task and channel input, enabled by the `input-task` feature
+#[cfg(feature = "input-task")]
+pub mod input_task;
+// Needs the ssd1306 driver, enabled by the `oled` feature
+#[cfg(feature = "oled")]
+pub mod example_i2c_oled;
@@ -1,101 +1,398 @@
-use crate::game::{Direction, GameState, Position};
-use crate::traits::{Color, GameDisplay, GameInput, GamePlatform, GameRenderer, InputEvent};
+use snake_embedded::game::Direction;
+use snake_embedded::renderer::GridRenderer;
+use snake_embedded::snapshot::GameSnapshot;
+#[cfg(feature = "buzzer")]
+use snake_embedded::traits::GameAudio;
+use snake_embedded::traits::{
+    joystick_to_direction, Color, Font, GameBacklight, GameDisplay, GameInput, GamePlatform,
+    GameStorage, InputEvent, InputEvents, Leaderboard, ScoreEntry, LEADERBOARD_SIZE,
+};
+
+use core::cell::RefCell;
 
+use embassy_embedded_hal::shared_bus::blocking::spi::SpiDeviceWithConfig;
+use embassy_futures::select::{select, select_array};
 use embassy_rp::adc::{Adc, Channel};
+#[cfg(feature = "buzzer")]
+use embassy_rp::clocks::clk_sys_freq;
+use embassy_rp::flash::{Blocking, Flash, ERASE_SIZE};
 use embassy_rp::gpio::{Input, Output};
+use embassy_rp::peripherals::{FLASH, SPI1};
+use embassy_rp::pwm::{Config as PwmConfig, Pwm};
+use embassy_rp::spi::{self, Spi};
+use embassy_rp::watchdog::Watchdog;
+use embassy_sync::blocking_mutex::raw::{CriticalSectionRawMutex, NoopRawMutex};
+use embassy_sync::blocking_mutex::Mutex as BlockingMutex;
 use embassy_time::{Duration, Instant, Timer};
 
 use embedded_graphics::{
-    mono_font::{ascii::FONT_6X10, MonoTextStyle},
+    image::{Image, ImageRaw},
+    mono_font::MonoTextStyle,
     pixelcolor::Rgb565,
     prelude::*,
-    primitives::{PrimitiveStyle, Rectangle},
-    text::Text,
+    primitives::{Circle, Line, PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
 };
-use mipidsi::Display;
 use mipidsi::interface::SpiInterface;
-use mipidsi::models::ST7789;
-use embassy_embedded_hal::adapter::BlockingAsync;
+#[cfg(feature = "st7735")]
+use mipidsi::options::ColorOrder;
+use mipidsi::options::{ColorInversion, Orientation, Rotation};
+use mipidsi::{Builder, Display, NoResetPin};
 
-// Type alias to simplify the complex Display type
-type MipiDisplay = Display<
-    SpiInterface<'static, BlockingAsync<embassy_rp::spi::Spi<'static, embassy_rp::peripherals::SPI0, embassy_rp::spi::Blocking>>, Output<'static>>,
-    ST7789,
-    Output<'static>
+// Display controller, the ST7789 of the Waveshare Pico LCD 1.14 unless the
+// `st7735` feature picks the 128x160 ST7735S hats
+#[cfg(feature = "st7735")]
+pub use mipidsi::models::ST7735s as PanelModel;
+#[cfg(not(feature = "st7735"))]
+pub use mipidsi::models::ST7789 as PanelModel;
+
+// mipidsi 0.9 only drives the bus through the blocking embedded-hal traits, so
+// SPI1 runs in blocking mode: a DMA channel would never carry a byte
+pub type PicoSpiMode = spi::Blocking;
+
+// The LCD sits on SPI1, shared through a blocking mutex so CS is handled per transfer
+pub type PicoSpiBus = BlockingMutex<NoopRawMutex, RefCell<Spi<'static, SPI1, PicoSpiMode>>>;
+
+// The ADC reads the analog joystick and VSYS, shared the same way as the SPI bus
+pub type PicoAdc = BlockingMutex<NoopRawMutex, RefCell<Adc<'static, embassy_rp::adc::Blocking>>>;
+
+// Hardware watchdog, fed by the platform every frame and around the blocking
+// operations that can run long (full clears, flash erases). Empty until
+// `start_watchdog`, so feeding is a no-op in builds without the `watchdog` feature.
+static WATCHDOG: BlockingMutex<CriticalSectionRawMutex, RefCell<Option<Watchdog>>> =
+    BlockingMutex::new(RefCell::new(None));
+
+// Reset the chip when the watchdog isn't fed within `timeout`. Paused while a
+// probe halts the cores, so stepping through the code doesn't trip it.
+#[allow(dead_code)]
+pub fn start_watchdog(mut watchdog: Watchdog, timeout: Duration) {
+    watchdog.pause_on_debug(true);
+    watchdog.start(timeout);
+    WATCHDOG.lock(|slot| slot.replace(Some(watchdog)));
+}
+
+pub fn feed_watchdog() {
+    WATCHDOG.lock(|slot| {
+        if let Some(watchdog) = slot.borrow_mut().as_mut() {
+            watchdog.feed();
+        }
+    });
+}
+
+// mipidsi interface to the LCD: SPI device with its CS pin, plus the DC pin
+pub type PicoSpiInterface = SpiInterface<
+    'static,
+    SpiDeviceWithConfig<'static, NoopRawMutex, Spi<'static, SPI1, PicoSpiMode>, Output<'static>>,
+    Output<'static>,
 >;
 
-// Convert our generic Color to Rgb565
-impl From<Color> for Rgb565 {
-    fn from(color: Color) -> Self {
-        Rgb565::new(
-            (color.r >> 3) as u8,
-            (color.g >> 2) as u8, 
-            (color.b >> 3) as u8,
-        )
-    }
+// Type alias to simplify the complex Display type
+pub type MipiDisplay = Display<PicoSpiInterface, PanelModel, Output<'static>>;
+
+// Waveshare LCD 1.14": a 135x240 panel at column 52, row 40 of the ST7789's
+// 240x320 RAM in its native portrait orientation. mipidsi mirrors and swaps
+// that offset for the other rotations, which puts the visible window at:
+//
+//   Rotation  Screen    Offset (x, y)
+//   Deg0      135x240   52, 40
+//   Deg90     240x135   40, 53   (the landscape layout the game is drawn for)
+//   Deg180    135x240   53, 40
+//   Deg270    240x135   40, 52
+//
+// 135 is odd, so 52 columns are left on one side and 53 on the other: the
+// offset has to follow the rotation or the image shifts by a pixel.
+#[cfg(not(feature = "st7735"))]
+pub fn waveshare_panel(
+    builder: Builder<PicoSpiInterface, PanelModel, NoResetPin>,
+    rotation: Rotation,
+) -> Builder<PicoSpiInterface, PanelModel, NoResetPin> {
+    builder
+        .display_size(135, 240) // Physical dimensions before rotation
+        .display_offset(52, 40) // Deg0 offset, the others are derived from it
+        .invert_colors(ColorInversion::Inverted)
+        .orientation(Orientation::new().rotate(rotation))
 }
 
+// ST7735S hats (e.g. Waveshare Pico LCD 1.8"): a 128x160 panel at column 2,
+// row 1 of the controller's 132x162 RAM, BGR subpixels and no inversion.
+// Landscape is 160x128 and the grid is sized from that.
+#[cfg(feature = "st7735")]
+pub fn waveshare_panel(
+    builder: Builder<PicoSpiInterface, PanelModel, NoResetPin>,
+    rotation: Rotation,
+) -> Builder<PicoSpiInterface, PanelModel, NoResetPin> {
+    builder
+        .display_size(128, 160) // Physical dimensions before rotation
+        .display_offset(2, 1) // Deg0 offset, the others are derived from it
+        .color_order(ColorOrder::Bgr)
+        .invert_colors(ColorInversion::Normal)
+        .orientation(Orientation::new().rotate(rotation))
+}
+
+// SPI or pin error from a draw call, a frame that hits one is redrawn
+pub type DisplayError = <MipiDisplay as DrawTarget>::Error;
+
+// Cell size is the renderer's business, the display only draws pixels
 pub struct PicoWaveshareDisplay {
     display: MipiDisplay,
-    #[allow(dead_code)]
-    cell_size: u16,
 }
 
 impl PicoWaveshareDisplay {
-    pub fn new(
-        display: MipiDisplay,
-        cell_size: u16,
-    ) -> Self {
-        Self { display, cell_size }
+    pub fn new(display: MipiDisplay) -> Self {
+        Self { display }
     }
 }
 
 impl GameDisplay for PicoWaveshareDisplay {
-    type Error = ();
-    
+    type Error = DisplayError;
+
+    // Screen size in the rotation the display was built with
     fn dimensions(&self) -> (u16, u16) {
-        (240, 135)
+        let size = self.display.size();
+        (size.width as u16, size.height as u16)
     }
-    
+
+    // The longest transfer there is, so it counts as a fresh start for the watchdog
     fn clear(&mut self, color: Color) -> Result<(), Self::Error> {
-        self.display.clear(color.into()).ok();
-        Ok(())
+        feed_watchdog();
+        let result = self.display.clear(color.into());
+        feed_watchdog();
+        result
     }
-    
-    fn draw_rect(&mut self, x: u16, y: u16, width: u16, height: u16, color: Color) -> Result<(), Self::Error> {
+
+    fn draw_rect(
+        &mut self,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        color: Color,
+    ) -> Result<(), Self::Error> {
         let rect = Rectangle::new(
             Point::new(x as i32, y as i32),
             Size::new(width as u32, height as u32),
         );
         rect.into_styled(PrimitiveStyle::with_fill(color.into()))
-            .draw(&mut self.display).ok();
-        Ok(())
+            .draw(&mut self.display)
     }
-    
+
     fn draw_text(&mut self, text: &str, x: u16, y: u16, color: Color) -> Result<(), Self::Error> {
-        let text_style = MonoTextStyle::new(&FONT_6X10, color.into());
-        Text::new(text, Point::new(x as i32, y as i32), text_style)
-            .draw(&mut self.display).ok();
-        Ok(())
+        self.draw_text_in(text, x, y, color, Font::Small)
+    }
+
+    fn draw_text_in(
+        &mut self,
+        text: &str,
+        x: u16,
+        y: u16,
+        color: Color,
+        font: Font,
+    ) -> Result<(), Self::Error> {
+        let text_style = MonoTextStyle::new(font.mono_font(), color.into());
+        // (x, y) is the top-left corner of the text, like draw_rect
+        Text::with_baseline(
+            text,
+            Point::new(x as i32, y as i32),
+            text_style,
+            Baseline::Top,
+        )
+        .draw(&mut self.display)
+        .map(|_| ())
+    }
+
+    fn draw_pixel(&mut self, x: u16, y: u16, color: Color) -> Result<(), Self::Error> {
+        Pixel(Point::new(x as i32, y as i32), color.into()).draw(&mut self.display)
     }
-    
+
+    fn draw_line(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        color: Color,
+    ) -> Result<(), Self::Error> {
+        Line::new(
+            Point::new(x0 as i32, y0 as i32),
+            Point::new(x1 as i32, y1 as i32),
+        )
+        .into_styled(PrimitiveStyle::with_stroke(color.into(), 1))
+        .draw(&mut self.display)
+    }
+
+    fn draw_circle(&mut self, cx: u16, cy: u16, r: u16, color: Color) -> Result<(), Self::Error> {
+        Circle::with_center(Point::new(cx as i32, cy as i32), 2 * r as u32 + 1)
+            .into_styled(PrimitiveStyle::with_fill(color.into()))
+            .draw(&mut self.display)
+    }
+
+    // One window write for the whole image instead of a transfer per pixel
+    fn draw_image(&mut self, x: u16, y: u16, image: &ImageRaw<Rgb565>) -> Result<(), Self::Error> {
+        Image::new(image, Point::new(x as i32, y as i32)).draw(&mut self.display)
+    }
+
     fn update(&mut self) -> Result<(), Self::Error> {
-        // ST7789 doesn't need explicit update
+        // The panel is written directly, no explicit update needed
         Ok(())
     }
 }
 
+// Timings of the Pico inputs, in milliseconds. The defaults are the original
+// fixed values.
+#[derive(Clone, Copy, Debug)]
+pub struct InputTiming {
+    #[allow(dead_code)] // Only the digital joystick repeats directions
+    pub direction_cooldown_ms: u64, // Between repeats of a held direction, 0 repeats every poll
+    pub button_a_debounce_ms: u64, // Level changes this close together are contact bounce
+    pub button_b_debounce_ms: u64,
+    pub long_press_ms: u64, // Holding A this long abandons a game
+}
+
+impl Default for InputTiming {
+    fn default() -> Self {
+        Self {
+            direction_cooldown_ms: 150,
+            // Sampling once per frame already rides over most bounce
+            button_a_debounce_ms: 0,
+            button_b_debounce_ms: 0,
+            long_press_ms: 1000,
+        }
+    }
+}
+
+// What a button did since the previous poll
+#[derive(Clone, Copy, PartialEq)]
+enum Edge {
+    None,
+    Down,
+    Up { tap: bool }, // False when the press already fired a long press or combo
+}
+
+// Active-low push button, sampled once per poll (every frame)
+struct Button {
+    pin: Input<'static>,
+    debounce_ms: u64,
+    down_since: Option<Instant>,
+    released_at: Instant,
+    consumed: bool, // This press already fired a long press or combo
+}
+
+impl Button {
+    fn new(pin: Input<'static>, debounce_ms: u64) -> Self {
+        Self {
+            pin,
+            debounce_ms,
+            down_since: None,
+            released_at: Instant::now(),
+            consumed: false,
+        }
+    }
+
+    fn sample(&mut self, now: Instant) -> Edge {
+        let last_change = self.down_since.unwrap_or(self.released_at);
+        if now.duration_since(last_change).as_millis() < self.debounce_ms {
+            return Edge::None;
+        }
+
+        match (self.pin.is_low(), self.down_since) {
+            (true, None) => {
+                self.down_since = Some(now);
+                self.consumed = false;
+                Edge::Down
+            }
+            (false, Some(_)) => {
+                self.down_since = None;
+                self.released_at = now;
+                Edge::Up {
+                    tap: !self.consumed,
+                }
+            }
+            _ => Edge::None,
+        }
+    }
+
+    fn is_down(&self) -> bool {
+        self.down_since.is_some()
+    }
+}
+
+// The A/B pair: B acts as soon as it goes down, A on release so a hold can
+// become a long press, and pressing one while the other is held is a combo
+struct Buttons {
+    a: Button,
+    b: Button,
+    long_press_ms: u64,
+}
+
+impl Buttons {
+    fn new(a: Input<'static>, b: Input<'static>, timing: InputTiming) -> Self {
+        Self {
+            a: Button::new(a, timing.button_a_debounce_ms),
+            b: Button::new(b, timing.button_b_debounce_ms),
+            long_press_ms: timing.long_press_ms,
+        }
+    }
+
+    fn set_timing(&mut self, timing: InputTiming) {
+        self.a.debounce_ms = timing.button_a_debounce_ms;
+        self.b.debounce_ms = timing.button_b_debounce_ms;
+        self.long_press_ms = timing.long_press_ms;
+    }
+
+    // Either button changing level, so an idle loop wakes on the press and
+    // again on the release that fires A
+    async fn wait_for_change(&mut self) {
+        select(
+            self.a.pin.wait_for_any_edge(),
+            self.b.pin.wait_for_any_edge(),
+        )
+        .await;
+    }
+
+    fn poll(&mut self, events: &mut InputEvents) {
+        let now = Instant::now();
+        let a = self.a.sample(now);
+        let b = self.b.sample(now);
+
+        if (a == Edge::Down && self.b.is_down()) || (b == Edge::Down && self.a.is_down()) {
+            self.a.consumed = true;
+            self.b.consumed = true;
+            events.push(InputEvent::ButtonAB).ok();
+            return;
+        }
+
+        if b == Edge::Down {
+            events.push(InputEvent::ButtonB).ok();
+        }
+        if a == (Edge::Up { tap: true }) {
+            events.push(InputEvent::ButtonA).ok();
+        }
+        if let Some(since) = self.a.down_since {
+            if !self.a.consumed && now.duration_since(since).as_millis() >= self.long_press_ms {
+                self.a.consumed = true;
+                events.push(InputEvent::ButtonALong).ok();
+            }
+        }
+    }
+}
+
+// Analog joystick read through the ADC, plus the A/B buttons
 pub struct PicoWaveshareInput {
-    adc: Adc<'static, embassy_rp::adc::Blocking>,
+    adc: &'static PicoAdc,
     joystick_x: Channel<'static>,
     joystick_y: Channel<'static>,
-    button_a: Input<'static>,
-    _button_b: Input<'static>,
+    buttons: Buttons,
+    deadzone: u16,
 }
 
 impl PicoWaveshareInput {
+    // 12-bit ADC reading with the stick at rest, and the default deflection that
+    // counts as a push
+    const CENTER: u16 = 2048;
+    const DEFAULT_DEADZONE: u16 = 1000;
+
     pub fn new(
-        adc: Adc<'static, embassy_rp::adc::Blocking>,
+        adc: &'static PicoAdc,
         joystick_x: Channel<'static>,
         joystick_y: Channel<'static>,
         button_a: Input<'static>,
@@ -105,135 +402,444 @@ impl PicoWaveshareInput {
             adc,
             joystick_x,
             joystick_y,
-            button_a,
-            _button_b: button_b,
+            buttons: Buttons::new(button_a, button_b, InputTiming::default()),
+            deadzone: Self::DEFAULT_DEADZONE,
         }
     }
-    
-    fn joystick_to_direction(x: u16, y: u16) -> Option<Direction> {
-        const THRESHOLD: u16 = 1000;
-        const CENTER: u16 = 2048;
 
-        if x < CENTER - THRESHOLD {
-            Some(Direction::Left)
-        } else if x > CENTER + THRESHOLD {
-            Some(Direction::Right)
-        } else if y < CENTER - THRESHOLD {
+    // Button timings. The stick reports on every poll, so the direction
+    // cooldown doesn't apply to it.
+    pub fn with_timing(mut self, timing: InputTiming) -> Self {
+        self.buttons.set_timing(timing);
+        self
+    }
+
+    // Deflection from the center (in ADC counts) below which the stick reads
+    // as centered. Lower is more sensitive, too low and a worn stick drifts.
+    #[allow(dead_code)]
+    pub fn with_deadzone(mut self, deadzone: u16) -> Self {
+        self.deadzone = deadzone;
+        self
+    }
+}
+
+impl GameInput for PicoWaveshareInput {
+    type Error = embassy_rp::adc::Error;
+
+    fn poll_input(&mut self) -> Result<InputEvents, Self::Error> {
+        let mut events = InputEvents::new();
+
+        // Read joystick, a failed conversion counts as centered
+        let (x_val, y_val) = self.adc.lock(|adc| {
+            let mut adc = adc.borrow_mut();
+            (
+                adc.blocking_read(&mut self.joystick_x)
+                    .unwrap_or(Self::CENTER),
+                adc.blocking_read(&mut self.joystick_y)
+                    .unwrap_or(Self::CENTER),
+            )
+        });
+
+        if let Some(direction) = joystick_to_direction(x_val, y_val, Self::CENTER, self.deadzone) {
+            events.push(InputEvent::Direction(direction)).ok();
+        }
+
+        // The stick and the buttons fit, none of them is dropped
+        self.buttons.poll(&mut events);
+        Ok(events)
+    }
+
+    // Only the buttons can wake the loop, the stick is read on its idle ticks
+    async fn wait_for_input(&mut self) -> bool {
+        self.buttons.wait_for_change().await;
+        true
+    }
+
+    fn button_a_held(&self) -> bool {
+        self.buttons.a.is_down()
+    }
+}
+
+// Digital joystick and A/B buttons of the Pico LCD 1.14 (all active low)
+pub struct PicoButtonInput {
+    up: Input<'static>,
+    down: Input<'static>,
+    left: Input<'static>,
+    right: Input<'static>,
+    buttons: Buttons,
+    direction_cooldown_ms: u64,
+    last_direction: Instant,
+}
+
+impl PicoButtonInput {
+    pub fn new(
+        up: Input<'static>,
+        down: Input<'static>,
+        left: Input<'static>,
+        right: Input<'static>,
+        button_a: Input<'static>,
+        button_b: Input<'static>,
+    ) -> Self {
+        Self {
+            up,
+            down,
+            left,
+            right,
+            buttons: Buttons::new(button_a, button_b, InputTiming::default()),
+            direction_cooldown_ms: InputTiming::default().direction_cooldown_ms,
+            last_direction: Instant::now(),
+        }
+    }
+
+    pub fn with_timing(mut self, timing: InputTiming) -> Self {
+        self.buttons.set_timing(timing);
+        self.direction_cooldown_ms = timing.direction_cooldown_ms;
+        self
+    }
+
+    fn held_direction(&self) -> Option<Direction> {
+        if self.up.is_low() {
             Some(Direction::Up)
-        } else if y > CENTER + THRESHOLD {
+        } else if self.down.is_low() {
             Some(Direction::Down)
+        } else if self.left.is_low() {
+            Some(Direction::Left)
+        } else if self.right.is_low() {
+            Some(Direction::Right)
         } else {
             None
         }
     }
 }
 
-impl GameInput for PicoWaveshareInput {
-    type Error = embassy_rp::adc::Error;
-    
-    async fn read_input(&mut self) -> Result<InputEvent, Self::Error> {
-        // Check button first (higher priority)
-        if self.button_a.is_low() {
-            return Ok(InputEvent::ButtonA);
-        }
-        
-        // Read joystick  
-        let x_val = self.adc.blocking_read(&mut self.joystick_x).unwrap_or(2048);
-        let y_val = self.adc.blocking_read(&mut self.joystick_y).unwrap_or(2048);
-        
-        if let Some(direction) = Self::joystick_to_direction(x_val, y_val) {
-            Ok(InputEvent::Direction(direction))
-        } else {
-            Ok(InputEvent::None)
+impl GameInput for PicoButtonInput {
+    type Error = ();
+
+    fn poll_input(&mut self) -> Result<InputEvents, Self::Error> {
+        let mut events = InputEvents::new();
+
+        // Holding the stick repeats the direction after the cooldown
+        let now = Instant::now();
+        if now.duration_since(self.last_direction).as_millis() >= self.direction_cooldown_ms {
+            if let Some(direction) = self.held_direction() {
+                self.last_direction = now;
+                events.push(InputEvent::Direction(direction)).ok();
+            }
         }
+
+        // Buttons are reported alongside the stick
+        self.buttons.poll(&mut events);
+        Ok(events)
+    }
+
+    async fn wait_for_input(&mut self) -> bool {
+        let stick = select_array([
+            self.up.wait_for_any_edge(),
+            self.down.wait_for_any_edge(),
+            self.left.wait_for_any_edge(),
+            self.right.wait_for_any_edge(),
+        ]);
+        select(stick, self.buttons.wait_for_change()).await;
+        true
+    }
+
+    fn button_a_held(&self) -> bool {
+        self.buttons.a.is_down()
     }
 }
 
 pub struct PicoPlatform {
     start_time: Instant,
+    vsys: Option<(&'static PicoAdc, Channel<'static>)>, // Battery reading, see `with_battery`
 }
 
 impl PicoPlatform {
+    // VSYS in millivolts: a Li-ion cell from empty to full, and the floor of
+    // USB power (5 V less the VSYS diode), above any battery
+    const EMPTY_MV: u32 = 3300;
+    const FULL_MV: u32 = 4150;
+    const USB_MV: u32 = 4400;
+
     pub fn new() -> Self {
         Self {
             start_time: Instant::now(),
+            vsys: None,
         }
     }
+
+    // Read the battery on the Pico's VSYS divider (GP29, VSYS/3)
+    pub fn with_battery(mut self, adc: &'static PicoAdc, vsys: Channel<'static>) -> Self {
+        self.vsys = Some((adc, vsys));
+        self
+    }
 }
 
 impl GamePlatform for PicoPlatform {
     async fn delay_ms(&self, ms: u32) {
         Timer::after(Duration::from_millis(ms as u64)).await;
     }
-    
+
     fn current_time_ms(&self) -> u32 {
         self.start_time.elapsed().as_millis() as u32
     }
+
+    // Wait on the deadline like embassy's `Ticker` does, so the frame clock
+    // stays on its grid whatever the frame cost
+    async fn delay_until_ms(&self, deadline_ms: u32) {
+        Timer::at(self.start_time + Duration::from_millis(deadline_ms as u64)).await;
+    }
+
+    fn feed_watchdog(&self) {
+        feed_watchdog();
+    }
+
+    // No battery set up, or on USB power, reads as full
+    fn read_battery_percent(&mut self) -> u8 {
+        let Some((adc, vsys)) = self.vsys.as_mut() else {
+            return 100;
+        };
+        let Ok(raw) = adc.lock(|adc| adc.borrow_mut().blocking_read(vsys)) else {
+            return 100;
+        };
+
+        // 12-bit reading against the 3.3 V reference, times 3 for the divider
+        let mv = raw as u32 * 3 * 3300 / 4096;
+        if mv >= Self::USB_MV {
+            return 100;
+        }
+        let above_empty = mv.saturating_sub(Self::EMPTY_MV);
+        (above_empty * 100 / (Self::FULL_MV - Self::EMPTY_MV)).min(100) as u8
+    }
 }
 
-pub struct PicoWaveshareRenderer {
-    display: PicoWaveshareDisplay,
-    cell_size: u16,
+// Piezo buzzer driven by a PWM slice (square wave at 50% duty), `buzzer` feature
+#[cfg(feature = "buzzer")]
+pub struct PicoBuzzer {
+    pwm: Pwm<'static>,
+    config: PwmConfig,
+    stop_at: Option<Instant>,
 }
 
-impl PicoWaveshareRenderer {
-    pub fn new(display: PicoWaveshareDisplay, cell_size: u16) -> Self {
-        Self { display, cell_size }
+#[cfg(feature = "buzzer")]
+impl PicoBuzzer {
+    // Slows the PWM counter so audible frequencies fit in the 16-bit TOP register
+    const DIVIDER: u8 = 64;
+
+    pub fn new(pwm: Pwm<'static>) -> Self {
+        let mut config = PwmConfig::default();
+        config.divider = Self::DIVIDER.into();
+        let mut buzzer = Self {
+            pwm,
+            config,
+            stop_at: None,
+        };
+        buzzer.silence();
+        buzzer
+    }
+
+    fn silence(&mut self) {
+        self.config.compare_a = 0;
+        self.config.compare_b = 0;
+        self.pwm.set_config(&self.config);
+        self.stop_at = None;
     }
 }
 
-impl GameRenderer for PicoWaveshareRenderer {
+#[cfg(feature = "buzzer")]
+impl GameAudio for PicoBuzzer {
     type Error = ();
-    
-    fn render_game(&mut self, 
-                   snake: &[Position], 
-                   food: &Position, 
-                   score: u16, 
-                   state: GameState,
-                   _grid_width: u8,
-                   _grid_height: u8) -> Result<(), Self::Error> {
-        
-        self.display.clear(Color::BLACK).ok();
-        
-        match state {
-            GameState::Playing => {
-                // Draw snake
-                for segment in snake {
-                    self.display.draw_rect(
-                        segment.x as u16 * self.cell_size,
-                        segment.y as u16 * self.cell_size,
-                        self.cell_size,
-                        self.cell_size,
-                        Color::GREEN,
-                    ).ok();
-                }
-                
-                // Draw food
-                self.display.draw_rect(
-                    food.x as u16 * self.cell_size,
-                    food.y as u16 * self.cell_size,
-                    self.cell_size, 
-                    self.cell_size,
-                    Color::RED,
-                ).ok();
-                
-                // Draw score
-                let mut score_text = heapless::String::<32>::new(); 
-                core::fmt::write(&mut score_text, format_args!("Score: {}", score)).unwrap();
-                self.display.draw_text(&score_text, 5, 15, Color::WHITE).ok();
-            }
-            GameState::GameOver => {
-                self.display.draw_text("GAME OVER", 85, 55, Color::WHITE).ok();
-                
-                let mut final_score = heapless::String::<32>::new();
-                core::fmt::write(&mut final_score, format_args!("Final Score: {}", score)).unwrap();
-                self.display.draw_text(&final_score, 75, 70, Color::WHITE).ok();
-                
-                self.display.draw_text("Press A to restart", 60, 90, Color::WHITE).ok();
+
+    fn beep(&mut self, freq_hz: u16, dur_ms: u16) -> Result<(), Self::Error> {
+        if freq_hz == 0 || dur_ms == 0 {
+            self.silence();
+            return Ok(());
+        }
+
+        let counter_hz = clk_sys_freq() / Self::DIVIDER as u32;
+        let top = (counter_hz / freq_hz as u32).clamp(2, u16::MAX as u32 + 1) - 1;
+
+        // Drive whichever channel the buzzer pin is on
+        self.config.top = top as u16;
+        self.config.compare_a = (top / 2) as u16;
+        self.config.compare_b = (top / 2) as u16;
+        self.pwm.set_config(&self.config);
+
+        // The tone keeps playing until `update` sees the deadline pass
+        self.stop_at = Some(Instant::now() + Duration::from_millis(dur_ms as u64));
+        Ok(())
+    }
+
+    fn update(&mut self) -> Result<(), Self::Error> {
+        if let Some(stop_at) = self.stop_at {
+            if Instant::now() >= stop_at {
+                self.silence();
             }
         }
-        
-        self.display.update().ok();
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+// LCD backlight dimmed through a PWM slice
+pub struct PicoBacklight {
+    pwm: Pwm<'static>,
+    config: PwmConfig,
+}
+
+impl PicoBacklight {
+    // ~12.5 kHz at the default 125 MHz clock, well above visible flicker
+    const TOP: u16 = 9_999;
+
+    pub fn new(pwm: Pwm<'static>) -> Self {
+        let mut config = PwmConfig::default();
+        config.top = Self::TOP;
+        let mut backlight = Self { pwm, config };
+        backlight.set_brightness(100);
+        backlight
+    }
+}
+
+impl GameBacklight for PicoBacklight {
+    fn set_brightness(&mut self, percent: u8) {
+        // A compare of 0 holds the pin low (off), TOP + 1 holds it high (full)
+        let percent = percent.min(100) as u32;
+        let compare = ((Self::TOP as u32 + 1) * percent / 100) as u16;
+
+        // Drive whichever channel the backlight pin is on
+        self.config.compare_a = compare;
+        self.config.compare_b = compare;
+        self.pwm.set_config(&self.config);
+    }
+}
+
+// Size of the Pico's on-board QSPI flash
+const FLASH_SIZE: usize = 2 * 1024 * 1024;
+// Last sector, kept out of the program region by memory.x
+const HIGH_SCORE_OFFSET: u32 = (FLASH_SIZE - ERASE_SIZE) as u32;
+// The high score, then the leaderboard: each score as 2 little endian bytes,
+// the entries followed by their 3 initials. The mute flag goes last.
+const ENTRY_SIZE: usize = 2 + 3;
+const MUTED_OFFSET: usize = 2 + ENTRY_SIZE * LEADERBOARD_SIZE;
+const RECORD_SIZE: usize = MUTED_OFFSET + 1;
+// An erased sector reads back as 0xFF, an empty slot
+const EMPTY_SCORE: u16 = 0xFFFF;
+// The sectors below the scores hold the round saved on pause, erased when
+// there is none. memory.x keeps all three out of the program region.
+const SNAPSHOT_SIZE: usize = <GameSnapshot>::SIZE;
+const SNAPSHOT_SECTORS: usize = SNAPSHOT_SIZE.div_ceil(ERASE_SIZE);
+const SNAPSHOT_OFFSET: u32 = HIGH_SCORE_OFFSET - (SNAPSHOT_SECTORS * ERASE_SIZE) as u32;
+const _: () = assert!(SNAPSHOT_SECTORS == 2);
+
+// High score and leaderboard stored in the reserved last flash sector
+pub struct PicoFlashStorage {
+    flash: Flash<'static, FLASH, Blocking, FLASH_SIZE>,
+}
+
+impl PicoFlashStorage {
+    pub fn new(flash: Flash<'static, FLASH, Blocking, FLASH_SIZE>) -> Self {
+        Self { flash }
+    }
+
+    fn read_record(&mut self) -> Result<[u8; RECORD_SIZE], embassy_rp::flash::Error> {
+        let mut record = [0u8; RECORD_SIZE];
+        self.flash.blocking_read(HIGH_SCORE_OFFSET, &mut record)?;
+        Ok(record)
+    }
+
+    // Saving one half erases the sector, so the other half is written back too
+    // A sector erase can take a few hundred ms, the watchdog is fed around it
+    fn write_record(&mut self, record: &[u8; RECORD_SIZE]) -> Result<(), embassy_rp::flash::Error> {
+        feed_watchdog();
+        self.flash
+            .blocking_erase(HIGH_SCORE_OFFSET, HIGH_SCORE_OFFSET + ERASE_SIZE as u32)?;
+        feed_watchdog();
+        self.flash.blocking_write(HIGH_SCORE_OFFSET, record)
+    }
+}
+
+impl GameStorage for PicoFlashStorage {
+    type Error = embassy_rp::flash::Error;
+
+    fn load_high_score(&mut self) -> Result<u16, Self::Error> {
+        let record = self.read_record()?;
+
+        // An erased sector means no score yet
+        match u16::from_le_bytes([record[0], record[1]]) {
+            EMPTY_SCORE => Ok(0),
+            score => Ok(score),
+        }
+    }
+
+    fn save_high_score(&mut self, score: u16) -> Result<(), Self::Error> {
+        let mut record = self.read_record()?;
+        record[..2].copy_from_slice(&score.to_le_bytes());
+        self.write_record(&record)
+    }
+
+    fn load_scores(&mut self) -> Result<Leaderboard, Self::Error> {
+        let record = self.read_record()?;
+
+        // The list ends at the first empty slot, all of them on erased flash
+        Ok(record[2..MUTED_OFFSET]
+            .chunks_exact(ENTRY_SIZE)
+            .map(|bytes| ScoreEntry {
+                score: u16::from_le_bytes([bytes[0], bytes[1]]),
+                initials: [bytes[2], bytes[3], bytes[4]],
+            })
+            .take_while(|entry| entry.score != EMPTY_SCORE)
+            .collect())
+    }
+
+    fn save_scores(&mut self, scores: &Leaderboard) -> Result<(), Self::Error> {
+        let mut record = self.read_record()?;
+        let entries = &mut record[2..MUTED_OFFSET];
+        for (i, slot) in entries.chunks_exact_mut(ENTRY_SIZE).enumerate() {
+            match scores.get(i) {
+                Some(entry) => {
+                    slot[..2].copy_from_slice(&entry.score.to_le_bytes());
+                    slot[2..].copy_from_slice(&entry.initials);
+                }
+                None => slot.fill(0xFF),
+            }
+        }
+        self.write_record(&record)
+    }
+
+    fn load_muted(&mut self) -> Result<bool, Self::Error> {
+        // Erased flash reads 0xFF, sound on until muted once
+        Ok(self.read_record()?[MUTED_OFFSET] == 0)
+    }
+
+    fn save_muted(&mut self, muted: bool) -> Result<(), Self::Error> {
+        let mut record = self.read_record()?;
+        record[MUTED_OFFSET] = if muted { 0 } else { 0xFF };
+        self.write_record(&record)
+    }
+
+    fn load_snapshot(&mut self) -> Result<Option<GameSnapshot>, Self::Error> {
+        let mut bytes = [0u8; SNAPSHOT_SIZE];
+        self.flash.blocking_read(SNAPSHOT_OFFSET, &mut bytes)?;
+        // Erased flash fails the version check, there is no round then
+        Ok(GameSnapshot::decode(&bytes).ok())
+    }
+
+    // One sector erased at a time, feeding the watchdog in between
+    fn save_snapshot(&mut self, snapshot: Option<&GameSnapshot>) -> Result<(), Self::Error> {
+        for sector in 0..SNAPSHOT_SECTORS as u32 {
+            let start = SNAPSHOT_OFFSET + sector * ERASE_SIZE as u32;
+            feed_watchdog();
+            self.flash
+                .blocking_erase(start, start + ERASE_SIZE as u32)?;
+        }
+        feed_watchdog();
+        let Some(snapshot) = snapshot else {
+            return Ok(());
+        };
+        let mut bytes = [0u8; SNAPSHOT_SIZE];
+        // The buffer is exactly the encoded size, so encoding can't fail
+        snapshot.encode(&mut bytes).ok();
+        self.flash.blocking_write(SNAPSHOT_OFFSET, &bytes)
+    }
+}
+
+// Grid renderer drawing on the LCD
+pub type PicoWaveshareRenderer = GridRenderer<PicoWaveshareDisplay>;
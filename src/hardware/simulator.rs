@@ -0,0 +1,369 @@
+// Desktop simulator: runs the game in a window through embedded-graphics-simulator,
+// so gameplay can be tried without flashing a Pico.
+//
+// Built by the `snake_sim` binary with the `sim` feature, for the host target:
+//     cargo run --features sim --bin snake_sim --target <host triple>
+// SDL2 has to be installed on the host.
+//
+// Keys: arrows move, Space is button A (leave the end screen), Escape is a long
+// press of A (abandon the game), Enter is button B (start/pause), closing the
+// window quits.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use embedded_graphics::{
+    image::{Image, ImageRaw},
+    mono_font::MonoTextStyle,
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{Circle, Line, PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+};
+use embedded_graphics_simulator::{
+    sdl2::Keycode, OutputSettingsBuilder, SimulatorDisplay, SimulatorEvent, Window,
+};
+
+use snake_embedded::game::Direction;
+use snake_embedded::snapshot::GameSnapshot;
+use snake_embedded::traits::{
+    Color, Font, GameDisplay, GameInput, GamePlatform, GameStorage, InputEvent, InputEvents,
+    Leaderboard, ScoreEntry, LEADERBOARD_SIZE,
+};
+
+// Same panel as the Pico LCD 1.14 in landscape, scaled up to be readable
+pub const SIM_WIDTH: u16 = 240;
+pub const SIM_HEIGHT: u16 = 135;
+const SIM_SCALE: u32 = 3;
+
+// The window is shared: the display draws into it, the input reads its events
+pub type SharedWindow = Rc<RefCell<Window>>;
+
+pub fn new_window() -> SharedWindow {
+    let settings = OutputSettingsBuilder::new().scale(SIM_SCALE).build();
+    Rc::new(RefCell::new(Window::new("Snake", &settings)))
+}
+
+pub struct SimDisplay {
+    display: SimulatorDisplay<Rgb565>,
+    window: SharedWindow,
+}
+
+impl SimDisplay {
+    pub fn new(window: SharedWindow) -> Self {
+        let display = SimulatorDisplay::new(Size::new(SIM_WIDTH as u32, SIM_HEIGHT as u32));
+
+        // Open the window right away, it only reports events once it has been shown
+        window.borrow_mut().update(&display);
+
+        Self { display, window }
+    }
+}
+
+impl GameDisplay for SimDisplay {
+    type Error = ();
+
+    fn dimensions(&self) -> (u16, u16) {
+        (SIM_WIDTH, SIM_HEIGHT)
+    }
+
+    fn clear(&mut self, color: Color) -> Result<(), Self::Error> {
+        self.display.clear(Rgb565::from(color)).ok();
+        Ok(())
+    }
+
+    fn draw_rect(
+        &mut self,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        color: Color,
+    ) -> Result<(), Self::Error> {
+        Rectangle::new(
+            Point::new(x as i32, y as i32),
+            Size::new(width as u32, height as u32),
+        )
+        .into_styled(PrimitiveStyle::with_fill(Rgb565::from(color)))
+        .draw(&mut self.display)
+        .ok();
+        Ok(())
+    }
+
+    fn draw_text(&mut self, text: &str, x: u16, y: u16, color: Color) -> Result<(), Self::Error> {
+        self.draw_text_in(text, x, y, color, Font::Small)
+    }
+
+    fn draw_text_in(
+        &mut self,
+        text: &str,
+        x: u16,
+        y: u16,
+        color: Color,
+        font: Font,
+    ) -> Result<(), Self::Error> {
+        let text_style = MonoTextStyle::new(font.mono_font(), Rgb565::from(color));
+        Text::with_baseline(
+            text,
+            Point::new(x as i32, y as i32),
+            text_style,
+            Baseline::Top,
+        )
+        .draw(&mut self.display)
+        .ok();
+        Ok(())
+    }
+
+    fn draw_pixel(&mut self, x: u16, y: u16, color: Color) -> Result<(), Self::Error> {
+        Pixel(Point::new(x as i32, y as i32), Rgb565::from(color))
+            .draw(&mut self.display)
+            .ok();
+        Ok(())
+    }
+
+    fn draw_line(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        color: Color,
+    ) -> Result<(), Self::Error> {
+        Line::new(
+            Point::new(x0 as i32, y0 as i32),
+            Point::new(x1 as i32, y1 as i32),
+        )
+        .into_styled(PrimitiveStyle::with_stroke(Rgb565::from(color), 1))
+        .draw(&mut self.display)
+        .ok();
+        Ok(())
+    }
+
+    fn draw_circle(&mut self, cx: u16, cy: u16, r: u16, color: Color) -> Result<(), Self::Error> {
+        Circle::with_center(Point::new(cx as i32, cy as i32), 2 * r as u32 + 1)
+            .into_styled(PrimitiveStyle::with_fill(Rgb565::from(color)))
+            .draw(&mut self.display)
+            .ok();
+        Ok(())
+    }
+
+    fn draw_image(&mut self, x: u16, y: u16, image: &ImageRaw<Rgb565>) -> Result<(), Self::Error> {
+        Image::new(image, Point::new(x as i32, y as i32))
+            .draw(&mut self.display)
+            .ok();
+        Ok(())
+    }
+
+    fn update(&mut self) -> Result<(), Self::Error> {
+        // Present the frame, like the end of an SPI transfer on the real panel
+        self.window.borrow_mut().update(&self.display);
+        Ok(())
+    }
+}
+
+// Keyboard input read from the window events
+pub struct SimInput {
+    window: SharedWindow,
+    pending: VecDeque<InputEvent>, // Keys pressed since the last poll, oldest first
+    space_down: bool,              // Space stands in for button A
+}
+
+impl SimInput {
+    pub fn new(window: SharedWindow) -> Self {
+        Self {
+            window,
+            pending: VecDeque::new(),
+            space_down: false,
+        }
+    }
+
+    fn key_to_event(keycode: Keycode) -> Option<InputEvent> {
+        match keycode {
+            Keycode::Up => Some(InputEvent::Direction(Direction::Up)),
+            Keycode::Down => Some(InputEvent::Direction(Direction::Down)),
+            Keycode::Left => Some(InputEvent::Direction(Direction::Left)),
+            Keycode::Right => Some(InputEvent::Direction(Direction::Right)),
+            Keycode::Space => Some(InputEvent::ButtonA),
+            Keycode::Escape => Some(InputEvent::ButtonALong),
+            Keycode::Return => Some(InputEvent::ButtonB),
+            _ => None,
+        }
+    }
+}
+
+impl GameInput for SimInput {
+    type Error = ();
+
+    fn poll_input(&mut self) -> Result<InputEvents, Self::Error> {
+        // Drain the window so it stays responsive, then hand out the keys in
+        // order, as many per poll as fit. The rest wait for the next frame.
+        for event in self.window.borrow_mut().events() {
+            match event {
+                SimulatorEvent::Quit => std::process::exit(0),
+                SimulatorEvent::KeyDown {
+                    keycode,
+                    repeat: false,
+                    ..
+                } => {
+                    self.space_down |= keycode == Keycode::Space;
+                    if let Some(event) = Self::key_to_event(keycode) {
+                        self.pending.push_back(event);
+                    }
+                }
+                SimulatorEvent::KeyUp {
+                    keycode: Keycode::Space,
+                    ..
+                } => self.space_down = false,
+                _ => {}
+            }
+        }
+
+        let mut events = InputEvents::new();
+        while !events.is_full() {
+            let Some(event) = self.pending.pop_front() else {
+                break;
+            };
+            events.push(event).ok();
+        }
+        Ok(events)
+    }
+
+    fn button_a_held(&self) -> bool {
+        self.space_down
+    }
+}
+
+pub struct SimPlatform {
+    start_time: Instant,
+}
+
+impl SimPlatform {
+    pub fn new() -> Self {
+        Self {
+            start_time: Instant::now(),
+        }
+    }
+}
+
+impl GamePlatform for SimPlatform {
+    async fn delay_ms(&self, ms: u32) {
+        // Nothing else runs on the simulator thread, so blocking is fine
+        std::thread::sleep(Duration::from_millis(ms as u64));
+    }
+
+    fn current_time_ms(&self) -> u32 {
+        self.start_time.elapsed().as_millis() as u32
+    }
+}
+
+// High score kept in a small text file next to where the simulator is run.
+// The first line holds the high score, the second the leaderboard separated
+// by spaces, each entry as `score:INI`, the third `muted` when sound is off.
+pub struct FileStorage {
+    path: &'static str,
+}
+
+impl FileStorage {
+    pub fn new(path: &'static str) -> Self {
+        Self { path }
+    }
+
+    // All three lines, empty when there is no file yet
+    fn read_lines(&self) -> Result<[String; 3], std::io::Error> {
+        match fs::read_to_string(self.path) {
+            Ok(text) => {
+                let mut lines = text.lines().map(|line| line.trim().to_string());
+                Ok(core::array::from_fn(|_| lines.next().unwrap_or_default()))
+            }
+            // No file yet means no score yet
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Default::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    // Replace line `index`, keeping the others
+    fn write_line(&self, index: usize, line: String) -> Result<(), std::io::Error> {
+        let mut lines = self.read_lines()?;
+        lines[index] = line;
+        fs::write(self.path, lines.join("\n") + "\n")
+    }
+
+    // The round saved on pause sits next to the scores, in its byte layout
+    fn snapshot_path(&self) -> String {
+        format!("{}.round", self.path)
+    }
+}
+
+impl GameStorage for FileStorage {
+    type Error = std::io::Error;
+
+    fn load_high_score(&mut self) -> Result<u16, Self::Error> {
+        let [high_score, ..] = self.read_lines()?;
+        Ok(high_score.parse().unwrap_or(0))
+    }
+
+    fn save_high_score(&mut self, score: u16) -> Result<(), Self::Error> {
+        self.write_line(0, score.to_string())
+    }
+
+    fn load_scores(&mut self) -> Result<Leaderboard, Self::Error> {
+        let [_, scores, _] = self.read_lines()?;
+        Ok(scores
+            .split_whitespace()
+            .filter_map(|entry| {
+                let (score, initials) = entry.split_once(':').unwrap_or((entry, ""));
+                let mut parsed = ScoreEntry::new(score.parse().ok()?);
+                if let Ok(initials) = initials.as_bytes().try_into() {
+                    parsed.initials = initials;
+                }
+                Some(parsed)
+            })
+            .take(LEADERBOARD_SIZE)
+            .collect())
+    }
+
+    fn save_scores(&mut self, scores: &Leaderboard) -> Result<(), Self::Error> {
+        let scores: Vec<String> = scores
+            .iter()
+            .map(|entry| {
+                let initials: String = entry.initials().iter().collect();
+                format!("{}:{}", entry.score, initials)
+            })
+            .collect();
+        self.write_line(1, scores.join(" "))
+    }
+
+    fn load_muted(&mut self) -> Result<bool, Self::Error> {
+        let [.., muted] = self.read_lines()?;
+        Ok(muted == "muted")
+    }
+
+    fn save_muted(&mut self, muted: bool) -> Result<(), Self::Error> {
+        let line = if muted { "muted" } else { "" };
+        self.write_line(2, line.to_string())
+    }
+
+    fn load_snapshot(&mut self) -> Result<Option<GameSnapshot>, Self::Error> {
+        match fs::read(self.snapshot_path()) {
+            Ok(bytes) => Ok(GameSnapshot::decode(&bytes).ok()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn save_snapshot(&mut self, snapshot: Option<&GameSnapshot>) -> Result<(), Self::Error> {
+        let Some(snapshot) = snapshot else {
+            return match fs::remove_file(self.snapshot_path()) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+                _ => Ok(()),
+            };
+        };
+        let mut bytes = vec![0; <GameSnapshot>::SIZE];
+        // The buffer is exactly the encoded size, so encoding can't fail
+        snapshot.encode(&mut bytes).ok();
+        fs::write(self.snapshot_path(), bytes)
+    }
+}
@@ -0,0 +1,270 @@
+// Built-in maze levels
+//
+// A level is a const bitmap, one byte per cell row by row: b'1' is a wall and
+// anything else is open floor. `Game::load_level` turns the walls into
+// obstacles, centered on the board, and starts the snake on the level's spawn
+// cell with its body trailing to the left. Two players start where they always
+// do instead, and a level walling in either snake is refused.
+//
+// The built-in mazes are 30x14 so they fit every board in this project: the
+// 39x20 grid the Pico LCD and the simulator fit under their status bar, the
+// 40x22 `GameConfig` default and the 32x16 OLED. `load_level` still checks a
+// level against the board it is loaded on and refuses one that doesn't fit.
+
+use crate::game::Position;
+
+// Why a level can't be loaded on a board
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub enum LevelError {
+    // `cells` doesn't hold `width * height` bytes
+    BadData,
+    // The level is bigger than the board
+    TooLarge,
+    // More walls than `MAX_OBSTACLES`
+    TooManyWalls,
+    // The spawn cell or the starting body is off the level or on a wall
+    SpawnBlocked,
+}
+
+// A maze: walls and where the snake starts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LevelData {
+    pub name: &'static str,
+    pub width: u8,
+    pub height: u8,
+    pub cells: &'static [u8], // Row by row, b'1' for a wall
+    pub spawn: Position,      // Snake head at the start, heading right
+}
+
+impl LevelData {
+    // True when the cell at (x, y) of the level is a wall
+    pub fn is_wall(&self, x: u8, y: u8) -> bool {
+        let index = y as usize * self.width as usize + x as usize;
+        self.cells.get(index) == Some(&b'1')
+    }
+
+    // Number of wall cells
+    pub fn wall_count(&self) -> usize {
+        self.cells.iter().filter(|cell| **cell == b'1').count()
+    }
+}
+
+const WIDTH: u8 = 30;
+const HEIGHT: u8 = 14;
+const SPAWN: Position = Position { x: 15, y: 7 };
+
+// The OLED's board is the smallest the levels have to fit
+const _: () = assert!(WIDTH <= 32 && HEIGHT <= 16);
+
+// Four brackets guarding the corners
+pub const CORNERS: LevelData = LevelData {
+    name: "Corners",
+    width: WIDTH,
+    height: HEIGHT,
+    cells: concat!(
+        "000000000000000000000000000000",
+        "011111110000000000000011111110",
+        "010000000000000000000000000010",
+        "010000000000000000000000000010",
+        "010000000000000000000000000010",
+        "000000000000000000000000000000",
+        "000000000000000000000000000000",
+        "000000000000000000000000000000",
+        "000000000000000000000000000000",
+        "010000000000000000000000000010",
+        "010000000000000000000000000010",
+        "010000000000000000000000000010",
+        "011111110000000000000011111110",
+        "000000000000000000000000000000",
+    )
+    .as_bytes(),
+    spawn: SPAWN,
+};
+
+// Eight 2x2 pillars in two rows
+pub const PILLARS: LevelData = LevelData {
+    name: "Pillars",
+    width: WIDTH,
+    height: HEIGHT,
+    cells: concat!(
+        "000000000000000000000000000000",
+        "000000000000000000000000000000",
+        "000011000001100000110000011000",
+        "000011000001100000110000011000",
+        "000000000000000000000000000000",
+        "000000000000000000000000000000",
+        "000000000000000000000000000000",
+        "000000000000000000000000000000",
+        "000000000000000000000000000000",
+        "000000000000000000000000000000",
+        "000011000001100000110000011000",
+        "000011000001100000110000011000",
+        "000000000000000000000000000000",
+        "000000000000000000000000000000",
+    )
+    .as_bytes(),
+    spawn: SPAWN,
+};
+
+// Two long walls with a gap in the middle
+pub const TUNNELS: LevelData = LevelData {
+    name: "Tunnels",
+    width: WIDTH,
+    height: HEIGHT,
+    cells: concat!(
+        "000000000000001000000000000000",
+        "000000000000001000000000000000",
+        "000000000000001000000000000000",
+        "111111111111000000111111111111",
+        "000000000000000000000000000000",
+        "000000000000000000000000000000",
+        "000000000000000000000000000000",
+        "000000000000000000000000000000",
+        "000000000000000000000000000000",
+        "000000000000000000000000000000",
+        "111111111111000000111111111111",
+        "000000000000000100000000000000",
+        "000000000000000100000000000000",
+        "000000000000000100000000000000",
+    )
+    .as_bytes(),
+    spawn: SPAWN,
+};
+
+// Every built-in level, in the order a level select would show them
+pub const LEVELS: [LevelData; 3] = [CORNERS, PILLARS, TUNNELS];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Direction, Game};
+
+    #[test]
+    fn every_level_loads_on_every_board() {
+        for (width, height) in [(39, 20), (40, 22), (32, 16)] {
+            for level in &LEVELS {
+                let mut game: Game = Game::new(width, height);
+                assert_eq!(
+                    game.load_level(level),
+                    Ok(()),
+                    "{} on {width}x{height}",
+                    level.name
+                );
+                assert_eq!(game.obstacles().len(), level.wall_count());
+
+                // Centered on the board, the head on the spawn cell
+                let left = (width - WIDTH) / 2;
+                let top = (height - HEIGHT) / 2;
+                assert_eq!(game.head(), Position::new(left + SPAWN.x, top + SPAWN.y));
+                assert!(game.body().all(|cell| !game.obstacles().contains(&cell)));
+            }
+        }
+    }
+
+    #[test]
+    fn a_level_bigger_than_the_board_is_refused() {
+        let mut game: Game = Game::new(29, 20);
+        assert_eq!(game.load_level(&CORNERS), Err(LevelError::TooLarge));
+        let mut game: Game = Game::new(39, 13);
+        assert_eq!(game.load_level(&CORNERS), Err(LevelError::TooLarge));
+        assert!(game.obstacles().is_empty());
+    }
+
+    #[test]
+    fn malformed_levels_are_refused() {
+        let mut game: Game = Game::new(20, 12);
+        let short = LevelData {
+            name: "Short",
+            width: 4,
+            height: 2,
+            cells: b"0000",
+            spawn: Position::new(3, 0),
+        };
+        assert_eq!(game.load_level(&short), Err(LevelError::BadData));
+
+        let walled = LevelData {
+            name: "Walled",
+            width: 4,
+            height: 1,
+            cells: b"0100",
+            spawn: Position::new(3, 0),
+        };
+        assert_eq!(game.load_level(&walled), Err(LevelError::SpawnBlocked));
+        let cramped = LevelData {
+            name: "Cramped",
+            width: 4,
+            height: 1,
+            cells: b"0000",
+            spawn: Position::new(1, 0),
+        };
+        assert_eq!(game.load_level(&cramped), Err(LevelError::SpawnBlocked));
+        let outside = LevelData {
+            name: "Outside",
+            width: 4,
+            height: 1,
+            cells: b"0000",
+            spawn: Position::new(4, 0),
+        };
+        assert_eq!(game.load_level(&outside), Err(LevelError::SpawnBlocked));
+        assert!(game.obstacles().is_empty());
+    }
+
+    #[test]
+    fn neither_player_starts_in_a_wall() {
+        for level in &LEVELS {
+            let mut game: Game = Game::new(39, 20);
+            game.set_two_player(true);
+            assert_eq!(game.load_level(level), Ok(()), "{}", level.name);
+            let second = game.second_snake().unwrap();
+            assert!(game.body().all(|cell| !game.obstacles().contains(&cell)));
+            assert!(second
+                .body
+                .iter()
+                .all(|cell| !game.obstacles().contains(cell)));
+        }
+
+        // Player two starts on (9..=11, 8) of a 20x12 board, a wall at (9, 8)
+        let column = LevelData {
+            name: "Column",
+            width: 4,
+            height: 12,
+            cells: concat!(
+                "0000", "0000", "0000", "0000", "0000", "0000", "0000", "0000", "0100", "0000",
+                "0000", "0000",
+            )
+            .as_bytes(),
+            spawn: Position::new(3, 0),
+        };
+        let mut game: Game = Game::new(20, 12);
+        assert_eq!(game.load_level(&column), Ok(()));
+        let mut game: Game = Game::new(20, 12);
+        game.set_two_player(true);
+        assert_eq!(game.load_level(&column), Err(LevelError::SpawnBlocked));
+        assert!(game.obstacles().is_empty());
+
+        // Nor can a wall added by hand once the tails have left their start
+        game.start();
+        game.set_direction(Direction::Up);
+        game.update();
+        game.add_obstacle(Position::new(8, 3));
+        game.add_obstacle(Position::new(11, 8));
+        assert!(game.obstacles().is_empty());
+        game.add_obstacle(Position::new(8, 4));
+        assert_eq!(game.obstacles(), &[Position::new(8, 4)]);
+    }
+
+    #[test]
+    fn walls_are_counted_from_the_bitmap() {
+        let level = LevelData {
+            name: "Dots",
+            width: 3,
+            height: 2,
+            cells: b"101x01",
+            spawn: Position::new(0, 0),
+        };
+        assert_eq!(level.wall_count(), 3);
+        assert!(level.is_wall(0, 0) && level.is_wall(2, 1));
+        assert!(!level.is_wall(1, 0) && !level.is_wall(0, 1));
+        assert!(!level.is_wall(3, 1));
+    }
+}
@@ -0,0 +1,23 @@
+// Hardware-independent part of the game: logic, engine, traits and renderers.
+//
+// Shared by the Pico firmware, the screen test and the desktop simulator. It
+// has no hardware dependency, so it also builds for the host, where
+// `cargo test --lib --target <host triple>` runs without a probe.
+
+#![cfg_attr(not(test), no_std)]
+
+pub mod ai;
+pub mod diagnostics;
+pub mod engine;
+pub mod game;
+pub mod levels;
+pub mod melody;
+#[cfg(any(test, feature = "std"))]
+pub mod mock;
+pub mod renderer;
+pub mod replay;
+pub mod scene;
+pub mod screensaver;
+pub mod snapshot;
+pub mod sprites;
+pub mod traits;
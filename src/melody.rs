@@ -0,0 +1,122 @@
+// Buzzer melodies: a short sequence of tones played without blocking the game
+//
+// The player only remembers where it is in the tune. The engine advances it
+// once a frame, after the frame's async delay, so notes start on the first
+// frame past their time and the game never waits for the buzzer.
+
+use crate::traits::GameAudio;
+
+// (frequency Hz, duration ms), a frequency of 0 is a rest
+pub type Note = (u16, u16);
+
+// Rising arpeggio when a round starts
+pub const START_FANFARE: &[Note] = &[(523, 80), (659, 80), (784, 80), (1047, 200)];
+
+// Falling notes when the snake dies
+pub const GAME_OVER_JINGLE: &[Note] = &[(392, 150), (330, 150), (262, 150), (196, 400)];
+
+pub struct MelodyPlayer {
+    notes: &'static [Note],
+    next: usize,  // Index of the next note to start
+    next_at: u32, // When it starts, the end of the current note
+}
+
+impl MelodyPlayer {
+    pub const fn new() -> Self {
+        Self {
+            notes: &[],
+            next: 0,
+            next_at: 0,
+        }
+    }
+
+    // Start a melody from its first note, replacing any that is still playing
+    pub fn play(&mut self, notes: &'static [Note], now: u32) {
+        self.notes = notes;
+        self.next = 0;
+        self.next_at = now;
+    }
+
+    pub fn stop(&mut self) {
+        self.notes = &[];
+        self.next = 0;
+    }
+
+    // True while notes are left to start, the last one may still be sounding after
+    pub fn is_playing(&self) -> bool {
+        self.next < self.notes.len()
+    }
+
+    // Start the note that is due, if any. Notes are timed from the end of the
+    // previous one rather than from the frame, so the tempo doesn't drift with
+    // the frame rate; after a long frame the missed notes are skipped.
+    pub fn update<A: GameAudio>(&mut self, now: u32, audio: &mut A) {
+        let mut due = None;
+        while self.is_playing() && now.wrapping_sub(self.next_at) as i32 >= 0 {
+            let (freq_hz, dur_ms) = self.notes[self.next];
+            due = Some((freq_hz, dur_ms, self.next_at));
+            self.next += 1;
+            self.next_at = self.next_at.wrapping_add(dur_ms as u32);
+        }
+
+        if let Some((freq_hz, dur_ms, started)) = due {
+            // Only sound what is left of the note, sound is cosmetic so ignore failures
+            let late = now.wrapping_sub(started).min(dur_ms as u32) as u16;
+            audio.beep(freq_hz, dur_ms - late).ok();
+        }
+    }
+}
+
+impl Default for MelodyPlayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockAudio;
+
+    #[test]
+    fn notes_start_on_time_and_late_ones_are_cut_short() {
+        let mut player = MelodyPlayer::new();
+        let mut audio: MockAudio = MockAudio::new();
+        player.play(START_FANFARE, 1000);
+        for now in [1000, 1030, 1080, 1170, 1240, 1300] {
+            player.update(now, &mut audio);
+        }
+        assert_eq!(
+            audio.beeps(),
+            [(523, 80), (659, 80), (784, 70), (1047, 200)]
+        );
+        assert!(!player.is_playing());
+    }
+
+    #[test]
+    fn a_long_frame_skips_the_missed_notes() {
+        let mut player = MelodyPlayer::new();
+        let mut audio: MockAudio = MockAudio::new();
+        player.play(GAME_OVER_JINGLE, 0);
+        player.update(0, &mut audio);
+        player.update(460, &mut audio);
+        assert_eq!(audio.beeps(), [(392, 150), (196, 390)]);
+        assert!(!player.is_playing());
+    }
+
+    #[test]
+    fn a_new_tune_replaces_the_old_one() {
+        let mut player = MelodyPlayer::new();
+        let mut audio: MockAudio = MockAudio::new();
+        player.play(GAME_OVER_JINGLE, 0);
+        player.update(0, &mut audio);
+        player.play(START_FANFARE, 50);
+        player.update(150, &mut audio);
+        assert_eq!(audio.beeps(), [(392, 150), (659, 60)]);
+
+        player.stop();
+        assert!(!player.is_playing());
+        player.update(1000, &mut audio);
+        assert_eq!(audio.beeps().len(), 2);
+    }
+}
@@ -0,0 +1,638 @@
+// Recording display for checking what a renderer draws, without hardware,
+// a framebuffer display for comparing whole frames, a platform on a manual
+// clock for checking the engine's timing, an audio sink that logs the tones
+// it is asked for and storage kept in memory
+//
+// Every call is logged as a `Shape`, so a host test (or a debugging session)
+// can assert on the result of a frame: which cells were filled, where the text
+// went. Lines, pixels and circles go through the default trait methods, so they
+// show up as the rectangles they are made of.
+//
+// `MockPlatform` and `MockInput` drive a whole engine: the input plays a script
+// of timed presses on the platform's clock, and a stop time on the clock parks
+// `GameEngine::run` so the test can look at where the game got to.
+//
+// `FrameBufferDisplay` keeps the pixels instead, drawn with embedded-graphics
+// like on the panel, so a frame can be hashed against a known good (golden)
+// value or dumped as a PPM image to look at.
+
+use core::cell::Cell;
+use core::convert::Infallible;
+use core::fmt;
+
+use embedded_graphics::{
+    image::{Image, ImageRaw},
+    mono_font::MonoTextStyle,
+    pixelcolor::{raw::RawU16, Rgb565},
+    prelude::*,
+    primitives::{Circle, Line, PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+};
+use heapless::{String, Vec};
+
+use crate::snapshot::GameSnapshot;
+use crate::traits::{
+    Color, Font, GameAudio, GameDisplay, GameInput, GamePlatform, GameStorage, InputEvent,
+    InputEvents, Leaderboard,
+};
+
+/// One recorded drawing call
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Clear(Color),
+    Rect {
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        color: Color,
+    },
+    Text {
+        text: String<32>,
+        x: u16,
+        y: u16,
+        color: Color,
+        font: Font,
+    },
+}
+
+/// `GameDisplay` that records up to `N` shapes instead of drawing them
+pub struct MockDisplay<const N: usize = 256> {
+    width: u16,
+    height: u16,
+    shapes: Vec<Shape, N>,
+    dropped: usize, // Calls that didn't fit in the log
+    updates: usize,
+}
+
+impl<const N: usize> MockDisplay<N> {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            shapes: Vec::new(),
+            dropped: 0,
+            updates: 0,
+        }
+    }
+
+    pub fn shapes(&self) -> &[Shape] {
+        &self.shapes
+    }
+
+    /// Number of calls lost because the log was full
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Number of `update` calls, one per presented frame
+    pub fn updates(&self) -> usize {
+        self.updates
+    }
+
+    /// Forget everything recorded so far, e.g. to look at a single frame
+    pub fn reset(&mut self) {
+        self.shapes.clear();
+        self.dropped = 0;
+        self.updates = 0;
+    }
+
+    /// Number of rectangles drawn in `color`
+    pub fn rect_count(&self, color: Color) -> usize {
+        self.shapes
+            .iter()
+            .filter(|shape| matches!(shape, Shape::Rect { color: c, .. } if *c == color))
+            .count()
+    }
+
+    /// Whether some text call contained `needle`
+    pub fn has_text(&self, needle: &str) -> bool {
+        self.shapes
+            .iter()
+            .any(|shape| matches!(shape, Shape::Text { text, .. } if text.contains(needle)))
+    }
+
+    fn record(&mut self, shape: Shape) {
+        if self.shapes.push(shape).is_err() {
+            self.dropped += 1;
+        }
+    }
+}
+
+impl<const N: usize> GameDisplay for MockDisplay<N> {
+    type Error = ();
+
+    fn dimensions(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+
+    fn clear(&mut self, color: Color) -> Result<(), Self::Error> {
+        self.record(Shape::Clear(color));
+        Ok(())
+    }
+
+    fn draw_rect(
+        &mut self,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        color: Color,
+    ) -> Result<(), Self::Error> {
+        self.record(Shape::Rect {
+            x,
+            y,
+            width,
+            height,
+            color,
+        });
+        Ok(())
+    }
+
+    fn draw_text(&mut self, text: &str, x: u16, y: u16, color: Color) -> Result<(), Self::Error> {
+        self.draw_text_in(text, x, y, color, Font::Small)
+    }
+
+    fn draw_text_in(
+        &mut self,
+        text: &str,
+        x: u16,
+        y: u16,
+        color: Color,
+        font: Font,
+    ) -> Result<(), Self::Error> {
+        // Longer strings are cut, which is enough to match on them
+        let mut recorded = String::new();
+        for c in text.chars() {
+            if recorded.push(c).is_err() {
+                break;
+            }
+        }
+        self.record(Shape::Text {
+            text: recorded,
+            x,
+            y,
+            color,
+            font,
+        });
+        Ok(())
+    }
+
+    fn update(&mut self) -> Result<(), Self::Error> {
+        self.updates += 1;
+        Ok(())
+    }
+}
+
+/// `GameDisplay` drawing into a `W` x `H` RGB565 framebuffer in RAM. Drawing
+/// off the edges is clipped, like on the panel.
+pub struct FrameBufferDisplay<const W: usize, const H: usize> {
+    pixels: [[Rgb565; W]; H],
+    updates: usize,
+}
+
+impl<const W: usize, const H: usize> FrameBufferDisplay<W, H> {
+    pub fn new() -> Self {
+        Self {
+            pixels: [[Rgb565::BLACK; W]; H],
+            updates: 0,
+        }
+    }
+
+    /// Color at (x, y), `None` off the screen
+    pub fn pixel(&self, x: u16, y: u16) -> Option<Color> {
+        let row = self.pixels.get(y as usize)?;
+        row.get(x as usize).map(|pixel| Color::from(*pixel))
+    }
+
+    /// Number of `update` calls, one per presented frame
+    pub fn updates(&self) -> usize {
+        self.updates
+    }
+
+    /// FNV-1a hash of the raw RGB565 pixels, row by row. Any change to what is
+    /// on screen changes it, so a frame can be checked against a golden value.
+    pub fn hash(&self) -> u32 {
+        let mut hash: u32 = 0x811C_9DC5;
+        for pixel in self.pixels.iter().flatten() {
+            for byte in RawU16::from(*pixel).into_inner().to_be_bytes() {
+                hash ^= byte as u32;
+                hash = hash.wrapping_mul(0x0100_0193);
+            }
+        }
+        hash
+    }
+
+    /// Write the frame as a plain (P3) PPM image, to look at what a golden
+    /// value stands for or why a frame no longer matches it
+    pub fn write_ppm<O: fmt::Write>(&self, out: &mut O) -> fmt::Result {
+        writeln!(out, "P3\n{} {}\n255", W, H)?;
+        for row in &self.pixels {
+            for pixel in row {
+                let color = Color::from(*pixel);
+                write!(out, "{} {} {} ", color.r, color.g, color.b)?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
+
+impl<const W: usize, const H: usize> Default for FrameBufferDisplay<W, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const W: usize, const H: usize> OriginDimensions for FrameBufferDisplay<W, H> {
+    fn size(&self) -> Size {
+        Size::new(W as u32, H as u32)
+    }
+}
+
+impl<const W: usize, const H: usize> DrawTarget for FrameBufferDisplay<W, H> {
+    type Color = Rgb565;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            let (Ok(x), Ok(y)) = (usize::try_from(point.x), usize::try_from(point.y)) else {
+                continue;
+            };
+            if let Some(pixel) = self.pixels.get_mut(y).and_then(|row| row.get_mut(x)) {
+                *pixel = color;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<const W: usize, const H: usize> GameDisplay for FrameBufferDisplay<W, H> {
+    type Error = Infallible;
+
+    fn dimensions(&self) -> (u16, u16) {
+        (W as u16, H as u16)
+    }
+
+    fn clear(&mut self, color: Color) -> Result<(), Self::Error> {
+        DrawTarget::clear(self, color.into())
+    }
+
+    fn draw_rect(
+        &mut self,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        color: Color,
+    ) -> Result<(), Self::Error> {
+        Rectangle::new(
+            Point::new(x as i32, y as i32),
+            Size::new(width as u32, height as u32),
+        )
+        .into_styled(PrimitiveStyle::with_fill(color.into()))
+        .draw(self)
+    }
+
+    fn draw_text(&mut self, text: &str, x: u16, y: u16, color: Color) -> Result<(), Self::Error> {
+        self.draw_text_in(text, x, y, color, Font::Small)
+    }
+
+    fn draw_text_in(
+        &mut self,
+        text: &str,
+        x: u16,
+        y: u16,
+        color: Color,
+        font: Font,
+    ) -> Result<(), Self::Error> {
+        let text_style = MonoTextStyle::new(font.mono_font(), color.into());
+        // (x, y) is the top-left corner of the text, like draw_rect
+        Text::with_baseline(
+            text,
+            Point::new(x as i32, y as i32),
+            text_style,
+            Baseline::Top,
+        )
+        .draw(self)
+        .map(|_| ())
+    }
+
+    fn draw_pixel(&mut self, x: u16, y: u16, color: Color) -> Result<(), Self::Error> {
+        Pixel(Point::new(x as i32, y as i32), color.into()).draw(self)
+    }
+
+    fn draw_line(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        color: Color,
+    ) -> Result<(), Self::Error> {
+        Line::new(
+            Point::new(x0 as i32, y0 as i32),
+            Point::new(x1 as i32, y1 as i32),
+        )
+        .into_styled(PrimitiveStyle::with_stroke(color.into(), 1))
+        .draw(self)
+    }
+
+    fn draw_circle(&mut self, cx: u16, cy: u16, r: u16, color: Color) -> Result<(), Self::Error> {
+        Circle::with_center(Point::new(cx as i32, cy as i32), 2 * r as u32 + 1)
+            .into_styled(PrimitiveStyle::with_fill(color.into()))
+            .draw(self)
+    }
+
+    fn draw_image(&mut self, x: u16, y: u16, image: &ImageRaw<Rgb565>) -> Result<(), Self::Error> {
+        Image::new(image, Point::new(x as i32, y as i32)).draw(self)
+    }
+
+    fn update(&mut self) -> Result<(), Self::Error> {
+        self.updates += 1;
+        Ok(())
+    }
+}
+
+/// `GamePlatform` on a manual clock: time only moves when a delay runs or the
+/// test calls `advance`, so timing code can be checked without waiting.
+/// Delays complete at once after moving the clock forward, up to the stop time.
+pub struct MockPlatform {
+    now_ms: Cell<u32>,
+    stop_at: Cell<Option<u32>>,
+}
+
+impl MockPlatform {
+    pub fn new(start_ms: u32) -> Self {
+        Self {
+            now_ms: Cell::new(start_ms),
+            stop_at: Cell::new(None),
+        }
+    }
+
+    /// Move the clock forward by `ms`
+    pub fn advance(&self, ms: u32) {
+        self.now_ms.set(self.now_ms.get().wrapping_add(ms));
+    }
+
+    /// Delays that bring the clock to `ms` or past it never finish, so polling
+    /// `engine.run()` once with a no-op waker runs the engine up to then and
+    /// comes back pending. Set a later time and poll a new `run()` to go on.
+    pub fn stop_at(&self, ms: u32) {
+        self.stop_at.set(Some(ms));
+    }
+
+    async fn delay(&self, ms: u32) {
+        self.advance(ms);
+        if self
+            .stop_at
+            .get()
+            .is_some_and(|stop| self.now_ms.get() >= stop)
+        {
+            core::future::pending::<()>().await;
+        }
+    }
+}
+
+impl GamePlatform for MockPlatform {
+    async fn delay_ms(&self, ms: u32) {
+        self.delay(ms).await;
+    }
+
+    fn current_time_ms(&self) -> u32 {
+        self.now_ms.get()
+    }
+}
+
+// Borrowed, so a test can hand the clock to the engine and still read or move it
+impl GamePlatform for &MockPlatform {
+    async fn delay_ms(&self, ms: u32) {
+        self.delay(ms).await;
+    }
+
+    fn current_time_ms(&self) -> u32 {
+        self.now_ms.get()
+    }
+}
+
+/// `GameInput` playing back a script of `(time_ms, event)` pairs, in time
+/// order, on a `MockPlatform` clock. Each event comes out on the first poll at
+/// or after its time, at most three a poll; a script longer than `N` is cut.
+pub struct MockInput<'a, const N: usize = 16> {
+    clock: &'a MockPlatform,
+    script: Vec<(u32, InputEvent), N>,
+    next: usize,
+    a_held: bool,
+}
+
+impl<'a, const N: usize> MockInput<'a, N> {
+    pub fn new(clock: &'a MockPlatform, script: &[(u32, InputEvent)]) -> Self {
+        Self {
+            clock,
+            script: script.iter().copied().take(N).collect(),
+            next: 0,
+            a_held: false,
+        }
+    }
+
+    /// Press or release button A for `button_a_held`, separate from the script
+    pub fn hold_a(&mut self, held: bool) {
+        self.a_held = held;
+    }
+
+    /// Events still waiting for their time
+    pub fn remaining(&self) -> usize {
+        self.script.len() - self.next
+    }
+}
+
+impl<const N: usize> GameInput for MockInput<'_, N> {
+    type Error = Infallible;
+
+    fn poll_input(&mut self) -> Result<InputEvents, Self::Error> {
+        let now = self.clock.current_time_ms();
+        let mut events = InputEvents::new();
+        while let Some(&(at_ms, event)) = self.script.get(self.next) {
+            if at_ms > now || events.push(event).is_err() {
+                break;
+            }
+            self.next += 1;
+        }
+        Ok(events)
+    }
+
+    fn button_a_held(&self) -> bool {
+        self.a_held
+    }
+}
+
+/// `GameAudio` that records up to `N` beeps as (frequency, duration)
+pub struct MockAudio<const N: usize = 32> {
+    beeps: Vec<(u16, u16), N>,
+}
+
+impl<const N: usize> MockAudio<N> {
+    pub fn new() -> Self {
+        Self { beeps: Vec::new() }
+    }
+
+    pub fn beeps(&self) -> &[(u16, u16)] {
+        &self.beeps
+    }
+}
+
+impl<const N: usize> Default for MockAudio<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> GameAudio for MockAudio<N> {
+    type Error = ();
+
+    fn beep(&mut self, freq_hz: u16, dur_ms: u16) -> Result<(), Self::Error> {
+        // Beeps past the end of the log are dropped, the test sees the first N
+        let _ = self.beeps.push((freq_hz, dur_ms));
+        Ok(())
+    }
+}
+
+/// `GameStorage` kept in memory, starting out empty like fresh flash
+#[derive(Default)]
+pub struct MockStorage {
+    pub high_score: u16,
+    pub scores: Leaderboard,
+    pub muted: bool,
+    pub snapshot: Option<GameSnapshot>,
+    pub saves: usize, // Writes of any kind, to check what gets saved when
+}
+
+impl MockStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl GameStorage for MockStorage {
+    type Error = Infallible;
+
+    fn load_high_score(&mut self) -> Result<u16, Self::Error> {
+        Ok(self.high_score)
+    }
+
+    fn save_high_score(&mut self, score: u16) -> Result<(), Self::Error> {
+        self.high_score = score;
+        self.saves += 1;
+        Ok(())
+    }
+
+    fn load_scores(&mut self) -> Result<Leaderboard, Self::Error> {
+        Ok(self.scores.clone())
+    }
+
+    fn save_scores(&mut self, scores: &Leaderboard) -> Result<(), Self::Error> {
+        self.scores = scores.clone();
+        self.saves += 1;
+        Ok(())
+    }
+
+    fn load_muted(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.muted)
+    }
+
+    fn save_muted(&mut self, muted: bool) -> Result<(), Self::Error> {
+        self.muted = muted;
+        self.saves += 1;
+        Ok(())
+    }
+
+    fn load_snapshot(&mut self) -> Result<Option<GameSnapshot>, Self::Error> {
+        Ok(self.snapshot.clone())
+    }
+
+    fn save_snapshot(&mut self, snapshot: Option<&GameSnapshot>) -> Result<(), Self::Error> {
+        self.snapshot = snapshot.cloned();
+        self.saves += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Waker};
+
+    use super::*;
+
+    #[test]
+    fn the_display_log_counts_what_it_drops() {
+        let mut display: MockDisplay<2> = MockDisplay::new(240, 135);
+        display.draw_rect(0, 0, 5, 5, Color::RED).unwrap();
+        display.draw_text("Hi", 10, 10, Color::WHITE).unwrap();
+        display.draw_rect(5, 5, 5, 5, Color::RED).unwrap();
+        display.update().unwrap();
+        assert_eq!(display.rect_count(Color::RED), 1);
+        assert!(display.has_text("Hi"));
+        assert_eq!((display.dropped(), display.updates()), (1, 1));
+
+        display.reset();
+        assert!(display.shapes().is_empty());
+        assert_eq!((display.dropped(), display.updates()), (0, 0));
+    }
+
+    #[test]
+    fn the_framebuffer_clips_and_hashes_its_pixels() {
+        let mut display: FrameBufferDisplay<8, 4> = FrameBufferDisplay::new();
+        let blank = display.hash();
+        GameDisplay::draw_rect(&mut display, 6, 2, 5, 5, Color::RED).unwrap();
+        assert_eq!(display.pixel(7, 3), Some(Color::RED));
+        assert_eq!(display.pixel(5, 3), Some(Color::BLACK));
+        assert_eq!(display.pixel(8, 3), None);
+        assert_ne!(display.hash(), blank);
+
+        let mut ppm = std::string::String::new();
+        display.write_ppm(&mut ppm).unwrap();
+        assert!(ppm.starts_with("P3\n8 4\n255\n0 0 0 "));
+        assert_eq!(ppm.lines().count(), 3 + 4);
+    }
+
+    #[test]
+    fn the_input_plays_its_script_on_the_clock() {
+        let clock = MockPlatform::new(0);
+        let script = [
+            (100, InputEvent::ButtonA),
+            (100, InputEvent::ButtonB),
+            (100, InputEvent::ButtonAB),
+            (100, InputEvent::ButtonALong),
+            (300, InputEvent::ButtonB),
+        ];
+        let mut input: MockInput = MockInput::new(&clock, &script);
+        assert!(input.poll_input().unwrap().is_empty());
+        clock.advance(100);
+        assert_eq!(input.poll_input().unwrap().len(), 3); // A full poll
+        assert_eq!(input.poll_input().unwrap().len(), 1);
+        assert_eq!(input.remaining(), 1);
+        clock.advance(500);
+        assert_eq!(input.poll_input().unwrap().len(), 1);
+        assert_eq!(input.remaining(), 0);
+    }
+
+    #[test]
+    fn a_stop_time_parks_the_delay() {
+        let clock = MockPlatform::new(0);
+        embassy_futures::block_on(clock.delay_ms(40));
+        assert_eq!(clock.current_time_ms(), 40);
+
+        clock.stop_at(100);
+        let mut context = Context::from_waker(Waker::noop());
+        let mut short = pin!(clock.delay_ms(30));
+        assert!(short.as_mut().poll(&mut context).is_ready());
+        let mut long = pin!(clock.delay_ms(30));
+        assert!(long.as_mut().poll(&mut context).is_pending());
+        assert_eq!(clock.current_time_ms(), 100);
+    }
+}
@@ -0,0 +1,1977 @@
+// Grid renderer shared by the color displays
+//
+// Draws the board cell by cell through `GameDisplay`: moves only repaint the
+// cells that changed, the start/pause/end screens are drawn once when the state
+// changes, and game over plays a death animation before the blinking screen.
+// An optional status bar above the board shows the score while playing.
+
+use core::convert::Infallible;
+use core::fmt::Write;
+
+use embedded_graphics::geometry::{OriginDimensions, Size};
+use embedded_graphics::image::ImageRaw;
+use embedded_graphics::pixelcolor::Rgb565;
+use heapless::{String, Vec};
+
+use crate::game::{Difficulty, Direction, Game, GameState, Position, SNAKE_CAPACITY};
+use crate::scene::{text_width, Scene, Widget, CHAR_HEIGHT, CHAR_WIDTH};
+use crate::screensaver::Screensaver;
+use crate::traits::{Color, Font, GameDisplay, GameRenderer, GameStats, Leaderboard, Theme};
+
+// "Press B to Start" is shown and hidden for this long in turn, ~1 Hz
+const PROMPT_BLINK_MS: u32 = 500;
+
+// Pause banner drawn over the frozen board, in pixels
+const BANNER_WIDTH: u16 = 20 * CHAR_WIDTH;
+const BANNER_HEIGHT: u16 = 4 * CHAR_HEIGHT + 14;
+
+// Smallest board worth playing on, in cells each way
+const MIN_GRID: u16 = 5;
+
+/// Why a cell size doesn't give a playable grid on the display
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub enum GridError {
+    /// Cells need at least 2 px, one of them is the gap between cells
+    CellTooSmall,
+    /// Fewer than 5 cells across or down
+    GridTooSmall,
+    /// More cells than a `u8` coordinate or the snake can hold
+    GridTooLarge,
+}
+
+/// Number of `cell_size` cells that fit in `width` x `height` pixels. Leftover
+/// pixels that don't make a whole cell become a margin around the board.
+pub fn fit_grid(width: u16, height: u16, cell_size: u16) -> Result<(u8, u8), GridError> {
+    if cell_size < 2 {
+        return Err(GridError::CellTooSmall);
+    }
+    let (columns, rows) = (width / cell_size, height / cell_size);
+    if columns < MIN_GRID || rows < MIN_GRID {
+        return Err(GridError::GridTooSmall);
+    }
+    if columns > u8::MAX as u16
+        || rows > u8::MAX as u16
+        || columns as usize * rows as usize > SNAKE_CAPACITY
+    {
+        return Err(GridError::GridTooLarge);
+    }
+    Ok((columns as u8, rows as u8))
+}
+
+// Color the snake fades to when it dies
+const BROWN: Color = Color {
+    r: 136,
+    g: 36,
+    b: 0,
+};
+
+/// Game over presentation, counted in rendered frames (~30 ms each): the snake
+/// shrinks and fades, then the game over screen blinks and settles
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeathAnimConfig {
+    /// Off shows the settled game over screen on the first frame
+    pub enabled: bool,
+    /// Snake shrinking, ~2 seconds by default
+    pub duration_frames: u32,
+    /// Game over screen blinking after it, ~3 seconds by default
+    pub blink_frames: u32,
+    /// Blinks in `blink_frames`, 0 shows the screen steadily
+    pub total_blinks: u32,
+    /// Color the snake fades to over the first half
+    pub fade_to: Color,
+}
+
+impl Default for DeathAnimConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            duration_frames: 60,
+            blink_frames: 90,
+            total_blinks: 12,
+            fade_to: BROWN,
+        }
+    }
+}
+
+impl DeathAnimConfig {
+    // Frame the game over screen first shows on, at least one frame of snake
+    fn end_screen_frame(&self) -> u32 {
+        self.duration_frames.max(1)
+    }
+
+    // Frame after which the screen stays as it is
+    fn last_frame(&self) -> u32 {
+        self.end_screen_frame() + self.blink_frames
+    }
+
+    fn end_screen_visible(&self, frame: u32) -> bool {
+        let blink_frame = frame.saturating_sub(self.end_screen_frame());
+        let interval = self.blink_frames / (self.total_blinks * 2).max(1); // Frames per half-blink
+        blink_frame >= self.blink_frames
+            || self.total_blinks == 0
+            || interval == 0
+            || (blink_frame / interval).is_multiple_of(2)
+    }
+}
+
+// Death burst: pixels thrown out of the head when the snake dies, fading to
+// black over the death animation. Positions and velocities are in 1/16 px.
+#[derive(Clone, Copy, Default)]
+struct Particle {
+    x: i32,
+    y: i32,
+    vx: i32,
+    vy: i32,
+}
+
+const PARTICLE_COUNT: usize = 16;
+const PARTICLE_DRAG: i32 = 15; // Velocity kept each frame, out of 16
+
+// One direction per particle around the circle, unit vectors scaled by 16
+const BURST_DIRECTIONS: [(i32, i32); PARTICLE_COUNT] = [
+    (16, 0),
+    (15, 6),
+    (11, 11),
+    (6, 15),
+    (0, 16),
+    (-6, 15),
+    (-11, 11),
+    (-15, 6),
+    (-16, 0),
+    (-15, -6),
+    (-11, -11),
+    (-6, -15),
+    (0, -16),
+    (6, -15),
+    (11, -11),
+    (15, -6),
+];
+
+// Cells a move can change before `render_delta` stops counting and repaints
+// the whole snake: the new head and the tail, a few more after poison
+const DELTA_CAPACITY: usize = 8;
+
+// Player one's snake between two moves, as the cells to repaint: the ones it
+// left and the segments on cells it didn't cover before. A grown snake leaves
+// nothing and food it ate becomes its head, so neither costs an extra cell.
+struct SnakeDelta {
+    vacated: Vec<Position, DELTA_CAPACITY>,
+    entered: Vec<usize, DELTA_CAPACITY>, // Segment indices, the head at 0
+}
+
+impl SnakeDelta {
+    // `None` when more cells changed than the scratch sets hold
+    fn between(prev: &[Position], snake: &[Position]) -> Option<Self> {
+        let mut delta = Self {
+            vacated: Vec::new(),
+            entered: Vec::new(),
+        };
+        for cell in prev.iter().filter(|cell| !snake.contains(cell)) {
+            delta.vacated.push(*cell).ok()?;
+        }
+        for (i, cell) in snake.iter().enumerate() {
+            if !prev.contains(cell) {
+                delta.entered.push(i).ok()?;
+            }
+        }
+        Some(delta)
+    }
+}
+
+// Display that keeps the first error instead of returning it, so the drawing
+// helpers carry on and the frame reports the failure once at the end
+struct Checked<D: GameDisplay> {
+    inner: D,
+    error: Option<D::Error>,
+}
+
+impl<D: GameDisplay> Checked<D> {
+    fn check(&mut self, result: Result<(), D::Error>) -> Result<(), Infallible> {
+        if let Err(error) = result {
+            self.error.get_or_insert(error);
+        }
+        Ok(())
+    }
+}
+
+impl<D: GameDisplay> GameDisplay for Checked<D> {
+    type Error = Infallible;
+
+    fn dimensions(&self) -> (u16, u16) {
+        self.inner.dimensions()
+    }
+
+    fn clear(&mut self, color: Color) -> Result<(), Self::Error> {
+        let result = self.inner.clear(color);
+        self.check(result)
+    }
+
+    fn draw_rect(
+        &mut self,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        color: Color,
+    ) -> Result<(), Self::Error> {
+        let result = self.inner.draw_rect(x, y, width, height, color);
+        self.check(result)
+    }
+
+    fn draw_text(&mut self, text: &str, x: u16, y: u16, color: Color) -> Result<(), Self::Error> {
+        let result = self.inner.draw_text(text, x, y, color);
+        self.check(result)
+    }
+
+    fn draw_text_in(
+        &mut self,
+        text: &str,
+        x: u16,
+        y: u16,
+        color: Color,
+        font: Font,
+    ) -> Result<(), Self::Error> {
+        let result = self.inner.draw_text_in(text, x, y, color, font);
+        self.check(result)
+    }
+
+    fn draw_pixel(&mut self, x: u16, y: u16, color: Color) -> Result<(), Self::Error> {
+        let result = self.inner.draw_pixel(x, y, color);
+        self.check(result)
+    }
+
+    fn draw_line(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        color: Color,
+    ) -> Result<(), Self::Error> {
+        let result = self.inner.draw_line(x0, y0, x1, y1, color);
+        self.check(result)
+    }
+
+    fn draw_circle(&mut self, cx: u16, cy: u16, r: u16, color: Color) -> Result<(), Self::Error> {
+        let result = self.inner.draw_circle(cx, cy, r, color);
+        self.check(result)
+    }
+
+    fn draw_image(&mut self, x: u16, y: u16, image: &ImageRaw<Rgb565>) -> Result<(), Self::Error> {
+        let result = self.inner.draw_image(x, y, image);
+        self.check(result)
+    }
+
+    fn update(&mut self) -> Result<(), Self::Error> {
+        let result = self.inner.update();
+        self.check(result)
+    }
+}
+
+pub struct GridRenderer<D: GameDisplay> {
+    display: Checked<D>,
+    cell_size: u16,
+    board_top: u16,            // Height of the status bar, the board starts below it
+    margin: (u16, u16),        // Kept clear on both sides of the board, (x, y) in pixels
+    board_offset: (u16, u16),  // Board moved right and down by this many pixels
+    eyes: bool,                // Draw eyes on the head, facing the direction of travel
+    tail_color: Option<Color>, // Body fades from the snake color to this one when set
+    food_sprite: Option<ImageRaw<'static, Rgb565>>, // Drawn on the regular food instead of a cell
+    theme: Theme,
+    // What is currently on screen, so frames only redraw what changed
+    shown_state: Option<GameState>,
+    previous_bonus: Option<Position>,
+    previous_poison: Option<Position>,
+    second_shown: Vec<Position, SNAKE_CAPACITY>, // Player two as last drawn
+    inset_shown: u8,                             // Sudden death rings drawn as walls
+    banner: Option<(u16, u16, u16, u16)>,        // Pause banner over the board (x, y, w, h)
+    hud_score: Option<u16>,
+    hud_food: Option<u16>,
+    hud_combo: Option<u8>,
+    hud_hunger: Option<u16>, // Length of the white part of the separator
+    hud_battery: Option<u8>,
+    battery: Option<u8>, // Charge in percent, once the engine has read it
+    fps_shown: Option<u16>,
+    leaderboard: Leaderboard,                 // Shown on the end screens
+    leaderboard_latest: Option<usize>,        // Entry of the round that just ended
+    stats: Option<GameStats>,                 // How the round that just ended went
+    initials_shown: Option<([u8; 3], usize)>, // Letters and cursor on the entry screen
+    muted: bool,
+    sound_line: Option<(u16, u16)>, // Where the pause screen shows the sound setting
+    muted_shown: Option<bool>,
+    prompt_blink: Option<(u32, bool)>, // Start screen blink: when it started, prompt shown
+    prompt: Scene<2>,                  // The prompt's two lines, hidden and shown to blink
+    screensaver: Screensaver,
+    screensaver_on: bool, // Drawn over the end screen, which needs drawing again
+    // Frames since game over, drives the death animation then the blinking
+    end_frame: u32,
+    death_snake: Vec<Position, SNAKE_CAPACITY>,
+    burst: bool, // Throw particles out of the head on death
+    death_anim: DeathAnimConfig,
+    particles: [Particle; PARTICLE_COUNT],
+}
+
+impl<D: GameDisplay> GridRenderer<D> {
+    pub fn new(display: D, cell_size: u16) -> Self {
+        Self {
+            display: Checked {
+                inner: display,
+                error: None,
+            },
+            cell_size,
+            board_top: 0,
+            margin: (0, 0),
+            board_offset: (0, 0),
+            eyes: true,
+            tail_color: None,
+            food_sprite: None,
+            theme: Theme::classic(),
+            shown_state: None,
+            previous_bonus: None,
+            previous_poison: None,
+            second_shown: Vec::new(),
+            inset_shown: 0,
+            banner: None,
+            hud_score: None,
+            hud_food: None,
+            hud_combo: None,
+            hud_hunger: None,
+            hud_battery: None,
+            battery: None,
+            fps_shown: None,
+            leaderboard: Leaderboard::new(),
+            leaderboard_latest: None,
+            stats: None,
+            initials_shown: None,
+            muted: false,
+            sound_line: None,
+            muted_shown: None,
+            prompt_blink: None,
+            prompt: Scene::new(Theme::classic().background),
+            screensaver: Screensaver::new(),
+            screensaver_on: false,
+            end_frame: 0,
+            death_snake: Vec::new(),
+            burst: true,
+            death_anim: DeathAnimConfig::default(),
+            particles: [Particle::default(); PARTICLE_COUNT],
+        }
+    }
+
+    // Reserve `height` pixels at the top of the screen for the score while playing.
+    // The game grid must leave these rows out.
+    pub fn with_status_bar(mut self, height: u16) -> Self {
+        self.board_top = height;
+        self
+    }
+
+    // Leave `x` pixels clear left and right of the board and `y` above and
+    // below it, inside the border and under the status bar. The game grid
+    // shrinks to match, so take `grid_size` after this.
+    pub fn with_margin(mut self, x: u16, y: u16) -> Self {
+        self.margin = (x, y);
+        self
+    }
+
+    // Move the board `x` pixels right and `y` down, leaving the space on the
+    // left and top for a title or a side panel. The grid shrinks as for
+    // `with_margin`.
+    pub fn with_board_offset(mut self, x: u16, y: u16) -> Self {
+        self.board_offset = (x, y);
+        self
+    }
+
+    // Eyes need cells of at least 4 px, they are skipped on smaller grids
+    pub fn with_eyes(mut self, eyes: bool) -> Self {
+        self.eyes = eyes;
+        self
+    }
+
+    // Particle burst on death, a few pixels per frame on top of the death
+    // animation. Leave it off to save SPI time on low power setups.
+    pub fn with_particles(mut self, burst: bool) -> Self {
+        self.burst = burst;
+        self
+    }
+
+    // Shorten, recolor or turn off the game over animation
+    pub fn with_death_animation(mut self, config: DeathAnimConfig) -> Self {
+        self.death_anim = config;
+        self
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    // Fade the body from the snake color behind the head to `tail` at the end of the snake
+    pub fn with_gradient(mut self, tail: Color) -> Self {
+        self.tail_color = Some(tail);
+        self
+    }
+
+    // Draw the regular food with `sprite`. It has to be the drawn part of a
+    // cell (cell size less the gap) square, others are ignored and the food
+    // stays a plain cell.
+    pub fn with_food_sprite(mut self, sprite: ImageRaw<'static, Rgb565>) -> Self {
+        let side = self.cell_size.saturating_sub(1) as u32;
+        if sprite.size() == Size::new(side, side) {
+            self.food_sprite = Some(sprite);
+        }
+        self
+    }
+
+    // Board size in cells for this display, cell size, border and status bar.
+    // Pass it to the engine so the game and the drawing agree on the grid.
+    pub fn grid_size(&self) -> Result<(u8, u8), GridError> {
+        let (_, _, width, height) = self.board_area();
+        fit_grid(width, height, self.cell_size)
+    }
+
+    // Pixels the cells can use (x, y, w, h): inside the border, below the
+    // status bar and past the margin and offset. Each cell starts with its
+    // 1 px gap, which may sit on the last line of the border or on the
+    // separator, so the snake never covers them.
+    fn board_area(&self) -> (u16, u16, u16, u16) {
+        let (width, height) = self.display.dimensions();
+        let thickness = self.theme.border_width.min(width / 2).min(height / 2);
+        let left = thickness.saturating_sub(1);
+        let top = if self.board_top > 0 {
+            self.board_top
+        } else {
+            left
+        };
+        let (margin_x, margin_y) = self.margin;
+        let (offset_x, offset_y) = self.board_offset;
+        (
+            left + margin_x + offset_x,
+            top + margin_y + offset_y,
+            (width - thickness - left).saturating_sub(2 * margin_x + offset_x),
+            (height - thickness)
+                .saturating_sub(top)
+                .saturating_sub(2 * margin_y + offset_y),
+        )
+    }
+
+    // Top-left pixel of a cell. The board is centered in its area when the
+    // cells don't fill it exactly.
+    fn cell_origin(&self, position: Position) -> (u16, u16) {
+        let (left, top, width, height) = self.board_area();
+        let cell_size = self.cell_size.max(1);
+        (
+            left + width % cell_size / 2 + position.x as u16 * cell_size,
+            top + height % cell_size / 2 + position.y as u16 * cell_size,
+        )
+    }
+
+    // The display being drawn on, e.g. a `MockDisplay` to inspect a frame
+    pub fn display(&self) -> &D {
+        &self.display.inner
+    }
+
+    pub fn display_mut(&mut self) -> &mut D {
+        &mut self.display.inner
+    }
+
+    // Report the first draw error of the frame. What is on screen is unknown
+    // after a failed frame, so the next one redraws the current state from
+    // scratch instead of updating what it thinks is shown.
+    fn finish_frame(&mut self) -> Result<(), D::Error> {
+        match self.display.error.take() {
+            Some(error) => {
+                self.shown_state = None;
+                self.banner = None;
+                self.initials_shown = None;
+                Err(error)
+            }
+            None => Ok(()),
+        }
+    }
+
+    // Record which screen is shown, true when it was not shown already
+    fn enter(&mut self, state: GameState) -> bool {
+        let entered = self.shown_state != Some(state);
+        self.shown_state = Some(state);
+        if entered && !matches!(state, GameState::Playing | GameState::Paused) {
+            self.banner = None; // Cleared along with the board
+        }
+        if entered {
+            self.initials_shown = None;
+            self.sound_line = None;
+        }
+        entered
+    }
+
+    fn draw_cell(&mut self, position: Position, color: Color) {
+        // Leave a 1 px gap so the grid stays readable and clear of the border
+        let (x, y) = self.cell_origin(position);
+        self.display
+            .draw_rect(x + 1, y + 1, self.cell_size - 1, self.cell_size - 1, color)
+            .ok();
+    }
+
+    // The sprite covers the same pixels as a cell, so erasing food is the same either way
+    fn draw_food(&mut self, position: Position) {
+        let Some(sprite) = self.food_sprite else {
+            self.draw_cell(position, self.theme.food);
+            return;
+        };
+        let (x, y) = self.cell_origin(position);
+        self.display.draw_image(x + 1, y + 1, &sprite).ok();
+    }
+
+    // Poison is the outline of a cell, so it can't be mistaken for food. Cells
+    // too small for a hole are filled.
+    fn draw_poison(&mut self, position: Position) {
+        let size = self.cell_size - 1;
+        if size < 3 {
+            self.draw_cell(position, self.theme.poison);
+            return;
+        }
+        let (x, y) = self.cell_origin(position);
+        let (x, y, color) = (x + 1, y + 1, self.theme.poison);
+        self.display.draw_rect(x, y, size, 1, color).ok();
+        self.display.draw_rect(x, y + size - 1, size, 1, color).ok();
+        self.display.draw_rect(x, y, 1, size, color).ok();
+        self.display.draw_rect(x + size - 1, y, 1, size, color).ok();
+    }
+
+    // Portals are a square half the size of the cell, in the middle, so the
+    // snake can be seen passing over one. Small cells get a single dot.
+    fn draw_portal(&mut self, position: Position) {
+        let size = ((self.cell_size - 1) / 2).max(1);
+        let (x, y) = self.cell_origin(position);
+        let offset = 1 + (self.cell_size - 1 - size) / 2;
+        self.display
+            .draw_rect(x + offset, y + offset, size, size, self.theme.portal)
+            .ok();
+    }
+
+    // Repaint a cell the snake has left: background, or the portal under it
+    fn erase_cell(&mut self, game: &Game, position: Position) {
+        self.draw_cell(position, self.theme.background);
+        if game
+            .portals()
+            .iter()
+            .any(|(a, b)| *a == position || *b == position)
+        {
+            self.draw_portal(position);
+        }
+    }
+
+    // Head in its own color, with two eye pixels towards the front. The previous
+    // head is repainted as body by `draw_board`, which also covers its eyes.
+    fn draw_head(&mut self, head: Position, direction: Direction) {
+        self.draw_cell(head, self.theme.snake_head);
+
+        let size = self.cell_size - 1; // Drawn part of the cell
+        if !self.eyes || size < 3 {
+            return;
+        }
+        let (x, y) = self.cell_origin(head);
+        let (x, y) = (x + 1, y + 1);
+        let (near, far) = (1, size - 2);
+        let eyes = match direction {
+            Direction::Up => [(near, near), (far, near)],
+            Direction::Down => [(near, far), (far, far)],
+            Direction::Left => [(near, near), (near, far)],
+            Direction::Right => [(far, near), (far, far)],
+        };
+        for (dx, dy) in eyes {
+            self.display
+                .draw_pixel(x + dx, y + dy, self.theme.background)
+                .ok();
+        }
+    }
+
+    fn draw_border(&mut self) {
+        let (width, height) = self.display.dimensions();
+        let thickness = self.theme.border_width.min(width / 2).min(height / 2);
+        if thickness == 0 {
+            return;
+        }
+        let border = self.theme.border;
+        self.display.draw_rect(0, 0, width, thickness, border).ok();
+        self.display
+            .draw_rect(0, height - thickness, width, thickness, border)
+            .ok();
+        self.display.draw_rect(0, 0, thickness, height, border).ok();
+        self.display
+            .draw_rect(width - thickness, 0, thickness, height, border)
+            .ok();
+    }
+
+    // Status bar separator, doubles as the top edge of the board
+    fn draw_status_bar(&mut self) {
+        let (width, _) = self.display.dimensions();
+        self.display
+            .draw_rect(0, self.board_top, width, 1, self.theme.border)
+            .ok();
+        self.hud_score = None;
+        self.hud_food = None;
+        self.hud_combo = None;
+        self.hud_hunger = None;
+        self.hud_battery = None;
+    }
+
+    // Score on the left of the status bar, food count on the right. Each value
+    // is only erased and redrawn when it changed, so the bar never flickers.
+    fn draw_hud(&mut self, game: &Game) {
+        if self.board_top == 0 {
+            return;
+        }
+        let y = self.board_top.saturating_sub(CHAR_HEIGHT) / 2 + 1;
+
+        if self.hud_score != Some(game.score) {
+            self.hud_score = Some(game.score);
+            self.draw_hud_value("Score", game.score, 3, y);
+        }
+
+        if self.hud_food != Some(game.food_eaten) {
+            self.hud_food = Some(game.food_eaten);
+            // "Food: " and up to 5 digits, right aligned
+            let (width, _) = self.display.dimensions();
+            let x = width.saturating_sub(3 + 11 * CHAR_WIDTH);
+            self.draw_hud_value("Food", game.food_eaten, x, y);
+        }
+
+        // Combo multiplier in the middle, only while a streak is going
+        let combo = game.combo();
+        if self.hud_combo != Some(combo) {
+            self.hud_combo = Some(combo);
+            let (width, _) = self.display.dimensions();
+            let x = (width / 2).saturating_sub(CHAR_WIDTH);
+            self.display
+                .draw_rect(x, y, 2 * CHAR_WIDTH, CHAR_HEIGHT, self.theme.background)
+                .ok();
+            if combo > 1 {
+                let mut text = String::<4>::new();
+                write!(&mut text, "x{}", combo).ok();
+                self.display
+                    .draw_text(&text, x, y, self.theme.bonus_food)
+                    .ok();
+            }
+        }
+
+        // With hunger on, the separator doubles as a bar that turns red as it runs out
+        if let Some(left) = game.hunger_left() {
+            let (width, _) = self.display.dimensions();
+            let full = (width as u32 * left as u32 / game.hunger_ticks() as u32) as u16;
+            if self.hud_hunger != Some(full) {
+                self.hud_hunger = Some(full);
+                self.display
+                    .draw_rect(0, self.board_top, full, 1, self.theme.border)
+                    .ok();
+                self.display
+                    .draw_rect(full, self.board_top, width - full, 1, self.theme.food)
+                    .ok();
+            }
+        }
+
+        if let Some(percent) = self.battery {
+            if self.hud_battery != Some(percent) {
+                self.hud_battery = Some(percent);
+                // Right of the score, the level turns red when it runs low
+                self.draw_battery(3 + 13 * CHAR_WIDTH, y + 1, percent);
+            }
+        }
+    }
+
+    // 12x7 battery outline with its terminal, filled in proportion to the charge
+    fn draw_battery(&mut self, x: u16, y: u16, percent: u8) {
+        let (text, background) = (self.theme.text, self.theme.background);
+        self.display.draw_rect(x, y, 12, 7, text).ok();
+        self.display.draw_rect(x + 1, y + 1, 10, 5, background).ok();
+        self.display.draw_rect(x + 12, y + 2, 1, 3, text).ok();
+
+        let level = (percent.min(100) as u16 * 8).div_ceil(100);
+        let color = if percent < 20 {
+            self.theme.food
+        } else {
+            self.theme.snake
+        };
+        self.display.draw_rect(x + 2, y + 2, level, 3, color).ok();
+    }
+
+    fn draw_hud_value(&mut self, label: &str, value: u16, x: u16, y: u16) {
+        // Wide enough for the label and a 5 digit value
+        let width = (label.len() as u16 + 7) * CHAR_WIDTH;
+        self.display
+            .draw_rect(x, y, width, CHAR_HEIGHT, self.theme.background)
+            .ok();
+        self.draw_value(label, value, x, y);
+    }
+
+    // The border is left to `render_border`, which the engine calls after a state change
+    fn clear_screen(&mut self) {
+        self.display.clear(self.theme.background).ok();
+        self.fps_shown = None;
+    }
+
+    fn draw_value(&mut self, label: &str, value: u16, x: u16, y: u16) {
+        let mut text = String::<32>::new();
+        write!(&mut text, "{}: {}", label, value).ok();
+        self.display.draw_text(&text, x, y, self.theme.text).ok();
+    }
+
+    fn draw_value_centered(&mut self, label: &str, value: u16, y: u16) {
+        let mut text = String::<32>::new();
+        write!(&mut text, "{}: {}", label, value).ok();
+        self.display
+            .draw_text_centered(&text, y, self.theme.text)
+            .ok();
+    }
+
+    // The prompt starts out shown, `render_menu` blinks it from there
+    fn draw_start_screen(&mut self) {
+        self.clear_screen();
+        let (width, _) = self.display.dimensions();
+        self.prompt = Scene::new(self.theme.background);
+        for (line, y) in [("Press B", 60), ("to Start", 75)] {
+            let x = width.saturating_sub(text_width(line)) / 2;
+            self.prompt.add(Widget::text(line, x, y, self.theme.text));
+        }
+        self.draw_start_prompt(true);
+        self.prompt_blink = None;
+        self.display
+            .draw_text_centered("A: Difficulty", 105, self.theme.text)
+            .ok();
+    }
+
+    // Hiding the prompt only clears the box around its two lines
+    fn draw_start_prompt(&mut self, visible: bool) {
+        self.prompt.set_all_visible(visible);
+        self.prompt.flush(&mut self.display).ok();
+    }
+
+    // Whether the prompt is up `elapsed_ms` after the start screen appeared
+    fn prompt_visible(elapsed_ms: u32) -> bool {
+        (elapsed_ms / PROMPT_BLINK_MS).is_multiple_of(2)
+    }
+
+    // One line per difficulty, centered, the highlighted one between arrows
+    // in the head color
+    fn draw_difficulty_menu(&mut self, selected: Difficulty) {
+        self.clear_screen();
+        self.display
+            .draw_text_centered("Difficulty", 25, self.theme.text)
+            .ok();
+
+        for (i, difficulty) in Difficulty::ALL.iter().enumerate() {
+            let mut text = String::<16>::new();
+            let color = if *difficulty == selected {
+                write!(&mut text, "> {} <", difficulty.name()).ok();
+                self.theme.snake_head
+            } else {
+                write!(&mut text, "{}", difficulty.name()).ok();
+                self.theme.text
+            };
+            let y = 50 + i as u16 * 15;
+            self.display.draw_text_centered(&text, y, color).ok();
+        }
+        self.display
+            .draw_text_centered("B to Start", 105, self.theme.text)
+            .ok();
+    }
+
+    fn draw_pause_screen(&mut self, score: u16, food_eaten: u16) {
+        self.clear_screen();
+        self.display
+            .draw_text_centered("PAUSED", 30, self.theme.text)
+            .ok();
+        self.draw_value_centered("Score", score, 50);
+        self.draw_value_centered("Food", food_eaten, 65);
+        self.display
+            .draw_text_centered("Press B", 85, self.theme.text)
+            .ok();
+        self.display
+            .draw_text_centered("to Resume", 100, self.theme.text)
+            .ok();
+        // Placed for the longer of the two texts, which share the line
+        let (width, _) = self.display.dimensions();
+        let x = width.saturating_sub(text_width("A: Sound off")) / 2;
+        self.sound_line = Some((x, 118));
+        self.draw_sound_line();
+    }
+
+    // What A does on the pause screen, redrawn on its own when it changes
+    fn draw_sound_line(&mut self) {
+        let Some((x, y)) = self.sound_line else {
+            return;
+        };
+        let text = if self.muted {
+            "A: Sound on"
+        } else {
+            "A: Sound off"
+        };
+        self.display
+            .draw_rect(x, y, 12 * CHAR_WIDTH, CHAR_HEIGHT, self.theme.background)
+            .ok();
+        self.display.draw_text(text, x, y, self.theme.text).ok();
+        self.muted_shown = Some(self.muted);
+    }
+
+    // Empty framed box in the middle of the board, `lift_banner` takes it off
+    // again. Returns its top-left corner.
+    fn draw_banner(&mut self) -> (u16, u16) {
+        let (width, height) = self.display.dimensions();
+        let (banner_width, banner_height) = self.banner_size();
+        let x = (width - banner_width) / 2;
+        let board_height = height - self.board_top;
+        let y = self.board_top + board_height.saturating_sub(banner_height) / 2;
+        self.banner = Some((x, y, banner_width, banner_height));
+
+        self.display
+            .draw_rect(x, y, banner_width, banner_height, self.theme.border)
+            .ok();
+        self.display
+            .draw_rect(
+                x + 1,
+                y + 1,
+                banner_width.saturating_sub(2),
+                banner_height.saturating_sub(2),
+                self.theme.background,
+            )
+            .ok();
+        (x, y)
+    }
+
+    // Banner size on this display, cut down to the screen
+    fn banner_size(&self) -> (u16, u16) {
+        let (width, height) = self.display.dimensions();
+        (BANNER_WIDTH.min(width), BANNER_HEIGHT.min(height))
+    }
+
+    // Pause over a board that is on screen: a framed banner on top of the frozen
+    // frame, lifted again on resume. Without a board, a full pause screen.
+    fn draw_pause(&mut self, over_board: bool, score: u16, food_eaten: u16) {
+        if !over_board {
+            self.draw_pause_screen(score, food_eaten);
+            return;
+        }
+
+        let (x, y) = self.draw_banner();
+        self.display
+            .draw_text("PAUSED", x + 7 * CHAR_WIDTH, y + 4, self.theme.text)
+            .ok();
+        self.draw_value("Score", score, x + 2 * CHAR_WIDTH, y + 6 + CHAR_HEIGHT);
+        self.display
+            .draw_text(
+                "Press B to Resume",
+                x + CHAR_WIDTH,
+                y + 8 + 2 * CHAR_HEIGHT,
+                self.theme.text,
+            )
+            .ok();
+        self.sound_line = Some((x + 4 * CHAR_WIDTH, y + 10 + 3 * CHAR_HEIGHT));
+        self.draw_sound_line();
+    }
+
+    // Erase the pause banner and repaint the cells it was covering
+    fn lift_banner(&mut self, game: &Game, (x, y, width, height): (u16, u16, u16, u16)) {
+        self.display
+            .draw_rect(x, y, width, height, self.theme.background)
+            .ok();
+
+        let cell_size = self.cell_size;
+        let (left, top) = self.cell_origin(Position::new(0, 0));
+        let covered = |position: &Position| {
+            let cell_x = left + position.x as u16 * cell_size;
+            let cell_y = top + position.y as u16 * cell_size;
+            cell_x + cell_size > x
+                && cell_x < x + width
+                && cell_y + cell_size > y
+                && cell_y < y + height
+        };
+        for (i, segment) in game.body().enumerate() {
+            if covered(&segment) {
+                self.draw_segment(game, i);
+            }
+        }
+        for food in game.foods().iter().filter(|food| covered(food)) {
+            self.draw_food(*food);
+        }
+        if let Some(bonus) = game.bonus_food().filter(|bonus| covered(bonus)) {
+            self.draw_cell(bonus, self.theme.bonus_food);
+        }
+        if let Some(poison) = game.poison_food().filter(|poison| covered(poison)) {
+            self.draw_poison(poison);
+        }
+        self.draw_rings(game, 0, covered);
+        self.draw_walls(game, covered);
+        if let Some(second) = game.second_snake() {
+            for segment in second.body.iter().filter(|segment| covered(segment)) {
+                self.draw_cell(*segment, self.theme.second_snake);
+            }
+        }
+    }
+
+    // Number centered on whatever panel the display reports
+    fn draw_countdown(&mut self, n: u8) {
+        let mut text = String::<4>::new();
+        write!(&mut text, "{}", n).ok();
+        let (_, height) = self.display.dimensions();
+        let y = height.saturating_sub(CHAR_HEIGHT) / 2;
+
+        self.clear_screen();
+        self.display
+            .draw_text_centered(&text, y, self.theme.text)
+            .ok();
+    }
+
+    fn draw_end_screen(&mut self, title: &str, game: &Game) {
+        self.clear_screen();
+        self.display
+            .draw_text_centered(title, 35, self.theme.text)
+            .ok();
+        self.draw_final_score(game.score);
+        match game.second_snake() {
+            Some(second) => self.draw_value_centered("P2 Score", second.score, 75),
+            None => self.draw_value_centered("Food Eaten", game.food_eaten, 75),
+        }
+        self.display
+            .draw_text_centered("Press A", 100, self.theme.text)
+            .ok();
+        self.display
+            .draw_text_centered("to Restart", 115, self.theme.text)
+            .ok();
+        self.draw_leaderboard();
+        self.draw_stats();
+    }
+
+    // "Final Score: n" in the small font, or only the number in the large one,
+    // between the title and the next line
+    fn draw_final_score(&mut self, score: u16) {
+        let font = self.theme.score_font;
+        if font == Font::Small {
+            self.draw_value_centered("Final Score", score, 55);
+            return;
+        }
+        let mut text = String::<8>::new();
+        write!(&mut text, "{}", score).ok();
+        let y = 65u16.saturating_sub(font.char_height() / 2);
+        self.display
+            .draw_text_centered_in(&text, y, self.theme.text, font)
+            .ok();
+    }
+
+    // The three letters spread out in the middle of the screen, the one being
+    // picked in the head color and underlined
+    fn draw_initials(&mut self, initials: &[u8; 3], cursor: usize) {
+        let (width, _) = self.display.dimensions();
+        let spacing = 3 * CHAR_WIDTH;
+        let left = width.saturating_sub(2 * spacing + CHAR_WIDTH) / 2;
+        let y = 75;
+        self.display
+            .draw_rect(
+                left,
+                y,
+                2 * spacing + CHAR_WIDTH,
+                CHAR_HEIGHT + 3,
+                self.theme.background,
+            )
+            .ok();
+
+        for (i, letter) in initials.iter().enumerate() {
+            let x = left + i as u16 * spacing;
+            let mut text = String::<4>::new();
+            text.push(*letter as char).ok();
+            let color = if i == cursor {
+                self.display
+                    .draw_rect(x, y + CHAR_HEIGHT + 1, CHAR_WIDTH, 2, self.theme.text)
+                    .ok();
+                self.theme.snake_head
+            } else {
+                self.theme.text
+            };
+            self.display.draw_text(&text, x, y, color).ok();
+        }
+    }
+
+    // Column along the right edge, the round that just ended marked with a star
+    fn draw_leaderboard(&mut self) {
+        if self.leaderboard.is_empty() {
+            return;
+        }
+        let (width, _) = self.display.dimensions();
+        let x = width.saturating_sub(3 + 10 * CHAR_WIDTH);
+        self.display.draw_text("Top", x, 35, self.theme.text).ok();
+
+        for (i, entry) in self.leaderboard.clone().iter().enumerate() {
+            let [a, b, c] = entry.initials();
+            let mut text = String::<12>::new();
+            write!(&mut text, "{}{}{} {:>5}", a, b, c, entry.score).ok();
+            if self.leaderboard_latest == Some(i) {
+                text.push('*').ok();
+            }
+            let y = 35 + (i as u16 + 1) * (CHAR_HEIGHT + 2);
+            self.display.draw_text(&text, x, y, self.theme.text).ok();
+        }
+    }
+
+    // Round stats down the left side, across from the leaderboard
+    fn draw_stats(&mut self) {
+        let Some(stats) = self.stats else {
+            return;
+        };
+        let x = 3;
+        self.display.draw_text("Stats", x, 35, self.theme.text).ok();
+
+        let mut lines = [String::<12>::new(), String::new(), String::new()];
+        write!(&mut lines[0], "APM {}", stats.apples_per_minute()).ok();
+        write!(&mut lines[1], "Moves {}", stats.moves).ok();
+        write!(&mut lines[2], "Streak x{}", stats.longest_streak).ok();
+        for (i, text) in lines.iter().enumerate() {
+            let y = 35 + (i as u16 + 1) * (CHAR_HEIGHT + 2);
+            self.display.draw_text(text, x, y, self.theme.text).ok();
+        }
+    }
+
+    // Segment `i` of the snake, the head at 0
+    fn draw_segment(&mut self, game: &Game, i: usize) {
+        if i == 0 {
+            self.draw_head(game.head(), game.direction);
+            return;
+        }
+        let last = game.snake.len().saturating_sub(1).max(1);
+        let color = match self.tail_color {
+            Some(tail) => Color::lerp(self.theme.snake, tail, i as f32 / last as f32),
+            None => self.theme.snake,
+        };
+        self.draw_cell(game.snake[i], color);
+    }
+
+    // Sudden death walls: fill the cells of rings `from` up to the game's inset
+    // that pass `only` in the border color, so the board visibly closes in
+    fn draw_rings(&mut self, game: &Game, from: u8, only: impl Fn(&Position) -> bool) {
+        let (width, height) = (game.width(), game.height());
+        for ring in from..game.border_inset() {
+            let (right, bottom) = (width - 1 - ring, height - 1 - ring);
+            let top_bottom = (ring..=right).flat_map(|x| [(x, ring), (x, bottom)]);
+            let sides = (ring + 1..bottom).flat_map(|y| [(ring, y), (right, y)]);
+            for (x, y) in top_bottom.chain(sides) {
+                let cell = Position::new(x, y);
+                if only(&cell) {
+                    self.draw_cell(cell, self.theme.border);
+                }
+            }
+        }
+        self.inset_shown = game.border_inset();
+    }
+
+    // Level walls and portals that pass `only`. They never move, so this runs
+    // on a fresh board and when the pause banner comes off, not every frame.
+    // Portals under the snake come back through `erase_cell` once it leaves.
+    fn draw_walls(&mut self, game: &Game, only: impl Fn(&Position) -> bool) {
+        for wall in game.obstacles().iter().filter(|wall| only(wall)) {
+            self.draw_cell(*wall, self.theme.border);
+        }
+        for (a, b) in game.portals() {
+            for portal in [*a, *b] {
+                if only(&portal) && !game.snake.contains(&portal) {
+                    self.draw_portal(portal);
+                }
+            }
+        }
+    }
+
+    fn draw_board(&mut self, game: &Game) {
+        // Player two in a flat color, under player one where they cross
+        self.second_shown.clear();
+        if let Some(second) = game.second_snake() {
+            self.second_shown = second.body.clone();
+            for segment in &second.body {
+                self.draw_cell(*segment, self.theme.second_snake);
+            }
+        }
+
+        // Body first, the head goes on top
+        for i in (0..game.snake.len()).rev() {
+            self.draw_segment(game, i);
+        }
+        for food in game.foods() {
+            self.draw_food(*food);
+        }
+        self.previous_bonus = game.bonus_food();
+        if let Some(bonus) = self.previous_bonus {
+            self.draw_cell(bonus, self.theme.bonus_food);
+        }
+        self.previous_poison = game.poison_food();
+        if let Some(poison) = self.previous_poison {
+            self.draw_poison(poison);
+        }
+    }
+
+    // Snake shrinks and fades to brown, then the game over screen blinks and
+    // settles. With the animation off it settles straight away.
+    fn draw_game_over(&mut self, game: &Game, entered: bool) {
+        let anim = self.death_anim;
+        if entered && !anim.enabled {
+            self.end_frame = anim.last_frame() + 1;
+            self.draw_end_screen("GAME OVER", game);
+            self.draw_border();
+            return;
+        }
+        if entered {
+            self.end_frame = 0;
+            self.death_snake = game.snake.clone();
+            if let Some(head) = game.snake.first() {
+                self.start_burst(*head);
+            }
+        }
+        if self.end_frame > anim.last_frame() {
+            return; // Settled on the game over screen
+        }
+        self.end_frame += 1;
+
+        if self.end_frame < anim.end_screen_frame() {
+            self.draw_death_frame();
+        } else if self.end_frame == anim.end_screen_frame() {
+            self.draw_end_screen("GAME OVER", game);
+            self.draw_border();
+        } else {
+            let visible = anim.end_screen_visible(self.end_frame);
+            if visible != anim.end_screen_visible(self.end_frame - 1) {
+                if visible {
+                    self.draw_end_screen("GAME OVER", game);
+                } else {
+                    self.clear_screen();
+                }
+                // Blinking happens within the state, so the border is ours to restore
+                self.draw_border();
+            }
+        }
+    }
+
+    // Particles start in the middle of the head cell, each in its own direction
+    // at a speed of 1 to 2.5 px per frame, scrambled from the head position
+    fn start_burst(&mut self, head: Position) {
+        let (x, y) = self.cell_origin(head);
+        let center = |origin: u16| (origin as i32 + self.cell_size as i32 / 2) * 16;
+        let (x, y) = (center(x), center(y));
+
+        let mut rng = (head.x as u32 * 31 + head.y as u32 * 17) | 1;
+        for (particle, (dx, dy)) in self.particles.iter_mut().zip(BURST_DIRECTIONS) {
+            rng = rng.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let speed = 16 + (rng >> 16) as i32 % 25;
+            *particle = Particle {
+                x,
+                y,
+                vx: dx * speed / 16,
+                vy: dy * speed / 16,
+            };
+        }
+    }
+
+    // Draw every particle in `color`, skipping the ones off the board
+    fn draw_particles(&mut self, color: Color) {
+        let (width, height) = self.display.dimensions();
+        for i in 0..PARTICLE_COUNT {
+            let (x, y) = (self.particles[i].x >> 4, self.particles[i].y >> 4);
+            if x >= 0 && y >= self.board_top as i32 && x < width as i32 && y < height as i32 {
+                self.display.draw_pixel(x as u16, y as u16, color).ok();
+            }
+        }
+    }
+
+    fn draw_death_frame(&mut self) {
+        let progress = self.end_frame as f32 / self.death_anim.end_screen_frame() as f32;
+        if self.burst {
+            // Rub out the last frame's pixels before the snake goes back on top
+            self.draw_particles(self.theme.background);
+            for particle in self.particles.iter_mut() {
+                particle.x += particle.vx;
+                particle.y += particle.vy;
+                particle.vx = particle.vx * PARTICLE_DRAG / 16;
+                particle.vy = particle.vy * PARTICLE_DRAG / 16;
+            }
+        }
+
+        let segments_to_show = ((1.0 - progress) * self.death_snake.len() as f32) as usize;
+
+        // Fade from the snake color over the first half
+        let color = Color::lerp(self.theme.snake, self.death_anim.fade_to, progress * 2.0);
+
+        let death_snake = core::mem::take(&mut self.death_snake);
+        for (i, segment) in death_snake.iter().enumerate() {
+            if i < segments_to_show {
+                self.draw_cell(*segment, color);
+            } else {
+                self.draw_cell(*segment, self.theme.background);
+            }
+        }
+        self.death_snake = death_snake;
+
+        if self.burst {
+            self.draw_particles(Color::lerp(self.theme.snake_head, Color::BLACK, progress));
+        }
+    }
+}
+
+impl<D: GameDisplay> GameRenderer for GridRenderer<D> {
+    type Error = D::Error;
+
+    fn render_game(&mut self, game: &Game) -> Result<(), Self::Error> {
+        // Full-screen layouts are only drawn when the state changes
+        let board_shown = self.shown_state == Some(GameState::Playing);
+        let entered = self.enter(game.state);
+        if core::mem::take(&mut self.screensaver_on) {
+            self.screensaver.reset();
+            // Back from the screensaver onto the same, settled, end screen
+            match game.state {
+                GameState::GameOver if !entered => self.draw_end_screen("GAME OVER", game),
+                GameState::Won if !entered => self.draw_end_screen("YOU WIN!", game),
+                _ => {}
+            }
+        }
+
+        match game.state {
+            GameState::Starting => {
+                if entered {
+                    self.draw_start_screen();
+                }
+            }
+            GameState::DifficultyMenu(selected) => {
+                if entered {
+                    self.draw_difficulty_menu(selected);
+                }
+            }
+            GameState::Countdown(n) => {
+                if entered {
+                    self.draw_countdown(n);
+                }
+            }
+            GameState::Playing => {
+                // Moves arrive through render_delta, only a fresh board is drawn here
+                if let Some(banner) = self.banner.take() {
+                    // Resuming, or back from the reset prompt: the frozen frame
+                    // is still there under the banner
+                    self.lift_banner(game, banner);
+                    self.display.update().ok();
+                    return self.finish_frame();
+                }
+                if entered {
+                    self.clear_screen();
+                    if self.board_top > 0 {
+                        self.draw_status_bar();
+                    }
+                    self.draw_rings(game, 0, |_| true);
+                    self.draw_walls(game, |_| true);
+                    self.draw_board(game);
+                    self.draw_hud(game);
+                }
+            }
+            GameState::Paused => {
+                if entered {
+                    self.draw_pause(board_shown, game.score, game.food_eaten);
+                }
+            }
+            GameState::GameOver => self.draw_game_over(game, entered),
+            GameState::Won => {
+                if entered {
+                    self.draw_end_screen("YOU WIN!", game);
+                }
+            }
+        }
+
+        self.display.update().ok();
+        self.finish_frame()
+    }
+
+    // Dirty-rectangle update: erase vacated cells and moved food, then redraw
+    fn render_delta(
+        &mut self,
+        game: &Game,
+        prev_snake: &[Position],
+        prev_foods: &[Position],
+    ) -> Result<(), Self::Error> {
+        if self.shown_state != Some(GameState::Playing) {
+            return self.render_game(game);
+        }
+        if let Some(banner) = self.banner.take() {
+            self.lift_banner(game, banner);
+        }
+
+        // Erase first: new food can spawn on the cell the tail just left
+        let delta = SnakeDelta::between(prev_snake, &game.snake);
+        match &delta {
+            Some(delta) => {
+                for old_segment in &delta.vacated {
+                    self.erase_cell(game, *old_segment);
+                }
+            }
+            None => {
+                for old_segment in prev_snake {
+                    if !game.snake.contains(old_segment) {
+                        self.erase_cell(game, *old_segment);
+                    }
+                }
+            }
+        }
+
+        // Eaten food is under the new head, which covers it
+        for old_food in prev_foods {
+            if !game.foods().contains(old_food) && !game.snake.contains(old_food) {
+                self.draw_cell(*old_food, self.theme.background);
+            }
+        }
+
+        let bonus_moved = game.bonus_food() != self.previous_bonus;
+        if let Some(old_bonus) = self.previous_bonus.filter(|_| bonus_moved) {
+            if !game.snake.contains(&old_bonus) {
+                self.draw_cell(old_bonus, self.theme.background);
+            }
+        }
+        let poison_moved = game.poison_food() != self.previous_poison;
+        if let Some(old_poison) = self.previous_poison.filter(|_| poison_moved) {
+            if !game.snake.contains(&old_poison) {
+                self.draw_cell(old_poison, self.theme.background);
+            }
+        }
+        let second_shown = core::mem::take(&mut self.second_shown);
+        for old_segment in &second_shown {
+            let still_there = game
+                .second_snake()
+                .is_some_and(|second| second.body.contains(old_segment));
+            if !still_there && !game.snake.contains(old_segment) {
+                self.erase_cell(game, *old_segment);
+            }
+        }
+
+        self.draw_rings(game, self.inset_shown, |_| true);
+        if let Some(second) = game.second_snake() {
+            self.second_shown = second.body.clone();
+            for segment in &second.body {
+                if !game.snake.contains(segment) {
+                    self.draw_cell(*segment, self.theme.second_snake);
+                }
+            }
+        }
+
+        // A gradient shifts every segment's color on each move. Otherwise only
+        // the new cells change, and the old head, which turns into body.
+        match delta.filter(|_| self.tail_color.is_none()) {
+            Some(delta) => {
+                for &i in delta.entered.iter().rev().filter(|&&i| i > 1) {
+                    self.draw_segment(game, i);
+                }
+                for i in (0..game.snake.len().min(2)).rev() {
+                    self.draw_segment(game, i);
+                }
+            }
+            None => {
+                for i in (0..game.snake.len()).rev() {
+                    self.draw_segment(game, i);
+                }
+            }
+        }
+
+        for food in game.foods() {
+            if !prev_foods.contains(food) {
+                self.draw_food(*food);
+            }
+        }
+        if bonus_moved {
+            self.previous_bonus = game.bonus_food();
+            if let Some(bonus) = self.previous_bonus {
+                self.draw_cell(bonus, self.theme.bonus_food);
+            }
+        }
+        if poison_moved {
+            self.previous_poison = game.poison_food();
+            if let Some(poison) = self.previous_poison {
+                self.draw_poison(poison);
+            }
+        }
+        self.draw_hud(game);
+        self.display.update().ok();
+        self.finish_frame()
+    }
+
+    fn render_menu(&mut self, t_ms: u32) -> Result<(), Self::Error> {
+        if self.enter(GameState::Starting) {
+            self.draw_start_screen();
+            self.display.update().ok();
+        }
+        // The blink counts from the first frame of the screen, shown
+        let (since, shown) = *self.prompt_blink.get_or_insert((t_ms, true));
+        let visible = Self::prompt_visible(t_ms.wrapping_sub(since));
+        if visible != shown {
+            self.draw_start_prompt(visible);
+            self.prompt_blink = Some((since, visible));
+        }
+        self.finish_frame()
+    }
+
+    fn render_pause(&mut self, score: u16, food_eaten: u16) -> Result<(), Self::Error> {
+        let board_shown = self.shown_state == Some(GameState::Playing);
+        if self.enter(GameState::Paused) {
+            self.draw_pause(board_shown, score, food_eaten);
+            self.display.update().ok();
+        } else if self.sound_line.is_some() && self.muted_shown != Some(self.muted) {
+            self.draw_sound_line();
+            self.display.update().ok();
+        }
+        self.finish_frame()
+    }
+
+    fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    // Drawn with the rest of the status bar on the next move
+    fn set_battery(&mut self, percent: u8) {
+        self.battery = Some(percent);
+    }
+
+    fn render_difficulty(&mut self, selected: Difficulty) -> Result<(), Self::Error> {
+        if self.enter(GameState::DifficultyMenu(selected)) {
+            self.draw_difficulty_menu(selected);
+            self.display.update().ok();
+        }
+        self.finish_frame()
+    }
+
+    fn render_countdown(&mut self, n: u8) -> Result<(), Self::Error> {
+        if self.enter(GameState::Countdown(n)) {
+            self.draw_countdown(n);
+            self.display.update().ok();
+        }
+        self.finish_frame()
+    }
+
+    // Top of the screen, left of the food count so it sits in the status bar
+    fn render_fps(&mut self, fps: u16) -> Result<(), Self::Error> {
+        if self.fps_shown == Some(fps) {
+            return Ok(());
+        }
+        self.fps_shown = Some(fps);
+
+        let (width, _) = self.display.dimensions();
+        let x = width.saturating_sub(3 + 18 * CHAR_WIDTH);
+        let y = self.board_top.saturating_sub(CHAR_HEIGHT) / 2 + 1;
+        self.display
+            .draw_rect(x, y, 6 * CHAR_WIDTH, CHAR_HEIGHT, self.theme.background)
+            .ok();
+        let mut text = String::<8>::new();
+        write!(&mut text, "{}fps", fps).ok();
+        self.display.draw_text(&text, x, y, self.theme.text).ok();
+        self.display.update().ok();
+        self.finish_frame()
+    }
+
+    // Title and score once, then only the letters when they change
+    fn render_initials(
+        &mut self,
+        score: u16,
+        initials: &[u8; 3],
+        cursor: usize,
+    ) -> Result<(), Self::Error> {
+        let shown = Some((*initials, cursor));
+        if self.initials_shown == shown {
+            return Ok(());
+        }
+        if self.initials_shown.is_none() {
+            // Leaving the end screen, which has to come back in full if it returns
+            self.shown_state = None;
+            self.clear_screen();
+            self.display
+                .draw_text_centered("NEW HIGH SCORE", 30, self.theme.text)
+                .ok();
+            self.draw_value_centered("Score", score, 50);
+            self.display
+                .draw_text_centered("Up/Down, A to confirm", 105, self.theme.text)
+                .ok();
+        }
+        self.initials_shown = shown;
+        self.draw_initials(initials, cursor);
+        self.display.update().ok();
+        self.finish_frame()
+    }
+
+    // Drawn with the next end screen, which is what shows it
+    fn set_leaderboard(&mut self, scores: &Leaderboard, latest: Option<usize>) {
+        self.leaderboard = scores.clone();
+        self.leaderboard_latest = latest;
+    }
+
+    // A banner over the frozen board, lifted by the next render_game
+    fn render_confirm_reset(&mut self, game: &Game) -> Result<(), Self::Error> {
+        if self.shown_state != Some(GameState::Playing) {
+            self.render_game(game)?;
+        }
+        if self.banner.is_none() {
+            let (x, y) = self.draw_banner();
+            let (banner_width, _) = self.banner_size();
+            let lines = ["RESET ROUND?", "Hold A to reset", "Tap to play on"];
+            for (i, text) in lines.into_iter().enumerate() {
+                let text_x = x + banner_width.saturating_sub(text_width(text)) / 2;
+                let text_y = y + 4 + i as u16 * (CHAR_HEIGHT + 4);
+                self.display
+                    .draw_text(text, text_x, text_y, self.theme.text)
+                    .ok();
+            }
+            self.display.update().ok();
+        }
+        self.finish_frame()
+    }
+
+    fn render_screensaver(&mut self, t_ms: u32) -> Result<(), Self::Error> {
+        self.screensaver_on = true;
+        let theme = self.theme;
+        self.screensaver.step(&mut self.display, t_ms, &theme).ok();
+        self.finish_frame()
+    }
+
+    // Drawn with the end screens, like the leaderboard
+    fn render_stats(&mut self, stats: &GameStats) -> Result<(), Self::Error> {
+        self.stats = Some(*stats);
+        Ok(())
+    }
+
+    fn render_border(&mut self) -> Result<(), Self::Error> {
+        self.draw_border();
+        self.display.update().ok();
+        self.finish_frame()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{FrameBufferDisplay, MockDisplay, Shape};
+
+    type TestRenderer = GridRenderer<MockDisplay<1024>>;
+
+    fn playing() -> Game {
+        let mut game: Game = Game::new(20, 12);
+        game.start();
+        game
+    }
+
+    // Color of the last rect drawn on the inside of `position`'s cell
+    fn cell_color(renderer: &TestRenderer, position: Position) -> Option<Color> {
+        let (x, y) = renderer.cell_origin(position);
+        let mut shapes = renderer.display().shapes().iter().rev();
+        shapes.find_map(|shape| match *shape {
+            Shape::Rect {
+                x: rx,
+                y: ry,
+                color,
+                ..
+            } if (rx, ry) == (x + 1, y + 1) => Some(color),
+            _ => None,
+        })
+    }
+
+    fn text_x(display: &MockDisplay<1024>, needle: &str) -> Option<u16> {
+        display.shapes().iter().find_map(|shape| match shape {
+            Shape::Text { text, x, .. } if text.as_str() == needle => Some(*x),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn the_reset_question_fits_a_narrow_screen() {
+        let mut renderer: TestRenderer = GridRenderer::new(MockDisplay::new(100, 135), 6);
+        let game = playing();
+        renderer.render_game(&game).unwrap();
+        renderer.display_mut().reset();
+        renderer.render_confirm_reset(&game).unwrap();
+
+        let display = renderer.display();
+        assert_eq!(text_x(display, "RESET ROUND?"), Some((100 - 72) / 2));
+        assert!(display.shapes().iter().all(|shape| match shape {
+            Shape::Rect { x, width, .. } => x + width <= 100,
+            _ => true,
+        }));
+    }
+
+    #[test]
+    fn the_start_prompt_blinks_on_its_own() {
+        let mut renderer: TestRenderer = GridRenderer::new(MockDisplay::new(240, 135), 6);
+        renderer.render_menu(0).unwrap();
+        assert!(renderer.display().has_text("Press B"));
+        assert!(renderer.display().has_text("A: Difficulty"));
+
+        // Hiding it erases the two lines and draws nothing else
+        renderer.display_mut().reset();
+        renderer.render_menu(PROMPT_BLINK_MS).unwrap();
+        let background = renderer.theme.background;
+        let display = renderer.display();
+        assert_eq!(display.rect_count(background), 2);
+        assert_eq!(display.shapes().len(), 2);
+        assert_eq!(display.updates(), 1);
+
+        renderer.display_mut().reset();
+        renderer.render_menu(PROMPT_BLINK_MS + 1).unwrap();
+        assert!(renderer.display().shapes().is_empty());
+        renderer.render_menu(2 * PROMPT_BLINK_MS).unwrap();
+        assert!(renderer.display().has_text("Press B"));
+        assert!(renderer.display().has_text("to Start"));
+        assert!(!renderer.display().has_text("A: Difficulty"));
+    }
+
+    // Hash of `golden_board()` on a 240x135 screen. If a layout or color change
+    // is intended, check the frame (`FrameBufferDisplay::write_ppm`) and update it.
+    const PLAYING_GOLDEN_HASH: u32 = 0xB029_0DCF;
+
+    // A fixed-seed round a few moves in, with two foods
+    fn golden_board() -> Game {
+        let mut game: Game = Game::new(20, 12);
+        game.reseed(0x5EED);
+        game.set_food_count(2);
+        game.start();
+        for direction in [None, Some(Direction::Up), None, Some(Direction::Left), None] {
+            game.step(direction);
+        }
+        game
+    }
+
+    #[test]
+    fn the_playing_screen_matches_its_golden_hash() {
+        let mut renderer = GridRenderer::new(FrameBufferDisplay::<240, 135>::new(), 6);
+        let mut game = golden_board();
+        renderer.render_game(&game).unwrap();
+        let hash = renderer.display().hash();
+        assert_eq!(hash, PLAYING_GOLDEN_HASH, "frame hash {hash:#010X}");
+
+        // Any change on the board shows up in the hash
+        game.step(None);
+        let mut renderer = GridRenderer::new(FrameBufferDisplay::<240, 135>::new(), 6);
+        renderer.render_game(&game).unwrap();
+        assert_ne!(renderer.display().hash(), PLAYING_GOLDEN_HASH);
+    }
+
+    #[test]
+    fn the_body_fades_to_the_tail_color() {
+        let display = MockDisplay::new(240, 135);
+        let mut renderer: TestRenderer = GridRenderer::new(display, 6).with_gradient(Color::BLUE);
+        let mut game = playing();
+        renderer.render_game(&game).unwrap();
+        let snake = renderer.theme.snake;
+        let middle = Color::lerp(snake, Color::BLUE, 0.5);
+        assert_eq!(cell_color(&renderer, Position::new(9, 6)), Some(middle));
+        assert_eq!(
+            cell_color(&renderer, Position::new(8, 6)),
+            Some(Color::BLUE)
+        );
+
+        // Growing moves every color along, so the whole body is redrawn
+        game.set_food(Position::new(11, 6));
+        let prev_snake = game.snake.clone();
+        let prev_foods: std::vec::Vec<Position> = game.foods().to_vec();
+        game.step(None);
+        renderer.display_mut().reset();
+        renderer
+            .render_delta(&game, &prev_snake, &prev_foods)
+            .unwrap();
+        let third = Color::lerp(snake, Color::BLUE, 1.0 / 3.0);
+        assert_eq!(cell_color(&renderer, Position::new(10, 6)), Some(third));
+        assert_eq!(
+            cell_color(&renderer, Position::new(8, 6)),
+            Some(Color::BLUE)
+        );
+    }
+
+    #[test]
+    fn the_grid_is_worked_out_from_the_screen() {
+        assert_eq!(fit_grid(240, 135, 6), Ok((40, 22)));
+        assert_eq!(fit_grid(128, 64, 4), Ok((32, 16)));
+        assert_eq!(fit_grid(240, 135, 1), Err(GridError::CellTooSmall));
+        assert_eq!(fit_grid(240, 20, 6), Err(GridError::GridTooSmall));
+        assert_eq!(fit_grid(480, 320, 2), Err(GridError::GridTooLarge));
+
+        // The border and the status bar come off the screen first
+        let renderer: TestRenderer = GridRenderer::new(MockDisplay::new(240, 135), 6);
+        assert_eq!(renderer.grid_size(), Ok((39, 22)));
+        let renderer = renderer.with_status_bar(12);
+        assert_eq!(renderer.grid_size(), Ok((39, 20)));
+        let small: TestRenderer = GridRenderer::new(MockDisplay::new(160, 80), 8);
+        assert_eq!(small.grid_size(), Ok((19, 9)));
+    }
+
+    #[test]
+    fn the_end_screen_shows_the_round_stats() {
+        let still = DeathAnimConfig {
+            enabled: false,
+            ..DeathAnimConfig::default()
+        };
+        let display = MockDisplay::new(240, 135);
+        let mut renderer: TestRenderer = GridRenderer::new(display, 6).with_death_animation(still);
+        let stats = GameStats {
+            food_eaten: 4,
+            moves: 42,
+            elapsed_ms: 60_000,
+            longest_streak: 3,
+        };
+        renderer.render_stats(&stats).unwrap();
+        let mut game = playing();
+        while game.is_playing() {
+            game.step(None);
+        }
+        renderer.render_game(&game).unwrap();
+
+        let display = renderer.display();
+        assert!(display.has_text("GAME OVER"));
+        assert!(display.has_text("APM 4"));
+        assert!(display.has_text("Moves 42"));
+        assert!(display.has_text("Streak x3"));
+    }
+
+    #[test]
+    fn the_delta_lists_the_cells_that_changed() {
+        let p = |x, y| Position::new(x, y);
+        let before = [p(10, 6), p(9, 6), p(8, 6)];
+        let moved = SnakeDelta::between(&before, &[p(11, 6), p(10, 6), p(9, 6)]).unwrap();
+        assert_eq!(moved.vacated, [p(8, 6)]);
+        assert_eq!(moved.entered, [0]);
+
+        // Growing leaves the tail where it was
+        let grown = [p(11, 6), p(10, 6), p(9, 6), p(8, 6)];
+        let grown = SnakeDelta::between(&before, &grown).unwrap();
+        assert!(grown.vacated.is_empty());
+        assert_eq!(grown.entered, [0]);
+
+        // Too many changes for the scratch sets: redraw everything
+        let far: std::vec::Vec<Position> = (0..10).map(|x| p(x, 0)).collect();
+        assert!(SnakeDelta::between(&before, &far).is_none());
+    }
+
+    #[test]
+    fn eating_draws_the_new_head_and_erases_nothing() {
+        let mut renderer: TestRenderer = GridRenderer::new(MockDisplay::new(240, 135), 6);
+        let mut game = playing();
+        game.set_food(Position::new(11, 6));
+        renderer.render_game(&game).unwrap();
+        let prev_snake = game.snake.clone();
+        let prev_foods: std::vec::Vec<Position> = game.foods().to_vec();
+        game.step(None);
+        assert_eq!(game.snake.len(), 4);
+        renderer.display_mut().reset();
+        renderer
+            .render_delta(&game, &prev_snake, &prev_foods)
+            .unwrap();
+
+        // No cell-sized erase, the food cell turned into the head
+        let background = renderer.theme.background;
+        let erased = renderer.display().shapes().iter().any(
+            |shape| matches!(shape, Shape::Rect { width: 5, color, .. } if *color == background),
+        );
+        assert!(!erased);
+        let head = renderer.theme.snake_head;
+        assert_eq!(cell_color(&renderer, Position::new(11, 6)), Some(head));
+        assert_eq!(
+            cell_color(&renderer, Position::new(10, 6)),
+            Some(renderer.theme.snake)
+        );
+        assert_eq!(cell_color(&renderer, Position::new(8, 6)), None); // Untouched tail
+    }
+
+    #[test]
+    fn the_death_animation_follows_its_config() {
+        let anim = DeathAnimConfig {
+            enabled: true,
+            duration_frames: 3,
+            blink_frames: 4,
+            total_blinks: 1,
+            fade_to: Color::BLUE,
+        };
+        let display = MockDisplay::new(240, 135);
+        let mut renderer: TestRenderer = GridRenderer::new(display, 6).with_death_animation(anim);
+        let mut game = playing();
+        renderer.render_game(&game).unwrap();
+        while game.is_playing() {
+            game.step(None);
+        }
+
+        // Per frame: the title drawn, the screen cleared
+        let mut frames = std::vec::Vec::new();
+        for _ in 0..9 {
+            renderer.display_mut().reset();
+            renderer.render_game(&game).unwrap();
+            let display = renderer.display();
+            let cleared = display
+                .shapes()
+                .iter()
+                .any(|shape| matches!(shape, Shape::Clear(_)));
+            frames.push((display.has_text("GAME OVER"), cleared));
+        }
+        let shrinking = (false, false);
+        let shown = (true, true);
+        let hidden = (false, true);
+        let still = (false, false);
+        assert_eq!(
+            frames,
+            [shrinking, shrinking, shown, still, hidden, still, shown, still, still]
+        );
+    }
+
+    #[test]
+    fn the_snake_fades_as_it_shrinks() {
+        let anim = DeathAnimConfig {
+            duration_frames: 4,
+            fade_to: Color::BLUE,
+            ..DeathAnimConfig::default()
+        };
+        let display = MockDisplay::new(240, 135);
+        let mut renderer: TestRenderer = GridRenderer::new(display, 6).with_death_animation(anim);
+        let mut game = playing();
+        renderer.render_game(&game).unwrap();
+        while game.is_playing() {
+            game.step(None);
+        }
+        renderer.render_game(&game).unwrap();
+        let snake = renderer.theme.snake;
+        let color = Color::lerp(snake, Color::BLUE, 0.5);
+        let head = game.snake[0];
+        assert_eq!(cell_color(&renderer, head), Some(color));
+    }
+
+    #[test]
+    fn the_large_font_shows_only_the_final_score() {
+        let still = DeathAnimConfig {
+            enabled: false,
+            ..DeathAnimConfig::default()
+        };
+        let large = Theme {
+            score_font: Font::Large,
+            ..Theme::classic()
+        };
+        let mut game = playing();
+        game.score = 40;
+        while game.is_playing() {
+            game.step(None);
+        }
+
+        let display = MockDisplay::new(240, 135);
+        let mut renderer: TestRenderer = GridRenderer::new(display, 6)
+            .with_death_animation(still)
+            .with_theme(large);
+        renderer.render_game(&game).unwrap();
+        let score = renderer
+            .display()
+            .shapes()
+            .iter()
+            .find_map(|shape| match shape {
+                Shape::Text {
+                    text, x, y, font, ..
+                } if text.as_str() == "40" => Some((*x, *y, *font)),
+                _ => None,
+            });
+        assert_eq!(score, Some((110, 55, Font::Large)));
+        assert!(!renderer.display().has_text("Final Score"));
+
+        let display = MockDisplay::new(240, 135);
+        let mut renderer: TestRenderer = GridRenderer::new(display, 6).with_death_animation(still);
+        renderer.render_game(&game).unwrap();
+        assert!(renderer.display().has_text("Final Score: 40"));
+    }
+
+    #[test]
+    fn margins_and_offsets_inset_the_board() {
+        let plain: TestRenderer = GridRenderer::new(MockDisplay::new(240, 135), 6);
+        assert_eq!(plain.grid_size(), Ok((39, 22)));
+        assert_eq!(plain.cell_origin(Position::new(0, 0)), (2, 1));
+
+        let inset: TestRenderer =
+            GridRenderer::new(MockDisplay::new(240, 135), 6).with_margin(10, 5);
+        assert_eq!(inset.grid_size(), Ok((36, 20)));
+        assert_eq!(inset.cell_origin(Position::new(0, 0)), (11, 7));
+        assert_eq!(
+            inset.cell_origin(Position::new(35, 19)),
+            (11 + 35 * 6, 7 + 19 * 6)
+        );
+
+        // An offset only takes space from the left and top
+        let shifted: TestRenderer =
+            GridRenderer::new(MockDisplay::new(240, 135), 6).with_board_offset(20, 10);
+        assert_eq!(shifted.grid_size(), Ok((36, 20)));
+        assert_eq!(shifted.cell_origin(Position::new(0, 0)), (21, 12));
+
+        // A drawn board stays out of the margin
+        let mut renderer: TestRenderer =
+            GridRenderer::new(MockDisplay::new(240, 135), 6).with_margin(10, 5);
+        renderer.render_game(&playing()).unwrap();
+        let background = renderer.theme.background;
+        let inside = renderer
+            .display()
+            .shapes()
+            .iter()
+            .all(|shape| match *shape {
+                Shape::Rect { x, y, color, .. } if color != background => x >= 10 && y >= 5,
+                _ => true,
+            });
+        assert!(inside);
+    }
+
+    #[test]
+    fn every_food_is_drawn() {
+        let mut renderer: TestRenderer = GridRenderer::new(MockDisplay::new(240, 135), 6);
+        let mut game = playing();
+        game.set_food_count(3);
+        renderer.render_game(&game).unwrap();
+
+        let food = renderer.theme.food;
+        assert_eq!(renderer.display().rect_count(food), 3);
+        for position in game.foods() {
+            assert_eq!(cell_color(&renderer, *position), Some(food));
+        }
+    }
+
+    #[test]
+    fn a_move_only_draws_the_food_that_changed() {
+        let mut renderer: TestRenderer = GridRenderer::new(MockDisplay::new(240, 135), 6);
+        let mut game = playing();
+        game.set_food_count(3);
+        game.set_food(Position::new(11, 6));
+        renderer.render_game(&game).unwrap();
+        let prev_snake = game.snake.clone();
+        let prev_foods: std::vec::Vec<Position> = game.foods().to_vec();
+
+        // The snake eats the food in front of it, one new food replaces it
+        game.step(None);
+        renderer.display_mut().reset();
+        renderer
+            .render_delta(&game, &prev_snake, &prev_foods)
+            .unwrap();
+
+        let fresh: std::vec::Vec<_> = game
+            .foods()
+            .iter()
+            .filter(|food| !prev_foods.contains(food))
+            .collect();
+        assert_eq!(fresh.len(), 1);
+        let food = renderer.theme.food;
+        assert_eq!(renderer.display().rect_count(food), 1);
+        assert_eq!(cell_color(&renderer, *fresh[0]), Some(food));
+    }
+
+    #[test]
+    fn food_that_went_away_is_erased() {
+        let mut renderer: TestRenderer = GridRenderer::new(MockDisplay::new(240, 135), 6);
+        let mut game = playing();
+        game.set_food_count(3);
+        renderer.render_game(&game).unwrap();
+        let prev_snake = game.snake.clone();
+        let prev_foods: std::vec::Vec<Position> = game.foods().to_vec();
+
+        // Dropping back to one food leaves two cells to clear
+        game.set_food_count(1);
+        game.step(None);
+        renderer.display_mut().reset();
+        renderer
+            .render_delta(&game, &prev_snake, &prev_foods)
+            .unwrap();
+
+        let background = renderer.theme.background;
+        for gone in prev_foods
+            .iter()
+            .filter(|food| !game.foods().contains(food))
+        {
+            assert_eq!(cell_color(&renderer, *gone), Some(background));
+        }
+        assert_eq!(renderer.display().rect_count(renderer.theme.food), 0);
+    }
+}
@@ -0,0 +1,117 @@
+// Replay recording: the turns of a round, enough to play it again
+//
+// The game is deterministic for a given seed, so a round is fully described by
+// its seed and the ticks on which the direction changed. `Game::replay` plays
+// such a log back.
+
+use heapless::Vec;
+
+use crate::game::{Direction, Game};
+
+// Turns kept per recording, a full log stops recording and marks it truncated
+pub const MAX_REPLAY_TURNS: usize = 256;
+
+pub type ReplayLog = Vec<(u16, Direction), MAX_REPLAY_TURNS>;
+
+pub struct Recorder {
+    seed: u32,
+    turns: ReplayLog,
+    last: Option<Direction>, // Direction of the last recorded tick
+    truncated: bool,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self {
+            seed: 0,
+            turns: Vec::new(),
+            last: None,
+            truncated: false,
+        }
+    }
+
+    // Start a new recording, call it when the round starts
+    pub fn start(&mut self, game: &Game) {
+        self.seed = game.seed();
+        self.turns.clear();
+        self.last = None;
+        self.truncated = false;
+    }
+
+    // Call after each `Game::update`, logs the direction the move used when it
+    // changed
+    pub fn record(&mut self, game: &Game) {
+        if self.last == Some(game.direction) || game.ticks() == 0 {
+            return;
+        }
+        let tick = game.ticks() - 1; // The move that just happened
+        if self.turns.push((tick, game.direction)).is_err() {
+            self.truncated = true;
+            return;
+        }
+        self.last = Some(game.direction);
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    pub fn turns(&self) -> &[(u16, Direction)] {
+        &self.turns
+    }
+
+    // The log ran out of room, a replay will diverge after the last turn
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_changes_of_direction_are_logged() {
+        let mut game: Game = Game::new(20, 12);
+        game.start();
+        let mut recorder = Recorder::new();
+        recorder.start(&game);
+        for direction in [None, None, Some(Direction::Up), Some(Direction::Up), None] {
+            game.step(direction);
+            recorder.record(&game);
+        }
+        assert_eq!(
+            recorder.turns(),
+            [(0, Direction::Right), (2, Direction::Up)]
+        );
+        assert_eq!(recorder.seed(), game.seed());
+    }
+
+    #[test]
+    fn a_full_log_is_marked_truncated() {
+        let mut game: Game = Game::new(40, 40);
+        game.set_wrap_walls(true);
+        game.start();
+        let mut recorder = Recorder::new();
+        recorder.start(&game);
+        let turns = [
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+            Direction::Right,
+        ];
+        for i in 0..=MAX_REPLAY_TURNS {
+            game.step(Some(turns[i % turns.len()]));
+            recorder.record(&game);
+        }
+        assert!(game.is_playing());
+        assert_eq!(recorder.turns().len(), MAX_REPLAY_TURNS);
+        assert!(recorder.truncated());
+    }
+}
@@ -0,0 +1,445 @@
+// Small retained-mode layer for overlays (start, pause, game over, HUD...)
+//
+// Instead of drawing and erasing screens by hand, overlays are described as a
+// list of widgets. Changing a widget only marks it dirty; `flush` then erases
+// the area it used to cover and redraws just the widgets that changed, along
+// with the ones the erasing or redrawing cut into.
+
+use heapless::{String, Vec};
+
+use crate::traits::{Color, GameDisplay};
+
+// Glyph metrics used to compute text bounds (matches FONT_6X10)
+pub const CHAR_WIDTH: u16 = 6;
+pub const CHAR_HEIGHT: u16 = 10;
+
+pub const MAX_TEXT_LEN: usize = 32;
+
+// Width of `text` in pixels, one advance per byte (the fonts are ASCII)
+pub fn text_width(text: &str) -> u16 {
+    text.len() as u16 * CHAR_WIDTH
+}
+
+// Handle returned by `Scene::add`, used to update a widget later
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WidgetId(usize);
+
+// Visual content of a widget
+#[derive(Debug, Clone)]
+pub enum WidgetKind {
+    Text(String<MAX_TEXT_LEN>),
+    Rect {
+        width: u16,
+        height: u16,
+    },
+    // 1 bit per pixel, rows padded to a whole byte, MSB first
+    Bitmap {
+        data: &'static [u8],
+        width: u16,
+        height: u16,
+    },
+}
+
+impl WidgetKind {
+    fn size(&self) -> (u16, u16) {
+        match self {
+            WidgetKind::Text(text) => (text_width(text), CHAR_HEIGHT),
+            WidgetKind::Rect { width, height } => (*width, *height),
+            WidgetKind::Bitmap { width, height, .. } => (*width, *height),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Bounds {
+    x: u16,
+    y: u16,
+    width: u16,
+    height: u16,
+}
+
+impl Bounds {
+    fn overlaps(&self, other: &Bounds) -> bool {
+        let right = |b: &Bounds| b.x as u32 + b.width as u32;
+        let bottom = |b: &Bounds| b.y as u32 + b.height as u32;
+        (self.x as u32) < right(other)
+            && (other.x as u32) < right(self)
+            && (self.y as u32) < bottom(other)
+            && (other.y as u32) < bottom(self)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Widget {
+    kind: WidgetKind,
+    x: u16,
+    y: u16,
+    color: Color,
+    visible: bool,
+    dirty: bool,
+    drawn: Option<Bounds>, // Area covered on screen by the last flush
+}
+
+impl Widget {
+    fn new(kind: WidgetKind, x: u16, y: u16, color: Color) -> Self {
+        Self {
+            kind,
+            x,
+            y,
+            color,
+            visible: true,
+            dirty: true,
+            drawn: None,
+        }
+    }
+
+    pub fn text(text: &str, x: u16, y: u16, color: Color) -> Self {
+        let mut content = String::new();
+        for c in text.chars() {
+            if content.push(c).is_err() {
+                break;
+            }
+        }
+        Self::new(WidgetKind::Text(content), x, y, color)
+    }
+
+    pub fn rect(x: u16, y: u16, width: u16, height: u16, color: Color) -> Self {
+        Self::new(WidgetKind::Rect { width, height }, x, y, color)
+    }
+
+    pub fn bitmap(
+        data: &'static [u8],
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        color: Color,
+    ) -> Self {
+        Self::new(
+            WidgetKind::Bitmap {
+                data,
+                width,
+                height,
+            },
+            x,
+            y,
+            color,
+        )
+    }
+
+    fn bounds(&self) -> Bounds {
+        let (width, height) = self.kind.size();
+        Bounds {
+            x: self.x,
+            y: self.y,
+            width,
+            height,
+        }
+    }
+
+    fn draw<D: GameDisplay>(&self, display: &mut D) -> Result<(), D::Error> {
+        match &self.kind {
+            WidgetKind::Text(text) => display.draw_text(text, self.x, self.y, self.color),
+            WidgetKind::Rect { width, height } => {
+                display.draw_rect(self.x, self.y, *width, *height, self.color)
+            }
+            WidgetKind::Bitmap {
+                data,
+                width,
+                height,
+            } => {
+                let stride = (*width as usize).div_ceil(8);
+                for row in 0..*height {
+                    for col in 0..*width {
+                        let byte = data.get(row as usize * stride + col as usize / 8);
+                        let set = byte.is_some_and(|b| b & (0x80 >> (col % 8)) != 0);
+                        if set {
+                            display.draw_rect(self.x + col, self.y + row, 1, 1, self.color)?;
+                        }
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// Fixed-capacity collection of widgets flushed through a `GameDisplay`
+pub struct Scene<const N: usize> {
+    widgets: Vec<Widget, N>,
+    background: Color,
+}
+
+impl<const N: usize> Scene<N> {
+    pub fn new(background: Color) -> Self {
+        Self {
+            widgets: Vec::new(),
+            background,
+        }
+    }
+
+    // Add a widget, returning `None` when the scene is full
+    pub fn add(&mut self, widget: Widget) -> Option<WidgetId> {
+        let id = WidgetId(self.widgets.len());
+        self.widgets.push(widget).ok()?;
+        Some(id)
+    }
+
+    pub fn clear(&mut self) {
+        self.widgets.clear();
+    }
+
+    pub fn set_text(&mut self, id: WidgetId, text: &str) {
+        if let Some(widget) = self.widgets.get_mut(id.0) {
+            if let WidgetKind::Text(current) = &widget.kind {
+                if current.as_str() == text {
+                    return;
+                }
+            }
+            let replacement = Widget::text(text, widget.x, widget.y, widget.color);
+            widget.kind = replacement.kind;
+            widget.dirty = true;
+        }
+    }
+
+    pub fn set_color(&mut self, id: WidgetId, color: Color) {
+        if let Some(widget) = self.widgets.get_mut(id.0) {
+            if widget.color != color {
+                widget.color = color;
+                widget.dirty = true;
+            }
+        }
+    }
+
+    pub fn set_position(&mut self, id: WidgetId, x: u16, y: u16) {
+        if let Some(widget) = self.widgets.get_mut(id.0) {
+            if widget.x != x || widget.y != y {
+                widget.x = x;
+                widget.y = y;
+                widget.dirty = true;
+            }
+        }
+    }
+
+    pub fn set_visible(&mut self, id: WidgetId, visible: bool) {
+        if let Some(widget) = self.widgets.get_mut(id.0) {
+            if widget.visible != visible {
+                widget.visible = visible;
+                widget.dirty = true;
+            }
+        }
+    }
+
+    // Show or hide every widget, e.g. to blink a whole overlay
+    pub fn set_all_visible(&mut self, visible: bool) {
+        for widget in &mut self.widgets {
+            if widget.visible != visible {
+                widget.visible = visible;
+                widget.dirty = true;
+            }
+        }
+    }
+
+    // Force every widget to be redrawn on the next flush (e.g. after a full clear)
+    pub fn invalidate(&mut self) {
+        for widget in &mut self.widgets {
+            widget.dirty = true;
+            widget.drawn = None;
+        }
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.widgets.iter().any(|w| w.dirty)
+    }
+
+    // A clean widget has to be drawn again when a dirty one is erased from, or
+    // drawn over, the area it covers. Repeats until nothing new is marked, as
+    // redrawing a widget can in turn cut into another.
+    fn mark_damaged(&mut self) {
+        loop {
+            let mut marked = false;
+            for i in 0..self.widgets.len() {
+                let widget = &self.widgets[i];
+                let Some(area) = widget.drawn.filter(|_| !widget.dirty) else {
+                    continue;
+                };
+                let damaged = self.widgets.iter().any(|other| {
+                    other.dirty
+                        && (other.drawn.is_some_and(|old| old.overlaps(&area))
+                            || (other.visible && other.bounds().overlaps(&area)))
+                });
+                if damaged {
+                    self.widgets[i].dirty = true;
+                    marked = true;
+                }
+            }
+            if !marked {
+                return;
+            }
+        }
+    }
+
+    // Erase and redraw only the widgets that changed since the last flush
+    pub fn flush<D: GameDisplay>(&mut self, display: &mut D) -> Result<(), D::Error> {
+        self.mark_damaged();
+
+        // Erase everything stale first so overlapping widgets aren't wiped after being drawn
+        for widget in &mut self.widgets {
+            if !widget.dirty {
+                continue;
+            }
+            if let Some(old) = widget.drawn.take() {
+                display.draw_rect(old.x, old.y, old.width, old.height, self.background)?;
+            }
+        }
+
+        for widget in &mut self.widgets {
+            if !widget.dirty {
+                continue;
+            }
+            if widget.visible {
+                widget.draw(display)?;
+                widget.drawn = Some(widget.bounds());
+            }
+            widget.dirty = false;
+        }
+
+        display.update()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockDisplay, Shape};
+
+    fn texts(display: &MockDisplay) -> std::vec::Vec<&str> {
+        let shapes = display.shapes().iter();
+        shapes
+            .filter_map(|shape| match shape {
+                Shape::Text { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn only_changed_widgets_are_drawn() {
+        let mut display: MockDisplay = MockDisplay::new(240, 135);
+        let mut scene: Scene<3> = Scene::new(Color::BLACK);
+        let score = scene
+            .add(Widget::text("Score: 0", 0, 0, Color::WHITE))
+            .unwrap();
+        scene.add(Widget::text("Food: 0", 120, 0, Color::WHITE));
+        scene.add(Widget::rect(0, 20, 10, 10, Color::RED));
+        scene.flush(&mut display).unwrap();
+        assert_eq!(texts(&display), ["Score: 0", "Food: 0"]);
+        assert_eq!(display.rect_count(Color::RED), 1);
+
+        // Nothing changed: no drawing at all
+        display.reset();
+        scene.flush(&mut display).unwrap();
+        assert!(display.shapes().is_empty());
+        assert!(!scene.is_dirty());
+
+        // Setting the same text again isn't a change either
+        scene.set_text(score, "Score: 0");
+        assert!(!scene.is_dirty());
+
+        scene.set_text(score, "Score: 10");
+        scene.flush(&mut display).unwrap();
+        let erase = Shape::Rect {
+            x: 0,
+            y: 0,
+            width: 48,
+            height: CHAR_HEIGHT,
+            color: Color::BLACK,
+        };
+        assert_eq!(display.shapes()[0], erase);
+        assert_eq!(texts(&display), ["Score: 10"]);
+        assert_eq!(display.rect_count(Color::RED), 0);
+    }
+
+    #[test]
+    fn widgets_cut_into_by_an_erase_are_redrawn() {
+        let mut display: MockDisplay = MockDisplay::new(240, 135);
+        let mut scene: Scene<3> = Scene::new(Color::BLACK);
+        let panel = scene.add(Widget::rect(0, 0, 100, 20, Color::BLUE)).unwrap();
+        scene.add(Widget::text("PAUSED", 10, 5, Color::WHITE));
+        scene.add(Widget::text("far away", 0, 100, Color::WHITE));
+        scene.flush(&mut display).unwrap();
+
+        // Hiding the panel erases it, the text on top has to come back
+        display.reset();
+        scene.set_visible(panel, false);
+        scene.flush(&mut display).unwrap();
+        assert_eq!(texts(&display), ["PAUSED"]);
+        assert_eq!(display.rect_count(Color::BLUE), 0);
+
+        // Showing it again paints over the text, which is drawn on top again
+        display.reset();
+        scene.set_visible(panel, true);
+        scene.flush(&mut display).unwrap();
+        assert_eq!(display.rect_count(Color::BLUE), 1);
+        assert_eq!(texts(&display), ["PAUSED"]);
+        let panel_at = display.shapes().iter().position(|s| {
+            matches!(
+                s,
+                Shape::Rect {
+                    color: Color::BLUE,
+                    ..
+                }
+            )
+        });
+        let text_at = display
+            .shapes()
+            .iter()
+            .position(|s| matches!(s, Shape::Text { .. }));
+        assert!(panel_at < text_at);
+    }
+
+    #[test]
+    fn a_moved_widget_is_erased_where_it_was() {
+        let mut display: MockDisplay = MockDisplay::new(240, 135);
+        let mut scene: Scene<1> = Scene::new(Color::BLACK);
+        let marker = scene.add(Widget::rect(5, 5, 4, 4, Color::RED)).unwrap();
+        assert_eq!(scene.add(Widget::rect(0, 0, 1, 1, Color::RED)), None);
+        scene.flush(&mut display).unwrap();
+
+        display.reset();
+        scene.set_position(marker, 20, 5);
+        scene.flush(&mut display).unwrap();
+        let erase = Shape::Rect {
+            x: 5,
+            y: 5,
+            width: 4,
+            height: 4,
+            color: Color::BLACK,
+        };
+        let draw = Shape::Rect {
+            x: 20,
+            y: 5,
+            width: 4,
+            height: 4,
+            color: Color::RED,
+        };
+        assert_eq!(display.shapes(), [erase, draw]);
+    }
+
+    #[test]
+    fn bitmaps_draw_their_set_bits() {
+        static ARROW: [u8; 2] = [0b1000_0000, 0b0100_0000];
+        let mut display: MockDisplay = MockDisplay::new(240, 135);
+        let mut scene: Scene<1> = Scene::new(Color::BLACK);
+        scene.add(Widget::bitmap(&ARROW, 10, 10, 2, 2, Color::GREEN));
+        scene.flush(&mut display).unwrap();
+        let pixel = |x, y| Shape::Rect {
+            x,
+            y,
+            width: 1,
+            height: 1,
+            color: Color::GREEN,
+        };
+        assert_eq!(display.shapes(), [pixel(10, 10), pixel(11, 11)]);
+    }
+}
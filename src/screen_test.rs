@@ -2,33 +2,37 @@
 #![no_main]
 
 use core::cell::RefCell;
-use defmt::info;
+use defmt::{error, info};
 use embassy_embedded_hal::shared_bus::blocking::spi::SpiDeviceWithConfig;
 use embassy_executor::Spawner;
 use embassy_rp::gpio::{Level, Output};
 use embassy_rp::spi::{Config as SpiConfig, Spi};
 use embassy_sync::blocking_mutex::raw::NoopRawMutex;
 use embassy_sync::blocking_mutex::Mutex as BlockingMutex;
-use embassy_time::Timer;
+use embassy_time::{Instant, Timer};
+use snake_embedded::diagnostics::{draw_color_bars, run_display_test};
+use snake_embedded::traits::GamePlatform;
 use static_cell::StaticCell;
 use {defmt_rtt as _, panic_probe as _};
 
-use embedded_graphics::{
-    mono_font::{ascii::FONT_6X10, MonoTextStyle},
-    pixelcolor::Rgb565,
-    prelude::*,
-    primitives::{Circle, PrimitiveStyle, Rectangle},
-    text::{Baseline, Text},
-};
+use embedded_graphics::{pixelcolor::Rgb565, prelude::*};
 use mipidsi::interface::SpiInterface;
-use mipidsi::{
-    models::ST7789,
-    options::ColorInversion,
-    Builder,
-};
+use mipidsi::{options::ColorInversion, Builder};
 
+// Same panels as the game: the ST7789 1.14" by default, `st7735` for 1.8" hats
+#[cfg(not(feature = "st7735"))]
+use mipidsi::models::ST7789 as PanelModel;
+#[cfg(feature = "st7735")]
+use mipidsi::{models::ST7735s as PanelModel, options::ColorOrder};
+
+#[cfg(not(feature = "st7735"))]
 const DISPLAY_WIDTH: i32 = 135;
+#[cfg(not(feature = "st7735"))]
 const DISPLAY_HEIGHT: i32 = 240;
+#[cfg(feature = "st7735")]
+const DISPLAY_WIDTH: i32 = 128;
+#[cfg(feature = "st7735")]
+const DISPLAY_HEIGHT: i32 = 160;
 
 type SpiBus = BlockingMutex<
     NoopRawMutex,
@@ -62,8 +66,8 @@ async fn main(_spawner: Spawner) {
     let spi_device = SpiDeviceWithConfig::new(spi_bus, Output::new(cs, Level::High), spi_config);
 
     // Buffer for mipidsi
-    static mut BUFFER: [u8; 64] = [0; 64];
-    let buffer = unsafe { (&raw mut BUFFER).cast::<[u8; 64]>().as_mut().unwrap() };
+    static BUFFER: StaticCell<[u8; 64]> = StaticCell::new();
+    let buffer = BUFFER.init([0; 64]);
 
     // Create SPI interface
     let spi_interface = SpiInterface::new(spi_device, Output::new(dc, Level::Low), buffer);
@@ -72,10 +76,18 @@ async fn main(_spawner: Spawner) {
     let reset_pin = Output::new(rst, Level::High);
 
     // Initialize display
-    let mut display = Builder::new(ST7789, spi_interface)
+    #[cfg(not(feature = "st7735"))]
+    let builder = Builder::new(PanelModel, spi_interface)
         .display_size(DISPLAY_WIDTH as u16, DISPLAY_HEIGHT as u16) // Rotated dimensions
         .display_offset(53, 40) // Waveshare LCD 1.14" offset for 90° rotation
-        .invert_colors(ColorInversion::Inverted)
+        .invert_colors(ColorInversion::Inverted);
+    #[cfg(feature = "st7735")]
+    let builder = Builder::new(PanelModel, spi_interface)
+        .display_size(DISPLAY_WIDTH as u16, DISPLAY_HEIGHT as u16)
+        .display_offset(2, 1) // 128x160 window in the ST7735S's 132x162 RAM
+        .color_order(ColorOrder::Bgr)
+        .invert_colors(ColorInversion::Normal);
+    let mut display = builder
         .reset_pin(reset_pin)
         .init(&mut embassy_time::Delay)
         .unwrap();
@@ -85,153 +97,36 @@ async fn main(_spawner: Spawner) {
 
     info!("Display initialized, starting tests...");
 
-    display.clear(Rgb565::BLACK).unwrap();
-    // Test 1: Fill screen with colors
-    display
-        .fill_solid(
-            &Rectangle::new(
-                Point::new(0, 0),
-                Size::new(DISPLAY_WIDTH as u32, DISPLAY_HEIGHT as u32),
-            ),
-            Rgb565::RED,
-        )
-        .unwrap();
-    Timer::after_millis(1000).await;
-
-    display
-        .fill_solid(
-            &Rectangle::new(
-                Point::new(0, 0),
-                Size::new(DISPLAY_WIDTH as u32, DISPLAY_HEIGHT as u32),
-            ),
-            Rgb565::GREEN,
-        )
-        .unwrap();
-    Timer::after_millis(1000).await;
-
-    display
-        .fill_solid(
-            &Rectangle::new(
-                Point::new(0, 0),
-                Size::new(DISPLAY_WIDTH as u32, DISPLAY_HEIGHT as u32),
-            ),
-            Rgb565::BLUE,
-        )
-        .unwrap();
-    Timer::after_millis(1000).await;
-
-    // Test 2: Draw shapes
-    display
-        .fill_solid(
-            &Rectangle::new(
-                Point::new(0, 0),
-                Size::new(DISPLAY_WIDTH as u32, DISPLAY_HEIGHT as u32),
-            ),
-            Rgb565::BLACK,
-        )
-        .unwrap();
-
-    // Draw some rectangles
-    Rectangle::new(Point::new(10, 10), Size::new(50, 30))
-        .into_styled(PrimitiveStyle::with_fill(Rgb565::RED))
-        .draw(&mut display)
-        .unwrap();
-
-    Rectangle::new(Point::new(70, 20), Size::new(40, 40))
-        .into_styled(PrimitiveStyle::with_fill(Rgb565::GREEN))
-        .draw(&mut display)
-        .unwrap();
-
-    Rectangle::new(Point::new(120, 15), Size::new(30, 50))
-        .into_styled(PrimitiveStyle::with_fill(Rgb565::BLUE))
-        .draw(&mut display)
-        .unwrap();
-
-    // Draw some circles
-    Circle::new(Point::new(170, 10), 25)
-        .into_styled(PrimitiveStyle::with_fill(Rgb565::YELLOW))
-        .draw(&mut display)
-        .unwrap();
-
-    Circle::new(Point::new(200, 40), 20)
-        .into_styled(PrimitiveStyle::with_fill(Rgb565::MAGENTA))
-        .draw(&mut display)
-        .unwrap();
+    if display.clear(Rgb565::BLACK).is_err() {
+        error!("Display clear failed");
+    }
 
-    Timer::after_millis(2000).await;
-
-    // Test 3: Text display
-    display
-        .fill_solid(
-            &Rectangle::new(
-                Point::new(0, 0),
-                Size::new(DISPLAY_WIDTH as u32, DISPLAY_HEIGHT as u32),
-            ),
-            Rgb565::BLACK,
-        )
-        .unwrap();
+    // Color bars first, for checking colors and the panel offsets
+    if draw_color_bars(&mut display).is_err() {
+        error!("Color bars failed to draw");
+    }
+    Timer::after_millis(3000).await;
 
-    let text_style = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+    loop {
+        if run_display_test(&mut display, &TimerDelay, true)
+            .await
+            .is_err()
+        {
+            error!("Display test failed to draw");
+        }
+        info!("Display test done, running it again");
+    }
+}
 
-    Text::with_baseline("Screen Test", Point::new(10, 20), text_style, Baseline::Top)
-        .draw(&mut display)
-        .unwrap();
+// The test only needs the pauses of a platform
+struct TimerDelay;
 
-    Text::with_baseline(
-        "Display: 240x135",
-        Point::new(10, 40),
-        text_style,
-        Baseline::Top,
-    )
-    .draw(&mut display)
-    .unwrap();
-
-    Text::with_baseline(
-        "All systems OK!",
-        Point::new(10, 60),
-        text_style,
-        Baseline::Top,
-    )
-    .draw(&mut display)
-    .unwrap();
-
-    Timer::after_millis(2000).await;
-
-    // Test 4: Animated pattern
-    info!("Starting animation test...");
+impl GamePlatform for TimerDelay {
+    async fn delay_ms(&self, ms: u32) {
+        Timer::after_millis(ms as u64).await;
+    }
 
-    loop {
-        for i in 0..30 {
-            display
-                .fill_solid(
-                    &Rectangle::new(
-                        Point::new(0, 0),
-                        Size::new(DISPLAY_WIDTH as u32, DISPLAY_HEIGHT as u32),
-                    ),
-                    Rgb565::BLACK,
-                )
-                .unwrap();
-
-            // Moving circle
-            let x = (i * 8) % (DISPLAY_WIDTH - 20);
-            let y = 50 + ((i * 2) % 20);
-
-            Circle::new(Point::new(x, y), 10)
-                .into_styled(PrimitiveStyle::with_fill(Rgb565::CYAN))
-                .draw(&mut display)
-                .unwrap();
-
-            // Static text
-            Text::with_baseline(
-                "Animation Test",
-                Point::new(10, 10),
-                text_style,
-                Baseline::Top,
-            )
-            .draw(&mut display)
-            .unwrap();
-
-            Timer::after_millis(100).await;
-        }
+    fn current_time_ms(&self) -> u32 {
+        Instant::now().as_millis() as u32
     }
 }
@@ -0,0 +1,150 @@
+// Screensaver: a small logo bouncing around a blank screen, for an end screen
+// left alone for a while, so no pixel stays lit in one place (OLED burn-in)
+// and the device still looks alive
+//
+// The position is worked out from the time alone, so the logo keeps the same
+// pace whatever the frame rate, including the few frames a second of the idle
+// sleep. Each step only erases and redraws the logo, not the whole screen.
+
+use crate::scene::{CHAR_HEIGHT, CHAR_WIDTH};
+use crate::traits::{GameDisplay, Theme};
+
+const LOGO_TEXT: &str = "SNAKE";
+const DOT_RADIUS: u16 = 4; // The moving circle in front of the text
+
+/// Size of the logo: the dot, a gap, then the text
+pub const LOGO_WIDTH: u16 = 2 * DOT_RADIUS + 1 + 3 + LOGO_TEXT.len() as u16 * CHAR_WIDTH;
+pub const LOGO_HEIGHT: u16 = CHAR_HEIGHT;
+
+// Pixels per second on each axis, different so the logo covers the screen
+const SPEED_X: u32 = 12;
+const SPEED_Y: u32 = 7;
+
+/// Top-left corner of the logo at `t_ms` on a `width` x `height` screen. The
+/// logo bounces off the edges and always stays on the screen; an axis too
+/// small for it stays at 0.
+pub fn position(width: u16, height: u16, t_ms: u32) -> (u16, u16) {
+    let x = bounce(
+        t_ms as u64 * SPEED_X as u64 / 1000,
+        width.saturating_sub(LOGO_WIDTH),
+    );
+    let y = bounce(
+        t_ms as u64 * SPEED_Y as u64 / 1000,
+        height.saturating_sub(LOGO_HEIGHT),
+    );
+    (x, y)
+}
+
+// Back and forth between 0 and `range`, `distance` pixels from the start
+fn bounce(distance: u64, range: u16) -> u16 {
+    if range == 0 {
+        return 0;
+    }
+    let period = 2 * range as u64;
+    let along = distance % period;
+    if along > range as u64 {
+        (period - along) as u16
+    } else {
+        along as u16
+    }
+}
+
+/// The logo as last drawn, so a step only repaints what moved
+#[derive(Debug, Default)]
+pub struct Screensaver {
+    shown: Option<(u16, u16)>,
+}
+
+impl Screensaver {
+    pub fn new() -> Self {
+        Self { shown: None }
+    }
+
+    /// Forget the screen, the next step starts from a cleared one
+    pub fn reset(&mut self) {
+        self.shown = None;
+    }
+
+    /// Draw the logo where it is at `t_ms`, clearing the screen on the first
+    /// step and erasing the previous logo after that
+    pub fn step<D: GameDisplay>(
+        &mut self,
+        display: &mut D,
+        t_ms: u32,
+        theme: &Theme,
+    ) -> Result<(), D::Error> {
+        let (width, height) = display.dimensions();
+        let (x, y) = position(width, height, t_ms);
+        match self.shown {
+            Some(shown) if shown == (x, y) => return Ok(()),
+            Some((old_x, old_y)) => {
+                display.draw_rect(old_x, old_y, LOGO_WIDTH, LOGO_HEIGHT, theme.background)?
+            }
+            None => display.clear(theme.background)?,
+        }
+        self.shown = Some((x, y));
+
+        let center_y = y + LOGO_HEIGHT / 2;
+        display.draw_circle(x + DOT_RADIUS, center_y, DOT_RADIUS, theme.snake_head)?;
+        let text_x = x + 2 * DOT_RADIUS + 1 + 3;
+        display.draw_text(LOGO_TEXT, text_x, y, theme.text)?;
+        display.update()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockDisplay, Shape};
+
+    #[test]
+    fn the_logo_bounces_off_the_edges() {
+        let range_x = 240 - LOGO_WIDTH;
+        assert_eq!(position(240, 135, 0), (0, 0));
+        assert_eq!(position(240, 135, 1000), (12, 7));
+        let at_edge = range_x as u32 * 1000 / SPEED_X;
+        assert_eq!(position(240, 135, at_edge).0, range_x);
+        assert_eq!(position(240, 135, at_edge + 1000).0, range_x - 12);
+        assert_eq!(position(240, 135, 2 * at_edge).0, 0);
+
+        // It never leaves the screen, and stays put where it doesn't fit
+        for t_ms in (0..600_000).step_by(777) {
+            let (x, y) = position(240, 135, t_ms);
+            assert!(x + LOGO_WIDTH <= 240 && y + LOGO_HEIGHT <= 135);
+        }
+        assert_eq!(position(LOGO_WIDTH - 1, 5, 12_345), (0, 0));
+    }
+
+    #[test]
+    fn a_step_only_repaints_the_logo() {
+        let theme = Theme::classic();
+        let mut display: MockDisplay = MockDisplay::new(240, 135);
+        let mut screensaver = Screensaver::new();
+        screensaver.step(&mut display, 0, &theme).unwrap();
+        assert_eq!(display.shapes()[0], Shape::Clear(theme.background));
+        assert!(display.has_text("SNAKE"));
+
+        // Not moved yet: nothing to draw
+        display.reset();
+        screensaver.step(&mut display, 50, &theme).unwrap();
+        assert!(display.shapes().is_empty());
+
+        // Moved: the old logo is erased, not the screen
+        screensaver.step(&mut display, 1000, &theme).unwrap();
+        let erase = Shape::Rect {
+            x: 0,
+            y: 0,
+            width: LOGO_WIDTH,
+            height: LOGO_HEIGHT,
+            color: theme.background,
+        };
+        assert_eq!(display.shapes()[0], erase);
+        assert!(!display.shapes().contains(&Shape::Clear(theme.background)));
+
+        // After a reset it starts from a cleared screen again
+        display.reset();
+        screensaver.reset();
+        screensaver.step(&mut display, 1000, &theme).unwrap();
+        assert_eq!(display.shapes()[0], Shape::Clear(theme.background));
+    }
+}
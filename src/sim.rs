@@ -0,0 +1,58 @@
+// Desktop build of the game, running the same engine as the Pico in a window.
+// See hardware/simulator.rs for how to run it.
+
+#[path = "hardware/simulator.rs"]
+mod simulator;
+
+use simulator::{FileStorage, SimDisplay, SimInput, SimPlatform};
+use snake_embedded::engine::GameEngine;
+use snake_embedded::levels::LEVELS;
+use snake_embedded::renderer::GridRenderer;
+use snake_embedded::sprites::{APPLE, APPLE_SIZE};
+use snake_embedded::traits::{FixedBacklight, SilentAudio};
+
+// Same board as the Pico: 240x135 pixels in 6 px cells, below the status bar
+const CELL_SIZE: u16 = 6;
+const STATUS_BAR_HEIGHT: u16 = 12;
+
+// The apple sprite is drawn in place of the food cell, so it has to fit one
+const _: () = assert!(APPLE_SIZE == CELL_SIZE - 1);
+
+fn main() {
+    let window = simulator::new_window();
+    let display = SimDisplay::new(window.clone());
+    let input = SimInput::new(window);
+    let renderer = GridRenderer::new(display, CELL_SIZE)
+        .with_status_bar(STATUS_BAR_HEIGHT)
+        .with_food_sprite(APPLE);
+    let (grid_width, grid_height) = renderer
+        .grid_size()
+        .expect("cell size doesn't fit the window");
+
+    let mut engine = GameEngine::new(input, SimPlatform::new(), renderer, grid_width, grid_height)
+        .with_audio(SilentAudio)
+        .with_storage(FileStorage::new("snake_highscore.txt"))
+        .with_backlight(FixedBacklight)
+        .with_event_handler(|event| println!("{:?}", event));
+
+    // Match the Pico's starting speed
+    engine.set_frame_rate(3);
+    engine.set_attract_mode(Some(10_000));
+    engine.set_boost(true);
+    engine.set_screensaver(Some(30_000));
+
+    // `snake_sim <n>` plays in maze n of `levels::LEVELS`, from 0
+    let level = std::env::args()
+        .nth(1)
+        .and_then(|arg| arg.parse::<usize>().ok());
+    if let Some(level) = level.and_then(|index| LEVELS.get(index)) {
+        if let Err(error) = engine.load_level(level) {
+            eprintln!("Level {} left out: {:?}", level.name, error);
+        }
+    }
+    engine.resume_saved_round();
+
+    // The simulator's input and delays never suspend, so a simple blocking
+    // executor is enough to drive the async engine
+    embassy_futures::block_on(engine.run()).ok();
+}
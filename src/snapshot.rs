@@ -0,0 +1,415 @@
+// Game snapshots: a round in progress in a fixed byte layout, to keep in flash
+// and pick the round up again after a reset or power loss
+//
+// Layout, little endian, always `GameSnapshot::SIZE` bytes whatever the length:
+//   0       format version (`SNAPSHOT_VERSION`)
+//   1       direction: 0 up, 1 down, 2 left, 3 right
+//   2..4    score
+//   4..6    food eaten
+//   6..10   RNG state
+//   10..14  moves this round
+//   14..16  moves since the last food
+//   16      combo
+//   17      sudden death rings closed
+//   18      flags: 1 player one alive, 2 bonus, 4 poison, 8 player two
+//   19..23  bonus (x, y) and ticks left
+//   23..27  poison (x, y) and ticks left
+//   27      player two's direction, 28 alive, 29..31 score
+//   31      food count, then `MAX_FOOD` (x, y) pairs
+//   ..      snake length (u16), player two's length (u16)
+//   ..      `CAP` (x, y) pairs for the snake, head first, then `CAP` for player two
+//   ..      difficulty: 0 easy, 1 normal, 2 hard, then the time played in ms (u32)
+//   ..      CRC-16 (CCITT) of every byte before it
+// Unused pairs are zero. New fields go before the CRC under a new version; a
+// save from another version, or one whose CRC doesn't match, is refused rather
+// than misread.
+
+use heapless::Vec;
+
+use crate::game::{Difficulty, Direction, Position, MAX_FOOD, SNAKE_CAPACITY};
+
+pub const SNAPSHOT_VERSION: u8 = 4;
+
+const FOODS_AT: usize = 32;
+const LENGTHS_AT: usize = FOODS_AT + 2 * MAX_FOOD;
+
+// Bytes before the snake cells
+const HEADER_SIZE: usize = LENGTHS_AT + 4;
+
+const FIRST_ALIVE: u8 = 1;
+const HAS_BONUS: u8 = 2;
+const HAS_POISON: u8 = 4;
+const HAS_SECOND: u8 = 8;
+
+// Why a snapshot can't be encoded, decoded or restored
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub enum SnapshotError {
+    TooShort,   // The buffer is shorter than `GameSnapshot::SIZE`
+    Version,    // Saved by another format version
+    Checksum,   // The CRC doesn't match, the bytes were corrupted
+    BadData,    // Holds values no game can have (direction, counts)
+    DoesNotFit, // Off this game's board, an empty snake, too many rings or combo
+}
+
+// Player two as `Game::snapshot` keeps it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnakeSnapshot<const CAP: usize = SNAKE_CAPACITY> {
+    pub body: Vec<Position, CAP>,
+    pub direction: Direction,
+    pub alive: bool,
+    pub score: u16,
+}
+
+// What `Game::snapshot` keeps of a round, enough to carry on playing it
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameSnapshot<const CAP: usize = SNAKE_CAPACITY> {
+    pub snake: Vec<Position, CAP>,
+    pub foods: Vec<Position, MAX_FOOD>,
+    pub direction: Direction,
+    pub score: u16,
+    pub food_eaten: u16,
+    pub rng_state: u32,
+    pub move_count: u32,
+    pub ticks_since_food: u16,
+    pub combo: u8,
+    pub border_inset: u8,
+    pub first_alive: bool,
+    pub bonus: Option<(Position, u16)>, // Cell and ticks left
+    pub poison: Option<(Position, u16)>,
+    pub second: Option<SnakeSnapshot<CAP>>,
+    // The engine's part: `Game::snapshot` leaves Normal and 0 for it to fill in
+    pub difficulty: Difficulty,
+    pub played_ms: u32, // Time played so far, pauses left out
+}
+
+impl<const CAP: usize> GameSnapshot<CAP> {
+    // Encoded size in bytes, the same for any snake length
+    pub const SIZE: usize = Self::ENGINE_AT + 5 + 2;
+    const ENGINE_AT: usize = HEADER_SIZE + 4 * CAP;
+    const CRC_AT: usize = Self::SIZE - 2;
+
+    // Write the snapshot to the first `SIZE` bytes of `out`
+    pub fn encode(&self, out: &mut [u8]) -> Result<(), SnapshotError> {
+        let out = out.get_mut(..Self::SIZE).ok_or(SnapshotError::TooShort)?;
+        out.fill(0);
+
+        out[0] = SNAPSHOT_VERSION;
+        out[1] = direction_byte(self.direction);
+        out[2..4].copy_from_slice(&self.score.to_le_bytes());
+        out[4..6].copy_from_slice(&self.food_eaten.to_le_bytes());
+        out[6..10].copy_from_slice(&self.rng_state.to_le_bytes());
+        out[10..14].copy_from_slice(&self.move_count.to_le_bytes());
+        out[14..16].copy_from_slice(&self.ticks_since_food.to_le_bytes());
+        out[16] = self.combo;
+        out[17] = self.border_inset;
+
+        let mut flags = 0;
+        if self.first_alive {
+            flags |= FIRST_ALIVE;
+        }
+        if let Some(bonus) = self.bonus {
+            flags |= HAS_BONUS;
+            write_timed(&mut out[19..23], bonus);
+        }
+        if let Some(poison) = self.poison {
+            flags |= HAS_POISON;
+            write_timed(&mut out[23..27], poison);
+        }
+        if let Some(second) = &self.second {
+            flags |= HAS_SECOND;
+            out[27] = direction_byte(second.direction);
+            out[28] = second.alive as u8;
+            out[29..31].copy_from_slice(&second.score.to_le_bytes());
+        }
+        out[18] = flags;
+
+        out[31] = self.foods.len() as u8;
+        write_cells(&mut out[FOODS_AT..], &self.foods);
+        let second_len = self.second.as_ref().map_or(0, |second| second.body.len());
+        out[LENGTHS_AT..LENGTHS_AT + 2].copy_from_slice(&(self.snake.len() as u16).to_le_bytes());
+        out[LENGTHS_AT + 2..HEADER_SIZE].copy_from_slice(&(second_len as u16).to_le_bytes());
+        write_cells(&mut out[HEADER_SIZE..], &self.snake);
+        if let Some(second) = &self.second {
+            write_cells(&mut out[HEADER_SIZE + 2 * CAP..], &second.body);
+        }
+        out[Self::ENGINE_AT] = match self.difficulty {
+            Difficulty::Easy => 0,
+            Difficulty::Normal => 1,
+            Difficulty::Hard => 2,
+        };
+        out[Self::ENGINE_AT + 1..Self::CRC_AT].copy_from_slice(&self.played_ms.to_le_bytes());
+        let crc = crc16(&out[..Self::CRC_AT]);
+        out[Self::CRC_AT..].copy_from_slice(&crc.to_le_bytes());
+        Ok(())
+    }
+
+    // Read a snapshot written by `encode`
+    pub fn decode(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let bytes = bytes.get(..Self::SIZE).ok_or(SnapshotError::TooShort)?;
+        if bytes[0] != SNAPSHOT_VERSION {
+            return Err(SnapshotError::Version);
+        }
+        let crc = u16::from_le_bytes([bytes[Self::CRC_AT], bytes[Self::CRC_AT + 1]]);
+        if crc != crc16(&bytes[..Self::CRC_AT]) {
+            return Err(SnapshotError::Checksum);
+        }
+
+        let u16_at = |at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+        let food_len = bytes[31] as usize;
+        let snake_len = u16_at(LENGTHS_AT) as usize;
+        let second_len = u16_at(LENGTHS_AT + 2) as usize;
+        if food_len > MAX_FOOD || snake_len > CAP || second_len > CAP {
+            return Err(SnapshotError::BadData);
+        }
+
+        let difficulty = match bytes[Self::ENGINE_AT] {
+            0 => Difficulty::Easy,
+            1 => Difficulty::Normal,
+            2 => Difficulty::Hard,
+            _ => return Err(SnapshotError::BadData),
+        };
+        let played_at = Self::ENGINE_AT + 1;
+
+        let flags = bytes[18];
+        let second = if flags & HAS_SECOND != 0 {
+            Some(SnakeSnapshot {
+                body: read_cells(&bytes[HEADER_SIZE + 2 * CAP..], second_len),
+                direction: read_direction(bytes[27])?,
+                alive: bytes[28] != 0,
+                score: u16_at(29),
+            })
+        } else {
+            None
+        };
+
+        Ok(Self {
+            snake: read_cells(&bytes[HEADER_SIZE..], snake_len),
+            foods: read_cells(&bytes[FOODS_AT..], food_len),
+            direction: read_direction(bytes[1])?,
+            score: u16_at(2),
+            food_eaten: u16_at(4),
+            rng_state: u32::from_le_bytes([bytes[6], bytes[7], bytes[8], bytes[9]]),
+            move_count: u32::from_le_bytes([bytes[10], bytes[11], bytes[12], bytes[13]]),
+            ticks_since_food: u16_at(14),
+            combo: bytes[16],
+            border_inset: bytes[17],
+            first_alive: flags & FIRST_ALIVE != 0,
+            bonus: (flags & HAS_BONUS != 0).then(|| read_timed(&bytes[19..23])),
+            poison: (flags & HAS_POISON != 0).then(|| read_timed(&bytes[23..27])),
+            second,
+            difficulty,
+            played_ms: u32::from_le_bytes([
+                bytes[played_at],
+                bytes[played_at + 1],
+                bytes[played_at + 2],
+                bytes[played_at + 3],
+            ]),
+        })
+    }
+}
+
+// CRC-16/CCITT-FALSE, bit by bit: a save is only checked once per boot
+fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc = 0xFFFF_u16;
+    for &byte in bytes {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+fn direction_byte(direction: Direction) -> u8 {
+    match direction {
+        Direction::Up => 0,
+        Direction::Down => 1,
+        Direction::Left => 2,
+        Direction::Right => 3,
+    }
+}
+
+fn read_direction(byte: u8) -> Result<Direction, SnapshotError> {
+    match byte {
+        0 => Ok(Direction::Up),
+        1 => Ok(Direction::Down),
+        2 => Ok(Direction::Left),
+        3 => Ok(Direction::Right),
+        _ => Err(SnapshotError::BadData),
+    }
+}
+
+// A bonus or poison cell and its ticks left, 4 bytes
+fn write_timed(out: &mut [u8], (cell, ticks): (Position, u16)) {
+    out[0] = cell.x;
+    out[1] = cell.y;
+    out[2..4].copy_from_slice(&ticks.to_le_bytes());
+}
+
+fn read_timed(bytes: &[u8]) -> (Position, u16) {
+    let ticks = u16::from_le_bytes([bytes[2], bytes[3]]);
+    (Position::new(bytes[0], bytes[1]), ticks)
+}
+
+fn write_cells(out: &mut [u8], cells: &[Position]) {
+    for (pair, cell) in out.chunks_exact_mut(2).zip(cells) {
+        pair[0] = cell.x;
+        pair[1] = cell.y;
+    }
+}
+
+// Callers check `len` against the capacity first
+fn read_cells<const N: usize>(bytes: &[u8], len: usize) -> Vec<Position, N> {
+    bytes
+        .chunks_exact(2)
+        .take(len)
+        .map(|pair| Position::new(pair[0], pair[1]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Game, GameState};
+
+    // Two players, a combo going, bonus and poison out and a ring closed
+    fn busy_round() -> Game {
+        let mut game: Game = Game::new(20, 12);
+        game.set_two_player(true);
+        game.set_sudden_death(3);
+        game.start();
+        game.set_food(Position::new(11, 3));
+        game.update();
+        game.set_bonus_food(Position::new(15, 5));
+        game.set_poison_food(Position::new(5, 5));
+        game.update();
+        game.update();
+        assert_eq!(game.border_inset(), 1);
+        assert_eq!(game.combo(), 1);
+        game
+    }
+
+    fn encoded(snapshot: &GameSnapshot) -> std::vec::Vec<u8> {
+        let mut bytes = std::vec![0; GameSnapshot::<SNAKE_CAPACITY>::SIZE];
+        snapshot.encode(&mut bytes).unwrap();
+        bytes
+    }
+
+    // `bytes` with a CRC that matches again after an edit
+    fn resealed(mut bytes: std::vec::Vec<u8>) -> std::vec::Vec<u8> {
+        let at = bytes.len() - 2;
+        let crc = crc16(&bytes[..at]);
+        bytes[at..].copy_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn a_round_survives_the_byte_layout() {
+        let mut snapshot = busy_round().snapshot();
+        snapshot.difficulty = Difficulty::Hard;
+        snapshot.played_ms = 123_456;
+        assert!(snapshot.bonus.is_some() && snapshot.poison.is_some());
+        assert!(snapshot.second.is_some());
+        assert_eq!(snapshot.move_count, 3);
+
+        let decoded = GameSnapshot::decode(&encoded(&snapshot)).unwrap();
+        assert_eq!(decoded, snapshot);
+    }
+
+    #[test]
+    fn a_restored_round_plays_on_the_same() {
+        let mut original = busy_round();
+        // The settings come from the game restored into
+        let mut restored: Game = Game::new(20, 12);
+        restored.set_sudden_death(3);
+        let snapshot = GameSnapshot::decode(&encoded(&original.snapshot())).unwrap();
+        restored.restore(snapshot).unwrap();
+        assert_eq!(restored.state, GameState::Paused);
+        assert_eq!(restored.snapshot(), original.snapshot());
+
+        restored.toggle_pause();
+        for _ in 0..5 {
+            original.update();
+            restored.update();
+        }
+        assert_eq!(restored.snapshot(), original.snapshot());
+        assert_eq!(restored.state, original.state);
+    }
+
+    #[test]
+    fn bad_bytes_are_refused() {
+        let snapshot = busy_round().snapshot();
+        let bytes = encoded(&snapshot);
+        assert_eq!(
+            GameSnapshot::<SNAKE_CAPACITY>::decode(&bytes[..10]),
+            Err(SnapshotError::TooShort)
+        );
+
+        // Erased flash, and a save from the first format
+        let erased = std::vec![0xFF; bytes.len()];
+        assert_eq!(
+            GameSnapshot::<SNAKE_CAPACITY>::decode(&erased),
+            Err(SnapshotError::Version)
+        );
+        let mut old = bytes.clone();
+        old[0] = 1;
+        assert_eq!(
+            GameSnapshot::<SNAKE_CAPACITY>::decode(&old),
+            Err(SnapshotError::Version)
+        );
+
+        // A flipped bit anywhere fails the CRC
+        let mut flipped = bytes.clone();
+        flipped[HEADER_SIZE + 1] ^= 0x10;
+        assert_eq!(
+            GameSnapshot::<SNAKE_CAPACITY>::decode(&flipped),
+            Err(SnapshotError::Checksum)
+        );
+
+        let mut turned = bytes.clone();
+        turned[1] = 4;
+        assert_eq!(
+            GameSnapshot::<SNAKE_CAPACITY>::decode(&resealed(turned)),
+            Err(SnapshotError::BadData)
+        );
+        let mut crowded = bytes.clone();
+        crowded[31] = MAX_FOOD as u8 + 1;
+        assert_eq!(
+            GameSnapshot::<SNAKE_CAPACITY>::decode(&resealed(crowded)),
+            Err(SnapshotError::BadData)
+        );
+
+        // Rings past the sudden death minimum, or a combo past its cap, decode
+        // but no game can take them
+        let mut game: Game = Game::new(20, 12);
+        let mut closed = bytes.clone();
+        closed[17] = 200;
+        let closed = GameSnapshot::decode(&resealed(closed)).unwrap();
+        assert_eq!(game.restore(closed), Err(SnapshotError::DoesNotFit));
+        let mut combo = bytes;
+        combo[16] = 200;
+        let combo = GameSnapshot::decode(&resealed(combo)).unwrap();
+        assert_eq!(game.restore(combo), Err(SnapshotError::DoesNotFit));
+        assert_eq!(game.state, GameState::Starting);
+    }
+
+    #[test]
+    fn a_round_from_a_bigger_board_is_refused() {
+        let mut big: Game = Game::new(30, 20);
+        big.start();
+        big.set_poison_food(Position::new(25, 15));
+        let mut game: Game = Game::new(20, 12);
+        let before = game.snapshot();
+        assert_eq!(game.restore(big.snapshot()), Err(SnapshotError::DoesNotFit));
+        assert_eq!(game.snapshot(), before);
+        assert_eq!(game.state, GameState::Starting);
+
+        let mut small = big.snapshot();
+        small.snake.clear();
+        assert_eq!(game.restore(small), Err(SnapshotError::DoesNotFit));
+    }
+}
@@ -0,0 +1,24 @@
+// Built-in sprites, raw RGB565 pixels (big endian, row by row) kept in flash
+//
+// Sprites fill the drawn part of a cell, the cell size less the 1 px grid gap,
+// so the renderer can erase one with the same rectangle as a plain cell.
+// Binaries check their cell size against these with a const assert.
+
+use embedded_graphics::image::ImageRaw;
+use embedded_graphics::pixelcolor::Rgb565;
+
+/// Width and height of the apple, for 6 px cells
+pub const APPLE_SIZE: u16 = 5;
+
+// Red with a light highlight and a green stem, black around it
+#[rustfmt::skip]
+const APPLE_DATA: [u8; 2 * (APPLE_SIZE * APPLE_SIZE) as usize] = [
+    0x00, 0x00, 0x00, 0x00, 0x26, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0xF8, 0x00, 0xF8, 0x00, 0xF8, 0x00, 0x00, 0x00,
+    0xF8, 0x00, 0xF8, 0x00, 0xFD, 0xF7, 0xF8, 0x00, 0xF8, 0x00,
+    0xF8, 0x00, 0xF8, 0x00, 0xF8, 0x00, 0xF8, 0x00, 0xF8, 0x00,
+    0x00, 0x00, 0xF8, 0x00, 0xF8, 0x00, 0xF8, 0x00, 0x00, 0x00,
+];
+
+/// Drawn on the regular food in place of a red cell
+pub const APPLE: ImageRaw<'static, Rgb565> = ImageRaw::new(&APPLE_DATA, APPLE_SIZE as u32);
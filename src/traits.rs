@@ -1,7 +1,16 @@
-use crate::game::{Direction, GameState, Position};
+use embedded_graphics::geometry::{OriginDimensions, Point};
+use embedded_graphics::image::{GetPixel, ImageRaw};
+use embedded_graphics::mono_font::ascii::{FONT_10X20, FONT_6X10};
+use embedded_graphics::mono_font::MonoFont;
+use embedded_graphics::pixelcolor::{Rgb565, RgbColor};
+use heapless::Vec;
+
+use crate::game::{Difficulty, Direction, Game, Position};
+use crate::scene::{CHAR_HEIGHT, CHAR_WIDTH};
+use crate::snapshot::GameSnapshot;
 
 /// Color representation that can be implemented for different display types
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -17,6 +26,193 @@ impl Color {
     };
     pub const GREEN: Color = Color { r: 0, g: 255, b: 0 };
     pub const RED: Color = Color { r: 255, g: 0, b: 0 };
+    pub const BLUE: Color = Color { r: 0, g: 0, b: 255 };
+    pub const YELLOW: Color = Color {
+        r: 255,
+        g: 255,
+        b: 0,
+    };
+    pub const CYAN: Color = Color {
+        r: 0,
+        g: 255,
+        b: 255,
+    };
+    pub const MAGENTA: Color = Color {
+        r: 255,
+        g: 0,
+        b: 255,
+    };
+    pub const ORANGE: Color = Color {
+        r: 255,
+        g: 165,
+        b: 0,
+    };
+
+    /// Expand a 5-6-5 color to 8 bits per channel. The low bits are filled by
+    /// repeating the high ones, so full intensity comes back as 255.
+    pub fn from_rgb565(color: Rgb565) -> Color {
+        Color {
+            r: (color.r() << 3) | (color.r() >> 2),
+            g: (color.g() << 2) | (color.g() >> 4),
+            b: (color.b() << 3) | (color.b() >> 2),
+        }
+    }
+
+    /// Perceived brightness from 0 (black) to 255 (white), Rec. 601 weights
+    pub fn luminance(&self) -> u8 {
+        ((299 * self.r as u32 + 587 * self.g as u32 + 114 * self.b as u32) / 1000) as u8
+    }
+
+    /// Blend from `a` (t = 0) to `b` (t = 1), `t` is clamped to that range
+    pub fn lerp(a: Color, b: Color, t: f32) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * t + 0.5) as u8;
+        Color {
+            r: mix(a.r, b.r),
+            g: mix(a.g, b.g),
+            b: mix(a.b, b.b),
+        }
+    }
+}
+
+// Convert our generic Color to Rgb565, used by the ST7789 and the simulator.
+// Lossy: the low 3 bits of red and blue and 2 bits of green are dropped, so a
+// round trip through `Color::from_rgb565` lands in the same 8 (or 4) wide bucket.
+impl From<Color> for Rgb565 {
+    fn from(color: Color) -> Self {
+        Rgb565::new(color.r >> 3, color.g >> 2, color.b >> 3)
+    }
+}
+
+impl From<Rgb565> for Color {
+    fn from(color: Rgb565) -> Self {
+        Color::from_rgb565(color)
+    }
+}
+
+/// How a color shows on a one bit panel, picked by brightness: bright colors
+/// fill the cell, mid tones get every other pixel and dark ones only the edge
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dither {
+    Solid,
+    Checkerboard,
+    Outline,
+    Empty,
+}
+
+impl Dither {
+    pub fn for_color(color: Color) -> Self {
+        match color.luminance() {
+            200.. => Dither::Solid,
+            100..=199 => Dither::Checkerboard,
+            1..=99 => Dither::Outline,
+            0 => Dither::Empty,
+        }
+    }
+}
+
+/// Fonts text can be drawn in, embedded-graphics ASCII mono fonts
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Font {
+    /// `FONT_6X10`, for all the regular text
+    #[default]
+    Small,
+    /// `FONT_10X20`, for a number that should stand out
+    Large,
+}
+
+impl Font {
+    pub fn mono_font(self) -> &'static MonoFont<'static> {
+        match self {
+            Font::Small => &FONT_6X10,
+            Font::Large => &FONT_10X20,
+        }
+    }
+
+    /// Advance of one character in pixels
+    pub fn char_width(self) -> u16 {
+        match self {
+            Font::Small => CHAR_WIDTH,
+            Font::Large => 10,
+        }
+    }
+
+    pub fn char_height(self) -> u16 {
+        match self {
+            Font::Small => CHAR_HEIGHT,
+            Font::Large => 20,
+        }
+    }
+
+    /// Width of `text` in pixels, one advance per byte
+    pub fn text_width(self, text: &str) -> u16 {
+        text.len() as u16 * self.char_width()
+    }
+}
+
+/// Colors used to draw the game, shared by the renderers
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Theme {
+    pub snake: Color,
+    pub snake_head: Color,
+    pub second_snake: Color, // Player two in two player rounds
+    pub food: Color,
+    pub bonus_food: Color,
+    pub poison: Color, // Drawn as an outline to tell it from food
+    pub portal: Color, // Drawn as a small square in the middle of the cell
+    pub border: Color,
+    pub border_width: u16, // Pixels, 0 leaves the border out
+    pub background: Color,
+    pub text: Color,
+    pub score_font: Font, // Final score on the game over screen
+}
+
+impl Theme {
+    /// Green snake and red food on black
+    pub fn classic() -> Self {
+        Self {
+            snake: Color::GREEN,
+            snake_head: Color {
+                r: 170,
+                g: 255,
+                b: 120,
+            },
+            second_snake: Color::CYAN,
+            food: Color::RED,
+            bonus_food: Color::YELLOW,
+            poison: Color::RED,
+            portal: Color::MAGENTA,
+            border: Color::WHITE,
+            border_width: 1,
+            background: Color::BLACK,
+            text: Color::WHITE,
+            score_font: Font::Small,
+        }
+    }
+
+    /// Everything lit on black, for monochrome panels
+    pub fn mono() -> Self {
+        Self {
+            snake: Color::WHITE,
+            snake_head: Color::WHITE,
+            second_snake: Color::WHITE,
+            food: Color::WHITE,
+            bonus_food: Color::WHITE,
+            poison: Color::WHITE,
+            portal: Color::WHITE,
+            border: Color::WHITE,
+            border_width: 1,
+            background: Color::BLACK,
+            text: Color::WHITE,
+            score_font: Font::Small,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::classic()
+    }
 }
 
 /// Input events from various input sources
@@ -24,17 +220,48 @@ impl Color {
 pub enum InputEvent {
     Direction(Direction),
     ButtonA,
-    #[allow(dead_code)]
     ButtonB,
+    /// Button A held for about a second
+    ButtonALong,
+    /// Buttons A and B pressed together
+    ButtonAB,
     None,
 }
 
+/// Everything read in one poll, e.g. a direction and a button pushed together
+pub type InputEvents = Vec<InputEvent, 3>;
+
+/// Direction of an analog stick from its two ADC readings and the reading at
+/// rest, `None` while both axes are within `deadzone` of `center`. The axis
+/// pushed the furthest wins, so a mostly vertical diagonal reads as up/down.
+/// An exact tie goes to left/right.
+pub fn joystick_to_direction(x: u16, y: u16, center: u16, deadzone: u16) -> Option<Direction> {
+    let dx = x as i32 - center as i32;
+    let dy = y as i32 - center as i32;
+    if dx.abs().max(dy.abs()) <= deadzone as i32 {
+        return None;
+    }
+
+    if dx.abs() >= dy.abs() {
+        Some(if dx < 0 {
+            Direction::Left
+        } else {
+            Direction::Right
+        })
+    } else {
+        Some(if dy < 0 {
+            Direction::Up
+        } else {
+            Direction::Down
+        })
+    }
+}
+
 /// Abstraction for different display technologies
 pub trait GameDisplay {
     type Error;
 
     /// Get display dimensions in pixels
-    #[allow(dead_code)]
     fn dimensions(&self) -> (u16, u16);
 
     /// Clear the entire display
@@ -53,39 +280,638 @@ pub trait GameDisplay {
     /// Draw text at specified position
     fn draw_text(&mut self, text: &str, x: u16, y: u16, color: Color) -> Result<(), Self::Error>;
 
+    /// Draw text in `font`. The default only has the small font and draws
+    /// any text with `draw_text`.
+    fn draw_text_in(
+        &mut self,
+        text: &str,
+        x: u16,
+        y: u16,
+        color: Color,
+        font: Font,
+    ) -> Result<(), Self::Error> {
+        let _ = font;
+        self.draw_text(text, x, y, color)
+    }
+
+    /// Draw text centered across the display, its top at `y`. Text wider than
+    /// the display starts at the left edge.
+    fn draw_text_centered(&mut self, text: &str, y: u16, color: Color) -> Result<(), Self::Error> {
+        self.draw_text_centered_in(text, y, color, Font::Small)
+    }
+
+    /// `draw_text_centered` in `font`, centered on that font's advance
+    fn draw_text_centered_in(
+        &mut self,
+        text: &str,
+        y: u16,
+        color: Color,
+        font: Font,
+    ) -> Result<(), Self::Error> {
+        let (width, _) = self.dimensions();
+        let x = width.saturating_sub(font.text_width(text)) / 2;
+        self.draw_text_in(text, x, y, color, font)
+    }
+
+    /// Draw a single pixel
+    fn draw_pixel(&mut self, x: u16, y: u16, color: Color) -> Result<(), Self::Error> {
+        self.draw_rect(x, y, 1, 1, color)
+    }
+
+    /// Draw a 1 px line between two points (inclusive)
+    fn draw_line(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16,
+        color: Color,
+    ) -> Result<(), Self::Error> {
+        // Bresenham, one pixel at a time
+        let (mut x, mut y) = (x0 as i32, y0 as i32);
+        let (x1, y1) = (x1 as i32, y1 as i32);
+        let dx = (x1 - x).abs();
+        let dy = -(y1 - y).abs();
+        let step_x = if x < x1 { 1 } else { -1 };
+        let step_y = if y < y1 { 1 } else { -1 };
+        let mut error = dx + dy;
+
+        loop {
+            self.draw_pixel(x as u16, y as u16, color)?;
+            if x == x1 && y == y1 {
+                return Ok(());
+            }
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    /// Draw a filled circle of radius `r` centered on (cx, cy)
+    fn draw_circle(&mut self, cx: u16, cy: u16, r: u16, color: Color) -> Result<(), Self::Error> {
+        // One horizontal span per row, clipped at the top/left edges
+        let (cx, cy, r) = (cx as i32, cy as i32, r as i32);
+        for dy in -r..=r {
+            let mut half = r;
+            while half * half + dy * dy > r * r {
+                half -= 1;
+            }
+            let y = cy + dy;
+            let x = (cx - half).max(0);
+            if y >= 0 {
+                let width = cx + half - x + 1;
+                self.draw_rect(x as u16, y as u16, width as u16, 1, color)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw an RGB565 image with its top-left corner at (x, y). The default
+    /// goes pixel by pixel, displays that can blit should override it. Pixels
+    /// past the end of the coordinate range are skipped.
+    fn draw_image(&mut self, x: u16, y: u16, image: &ImageRaw<Rgb565>) -> Result<(), Self::Error> {
+        let size = image.size();
+        let offset = |origin: u16, d: u32| u16::try_from(d).ok()?.checked_add(origin);
+        for dy in 0..size.height {
+            for dx in 0..size.width {
+                let (Some(px), Some(py)) = (offset(x, dx), offset(y, dy)) else {
+                    continue;
+                };
+                if let Some(color) = image.pixel(Point::new(dx as i32, dy as i32)) {
+                    self.draw_pixel(px, py, color.into())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Update/flush the display (for buffered displays)
     fn update(&mut self) -> Result<(), Self::Error>;
 }
 
 /// Abstraction for different input methods
+// Only implemented and awaited within this project, no Send bound needed
+#[allow(async_fn_in_trait)]
 pub trait GameInput {
     type Error;
 
-    /// Read the current input state
-    async fn read_input(&mut self) -> Result<InputEvent, Self::Error>;
+    /// Return the latest sampled input state right away, empty when nothing
+    /// happened. The engine polls this every frame and handles every event.
+    fn poll_input(&mut self) -> Result<InputEvents, Self::Error>;
+
+    /// Read the current input state. Event-driven sources can wait for the
+    /// next events here, the default just polls.
+    async fn read_input(&mut self) -> Result<InputEvents, Self::Error> {
+        self.poll_input()
+    }
+
+    /// Wait for a new press, so the engine can sleep on static screens and
+    /// still wake at once. True when something was pressed; the default returns
+    /// false right away for sources that can't wait, which keep being polled
+    /// every frame.
+    async fn wait_for_input(&mut self) -> bool {
+        false
+    }
+
+    /// Whether button A is down right now, for actions that last as long as
+    /// it's held (the boost). Sources that can't tell keep the default.
+    fn button_a_held(&self) -> bool {
+        false
+    }
 }
 
 /// Abstraction for platform-specific operations
+#[allow(async_fn_in_trait)]
 pub trait GamePlatform {
     /// Delay for specified milliseconds
     async fn delay_ms(&self, ms: u32);
 
     /// Get current time in milliseconds (for game timing)
     fn current_time_ms(&self) -> u32;
+
+    /// Delay until `current_time_ms` reaches `deadline_ms`, returns at once when
+    /// it already has. Platforms with a timer queue can wait on the deadline
+    /// itself instead of a duration.
+    async fn delay_until_ms(&self, deadline_ms: u32) {
+        let left = deadline_ms.wrapping_sub(self.current_time_ms());
+        if (left as i32) > 0 {
+            self.delay_ms(left).await;
+        }
+    }
+
+    /// Battery charge in percent, 0 to 100. Read every few seconds when the
+    /// engine's battery check is on; USB or mains powered devices report 100.
+    fn read_battery_percent(&mut self) -> u8 {
+        100
+    }
+
+    /// Keep a hardware watchdog from resetting the device, called at the top
+    /// of every frame. Platforms without one keep the default.
+    fn feed_watchdog(&self) {}
+}
+
+/// Abstraction for sound output (buzzer, speaker, ...)
+pub trait GameAudio {
+    type Error;
+
+    /// Start a tone at `freq_hz` lasting roughly `dur_ms` milliseconds
+    fn beep(&mut self, freq_hz: u16, dur_ms: u16) -> Result<(), Self::Error>;
+
+    /// Called once per frame so non-blocking implementations can stop finished tones
+    fn update(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// No-op audio for hardware without a buzzer
+pub struct SilentAudio;
+
+impl GameAudio for SilentAudio {
+    type Error = ();
+
+    fn beep(&mut self, _freq_hz: u16, _dur_ms: u16) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Abstraction for display backlight brightness control
+pub trait GameBacklight {
+    /// Set the brightness in percent, values above 100 are clamped and 0 turns it off
+    fn set_brightness(&mut self, percent: u8);
+}
+
+/// No-op backlight for displays without brightness control
+pub struct FixedBacklight;
+
+impl GameBacklight for FixedBacklight {
+    fn set_brightness(&mut self, _percent: u8) {}
+}
+
+/// Number of scores kept on the leaderboard
+pub const LEADERBOARD_SIZE: usize = 5;
+
+/// One leaderboard line: a score and the player's initials
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreEntry {
+    pub score: u16,
+    pub initials: [u8; 3], // ASCII capitals
+}
+
+impl ScoreEntry {
+    /// Initials of a score nobody has signed yet
+    pub const NO_INITIALS: [u8; 3] = *b"---";
+
+    pub fn new(score: u16) -> Self {
+        Self {
+            score,
+            initials: Self::NO_INITIALS,
+        }
+    }
+
+    /// Initials as text, bytes that aren't capitals read back as `-`
+    pub fn initials(&self) -> [char; 3] {
+        self.initials.map(|byte| match byte {
+            b'A'..=b'Z' => byte as char,
+            _ => '-',
+        })
+    }
+}
+
+/// How a finished round went, shown on the end screen
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct GameStats {
+    pub food_eaten: u16,
+    pub moves: u32,         // Steps the snake took
+    pub elapsed_ms: u32,    // Time played, pauses left out
+    pub longest_streak: u8, // Best combo reached
+}
+
+impl GameStats {
+    /// Foods eaten per minute of play, 0 for a round that took no time
+    pub fn apples_per_minute(&self) -> u32 {
+        if self.elapsed_ms == 0 {
+            return 0;
+        }
+        (self.food_eaten as u64 * 60_000 / self.elapsed_ms as u64) as u32
+    }
+}
+
+/// Best scores, highest first
+pub type Leaderboard = Vec<ScoreEntry, LEADERBOARD_SIZE>;
+
+/// Put `entry` in its place on the leaderboard, dropping the lowest score when
+/// it is full. Returns the new entry's index, `None` when the score didn't make
+/// it. Scores of 0 are left out. Ties go below the scores already there.
+pub fn insert_score(scores: &mut Leaderboard, entry: ScoreEntry) -> Option<usize> {
+    let score = entry.score;
+    if score == 0 {
+        return None;
+    }
+    let rank = scores.iter().position(|shown| shown.score < score);
+    let rank = match rank {
+        Some(rank) => rank,
+        None if !scores.is_full() => scores.len(),
+        None => return None,
+    };
+    if scores.is_full() {
+        scores.pop();
+    }
+    scores.insert(rank, entry).ok()?;
+    Some(rank)
+}
+
+/// Abstraction for persistent storage (flash, EEPROM, files, ...)
+pub trait GameStorage {
+    type Error;
+
+    /// Load the saved high score, 0 when nothing has been saved yet
+    fn load_high_score(&mut self) -> Result<u16, Self::Error>;
+
+    /// Persist a new high score
+    fn save_high_score(&mut self, score: u16) -> Result<(), Self::Error>;
+
+    /// Load the leaderboard, best first. Empty when nothing has been saved yet.
+    fn load_scores(&mut self) -> Result<Leaderboard, Self::Error>;
+
+    /// Persist the whole leaderboard, best first
+    fn save_scores(&mut self, scores: &Leaderboard) -> Result<(), Self::Error>;
+
+    /// Load the mute setting, sound on (false) when nothing has been saved yet
+    fn load_muted(&mut self) -> Result<bool, Self::Error>;
+
+    /// Persist the mute setting
+    fn save_muted(&mut self, muted: bool) -> Result<(), Self::Error>;
+
+    /// Load the round saved by `save_snapshot`, `None` when there is none
+    fn load_snapshot(&mut self) -> Result<Option<GameSnapshot>, Self::Error>;
+
+    /// Persist a round in progress, `None` clears the saved one
+    fn save_snapshot(&mut self, snapshot: Option<&GameSnapshot>) -> Result<(), Self::Error>;
 }
 
 /// Complete game renderer that handles the visual aspects
 pub trait GameRenderer {
     type Error;
 
-    /// Render the complete game state
-    fn render_game(
+    /// Render the complete game state. Called every frame, so renderers
+    /// can animate and only redraw what changed.
+    fn render_game(&mut self, game: &Game) -> Result<(), Self::Error>;
+
+    /// Render a single move of the snake while playing. `prev_snake` and
+    /// `prev_foods` are what the previous frame showed, the new positions are
+    /// in `game`. Defaults to a full `render_game`.
+    fn render_delta(
+        &mut self,
+        game: &Game,
+        prev_snake: &[Position],
+        prev_foods: &[Position],
+    ) -> Result<(), Self::Error> {
+        let _ = (prev_snake, prev_foods);
+        self.render_game(game)
+    }
+
+    /// Render the start menu, called every frame while waiting to start.
+    /// `t_ms` is the current time, for a blinking prompt.
+    fn render_menu(&mut self, t_ms: u32) -> Result<(), Self::Error> {
+        let _ = t_ms;
+        Ok(())
+    }
+
+    /// Render the difficulty menu with `selected` highlighted
+    fn render_difficulty(&mut self, selected: Difficulty) -> Result<(), Self::Error> {
+        let _ = selected;
+        Ok(())
+    }
+
+    /// Keep the mute setting to show on the pause screen. Called at startup
+    /// and whenever it changes.
+    fn set_muted(&mut self, muted: bool) {
+        let _ = muted;
+    }
+
+    /// Keep the latest battery charge (percent) to show in the status bar.
+    /// Only called when the engine's battery check is on.
+    fn set_battery(&mut self, percent: u8) {
+        let _ = percent;
+    }
+
+    /// Render the pause screen with the current score
+    fn render_pause(&mut self, score: u16, food_eaten: u16) -> Result<(), Self::Error> {
+        let _ = (score, food_eaten);
+        Ok(())
+    }
+
+    /// Render one step of the countdown before a game starts (3, 2, 1)
+    fn render_countdown(&mut self, n: u8) -> Result<(), Self::Error> {
+        let _ = n;
+        Ok(())
+    }
+
+    /// Show the measured frame rate in a corner. Called every frame while the
+    /// FPS overlay is on, so renderers should only repaint when it changed.
+    fn render_fps(&mut self, fps: u16) -> Result<(), Self::Error> {
+        let _ = fps;
+        Ok(())
+    }
+
+    /// Keep the leaderboard to show on the end screens. Called at startup and
+    /// whenever it changes, `latest` is the index of the score just added.
+    fn set_leaderboard(&mut self, scores: &Leaderboard, latest: Option<usize>) {
+        let _ = (scores, latest);
+    }
+
+    /// Ask whether to throw the round away, over the frozen board. Called every
+    /// frame while the question is up, the next `render_game` or `render_delta`
+    /// takes it off again.
+    fn render_confirm_reset(&mut self, game: &Game) -> Result<(), Self::Error> {
+        let _ = game;
+        Ok(())
+    }
+
+    /// Draw a frame of the screensaver that replaces an end screen left alone
+    /// for a while, `t_ms` being the current time. The next `render_game`
+    /// brings the end screen back. The default leaves the screen as it is.
+    fn render_screensaver(&mut self, t_ms: u32) -> Result<(), Self::Error> {
+        let _ = t_ms;
+        Ok(())
+    }
+
+    /// Keep the stats of the round that just ended to show on the end
+    /// screens. Called once per round, as it ends.
+    fn render_stats(&mut self, stats: &GameStats) -> Result<(), Self::Error> {
+        let _ = stats;
+        Ok(())
+    }
+
+    /// Render the initials entry for a new leaderboard `score`. Called every
+    /// frame while the player picks letters, `cursor` is the one being changed.
+    fn render_initials(
         &mut self,
-        snake: &[Position],
-        food: &Position,
         score: u16,
-        state: GameState,
-        grid_width: u8,
-        grid_height: u8,
-    ) -> Result<(), Self::Error>;
+        initials: &[u8; 3],
+        cursor: usize,
+    ) -> Result<(), Self::Error> {
+        let _ = (score, initials, cursor);
+        Ok(())
+    }
+
+    /// Draw the frame around the screen. Called once after each state change,
+    /// on top of whatever that state drew. Renderers without a border keep the
+    /// default.
+    fn render_border(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockDisplay, Shape};
+
+    #[test]
+    fn images_past_the_coordinate_range_are_clipped() {
+        const DATA: [u8; 8] = [0xF8, 0x00, 0x07, 0xE0, 0x00, 0x1F, 0xFF, 0xFF];
+        let image = ImageRaw::<Rgb565>::new(&DATA, 2);
+        let mut display: MockDisplay = MockDisplay::new(240, 135);
+        display.draw_image(u16::MAX, u16::MAX - 1, &image).unwrap();
+
+        let drawn: std::vec::Vec<(u16, u16)> = display
+            .shapes()
+            .iter()
+            .map(|shape| match shape {
+                Shape::Rect { x, y, .. } => (*x, *y),
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect();
+        assert_eq!(drawn, [(u16::MAX, u16::MAX - 1), (u16::MAX, u16::MAX)]);
+    }
+
+    #[test]
+    fn colors_round_trip_through_rgb565() {
+        let named = [
+            Color::BLACK,
+            Color::WHITE,
+            Color::GREEN,
+            Color::RED,
+            Color::BLUE,
+            Color::YELLOW,
+            Color::CYAN,
+            Color::MAGENTA,
+        ];
+        for color in named {
+            assert_eq!(Color::from(Rgb565::from(color)), color);
+        }
+        assert_eq!(Rgb565::from(Color::ORANGE), Rgb565::new(31, 41, 0));
+
+        // Other colors land in the bucket their low bits were dropped from
+        let color = Color {
+            r: 13,
+            g: 130,
+            b: 250,
+        };
+        assert_eq!(
+            Color::from_rgb565(color.into()),
+            Color {
+                r: 8,
+                g: 130,
+                b: 255
+            }
+        );
+    }
+
+    #[test]
+    fn lerp_blends_and_clamps() {
+        let (a, b) = (
+            Color::BLACK,
+            Color {
+                r: 200,
+                g: 100,
+                b: 51,
+            },
+        );
+        assert_eq!(Color::lerp(a, b, 0.0), a);
+        assert_eq!(Color::lerp(a, b, 1.0), b);
+        assert_eq!(
+            Color::lerp(a, b, 0.5),
+            Color {
+                r: 100,
+                g: 50,
+                b: 26
+            }
+        ); // Rounded
+        assert_eq!(
+            Color::lerp(b, a, 0.5),
+            Color {
+                r: 100,
+                g: 50,
+                b: 26
+            }
+        );
+        assert_eq!(Color::lerp(a, b, -1.0), a);
+        assert_eq!(Color::lerp(a, b, 2.0), b);
+    }
+
+    #[test]
+    fn the_oled_tells_head_body_and_food_apart() {
+        let theme = Theme::classic();
+        assert_eq!(Dither::for_color(theme.snake_head), Dither::Solid);
+        assert_eq!(Dither::for_color(theme.snake), Dither::Checkerboard);
+        assert_eq!(Dither::for_color(theme.food), Dither::Outline);
+        assert_eq!(Dither::for_color(theme.background), Dither::Empty);
+
+        // Just off black still shows
+        let dim = Color { r: 0, g: 2, b: 0 };
+        assert_eq!(Dither::for_color(dim), Dither::Outline);
+        assert_eq!(Dither::for_color(Color::WHITE), Dither::Solid);
+    }
+
+    fn leaderboard(scores: &[u16]) -> Leaderboard {
+        scores.iter().map(|&score| ScoreEntry::new(score)).collect()
+    }
+
+    fn scores_of(scores: &Leaderboard) -> std::vec::Vec<u16> {
+        scores.iter().map(|entry| entry.score).collect()
+    }
+
+    #[test]
+    fn scores_go_in_their_place() {
+        let mut scores = leaderboard(&[]);
+        assert_eq!(insert_score(&mut scores, ScoreEntry::new(0)), None);
+        assert_eq!(insert_score(&mut scores, ScoreEntry::new(30)), Some(0));
+        assert_eq!(insert_score(&mut scores, ScoreEntry::new(50)), Some(0));
+        assert_eq!(insert_score(&mut scores, ScoreEntry::new(30)), Some(2)); // Below the tie
+        assert_eq!(scores_of(&scores), [50, 30, 30]);
+
+        // A full board drops its lowest score, or refuses one too low
+        let mut scores = leaderboard(&[90, 70, 50, 30, 10]);
+        assert_eq!(insert_score(&mut scores, ScoreEntry::new(60)), Some(2));
+        assert_eq!(scores_of(&scores), [90, 70, 60, 50, 30]);
+        assert_eq!(insert_score(&mut scores, ScoreEntry::new(30)), None);
+        assert_eq!(scores_of(&scores), [90, 70, 60, 50, 30]);
+    }
+
+    #[test]
+    fn unsigned_initials_read_as_dashes() {
+        let mut entry = ScoreEntry::new(10);
+        assert_eq!(entry.initials(), ['-', '-', '-']);
+        entry.initials = *b"AB\0";
+        assert_eq!(entry.initials(), ['A', 'B', '-']);
+    }
+
+    #[test]
+    fn apples_per_minute_come_from_the_play_time() {
+        let mut stats = GameStats {
+            food_eaten: 5,
+            moves: 200,
+            elapsed_ms: 30_000,
+            longest_streak: 2,
+        };
+        assert_eq!(stats.apples_per_minute(), 10);
+        stats.elapsed_ms = 0;
+        assert_eq!(stats.apples_per_minute(), 0);
+        stats.elapsed_ms = 1;
+        stats.food_eaten = u16::MAX;
+        assert_eq!(stats.apples_per_minute(), u16::MAX as u32 * 60_000);
+    }
+
+    fn text_at(display: &MockDisplay, needle: &str) -> Option<(u16, u16, Font)> {
+        display.shapes().iter().find_map(|shape| match shape {
+            Shape::Text {
+                text, x, y, font, ..
+            } if text.as_str() == needle => Some((*x, *y, *font)),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn fonts_match_their_glyph_sizes() {
+        for font in [Font::Small, Font::Large] {
+            let size = font.mono_font().character_size;
+            assert_eq!(font.char_height() as u32, size.height);
+            let advance = size.width + font.mono_font().character_spacing;
+            assert_eq!(font.char_width() as u32, advance);
+        }
+        assert_eq!(Font::Large.text_width("1234"), 40);
+        assert_eq!(Font::default(), Font::Small);
+    }
+
+    #[test]
+    fn centered_text_is_placed_from_its_width() {
+        let mut display: MockDisplay = MockDisplay::new(240, 135);
+        display
+            .draw_text_centered("GAME OVER", 35, Color::WHITE)
+            .unwrap();
+        let long = "TOO LONG FOR A SMALL ONE";
+        let mut narrow: MockDisplay = MockDisplay::new(120, 60);
+        narrow.draw_text_centered(long, 5, Color::WHITE).unwrap();
+        display
+            .draw_text_centered_in("123", 60, Color::WHITE, Font::Large)
+            .unwrap();
+
+        assert_eq!(text_at(&display, "GAME OVER"), Some((93, 35, Font::Small)));
+        assert_eq!(text_at(&narrow, long), Some((0, 5, Font::Small)));
+        assert_eq!(text_at(&display, "123"), Some((105, 60, Font::Large)));
+    }
+
+    #[test]
+    fn the_joystick_reads_the_axis_pushed_furthest() {
+        let stick = |x, y| joystick_to_direction(x, y, 2048, 1000);
+        assert_eq!(stick(2048, 2048), None);
+        assert_eq!(stick(3048, 1048), None); // At the deadzone edge
+        assert_eq!(stick(0, 2048), Some(Direction::Left));
+        assert_eq!(stick(4095, 2048), Some(Direction::Right));
+        assert_eq!(stick(2048, 0), Some(Direction::Up));
+        assert_eq!(stick(2048, 4095), Some(Direction::Down));
+
+        // Diagonals go to the dominant axis, a tie to left/right
+        assert_eq!(stick(3500, 0), Some(Direction::Up));
+        assert_eq!(stick(4000, 3500), Some(Direction::Right));
+        assert_eq!(stick(0, 0), Some(Direction::Left));
+    }
 }